@@ -0,0 +1,358 @@
+//! Classifiers used to derive Job fields from the scraped title, location and tags. These are
+//! pure functions over stored fields, so existing rows can be reclassified without re-scraping.
+
+/// Returns true if any of the patterns appear as a whole word in the (lowercase) text.
+fn contains_word(text: &str, patterns: &[&str]) -> bool {
+    let words = text
+        .split(|c: char| !c.is_alphanumeric() && c != '-')
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<&str>>();
+    patterns.iter().any(|p| {
+        if p.contains(' ') {
+            text.contains(p)
+        } else {
+            words.contains(p)
+        }
+    })
+}
+
+const LEAD_KEYWORDS: &[&str] = &[
+    "lead",
+    "staff",
+    "principal",
+    "head",
+    "director",
+    "vp",
+    "architect",
+];
+const SENIOR_KEYWORDS: &[&str] = &["senior", "sr", "snr"];
+const JUNIOR_KEYWORDS: &[&str] = &[
+    "junior",
+    "jr",
+    "intern",
+    "graduate",
+    "entry",
+    "trainee",
+    "apprentice",
+];
+const HYBRID_KEYWORDS: &[&str] = &["hybrid"];
+const REMOTE_KEYWORDS: &[&str] = &["remote", "anywhere", "distributed", "wfh"];
+const INTERNSHIP_KEYWORDS: &[&str] = &["intern", "internship"];
+const CONTRACT_KEYWORDS: &[&str] = &["contract", "contractor", "freelance", "freelancer"];
+const PART_TIME_KEYWORDS: &[&str] = &["part-time", "part time"];
+
+/// Programming languages and the words that name them, e.g. "golang" for Go. Languages are
+/// stored by their first name.
+const LANGUAGES: &[&[&str]] = &[
+    &["rust"],
+    &["solidity"],
+    &["go", "golang"],
+    &["typescript", "ts"],
+    &["javascript", "js"],
+    &["python"],
+    &["java"],
+    &["kotlin"],
+    &["swift"],
+    &["scala"],
+    &["haskell"],
+    &["elixir"],
+    &["ruby"],
+    &["cairo"],
+    &["vyper"],
+];
+
+/// Returns the language a word names, e.g. "go" for "Golang", or None if it isn't a language.
+pub fn language(word: &str) -> Option<&'static str> {
+    let word = word.trim().to_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|names| names.contains(&word.as_str()))
+        .map(|names| names[0])
+}
+
+/// Classifies the primary programming language of a job - the first language named in its title,
+/// or failing that the first named in its tags, e.g. "solidity" for a "Solidity Engineer" whose
+/// tags are "rust" and "solidity". None is returned when no language is named.
+pub fn primary_language(title: &str, tags: &[String]) -> Option<String> {
+    let first_in = |text: &str| {
+        text.split(|c: char| !c.is_alphanumeric() && c != '-')
+            .find_map(language)
+    };
+    first_in(title)
+        .or_else(|| tags.iter().find_map(|tag| first_in(tag)))
+        .map(str::to_string)
+}
+
+/// Job categories (departments) and the labels that name them, e.g. "developer relations" for
+/// DevRel. Categories are stored by their first name.
+const CATEGORIES: &[&[&str]] = &[
+    &[
+        "engineering",
+        "software engineering",
+        "software development",
+        "development",
+    ],
+    &["infrastructure", "devops", "sre", "site reliability"],
+    &["research", "cryptography"],
+    &["devrel", "developer relations", "developer advocacy"],
+    &["data", "data science", "analytics"],
+    &["security"],
+    &["design"],
+    &["product", "product management"],
+];
+
+/// Returns the known category a label names, e.g. "devrel" for "Developer Relations".
+fn known_category(label: &str) -> Option<&'static str> {
+    let label = label
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ");
+    CATEGORIES
+        .iter()
+        .find(|names| names.contains(&label.as_str()))
+        .map(|names| names[0])
+}
+
+/// Normalises a category from a listing's category or department markup, e.g. "devrel" for
+/// "Developer Relations". Labels that aren't known categories are kept, lowercased. None is
+/// returned for an empty label.
+pub fn category(label: &str) -> Option<String> {
+    let label = label.trim();
+    match known_category(label) {
+        Some(category) => Some(category.to_string()),
+        None if label.is_empty() => None,
+        None => Some(label.to_lowercase()),
+    }
+}
+
+/// Returns the first tag naming a known category, for sites that label the department among a
+/// job's tags rather than in markup of its own.
+pub fn category_from_tags(tags: &[String]) -> Option<String> {
+    tags.iter()
+        .find_map(|tag| known_category(tag))
+        .map(str::to_string)
+}
+
+/// Normalises a location for matching, whether it came from free text or a meta attribute, e.g.
+/// "San Francisco, CA (US)" becomes "san francisco ca us".
+pub fn normalize_location(location: &str) -> String {
+    location
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Returns true if the location matches any of the regions (e.g. a country or city) as whole words.
+pub fn location_matches(location: &str, regions: &[&str]) -> bool {
+    let location = format!(" {} ", normalize_location(location));
+    regions.iter().any(|region| {
+        let region = normalize_location(region);
+        !region.is_empty() && location.contains(&format!(" {region} "))
+    })
+}
+
+/// Legal-entity suffixes dropped from company names before they're compared.
+const COMPANY_SUFFIXES: &[&str] = &[
+    "inc",
+    "incorporated",
+    "llc",
+    "ltd",
+    "limited",
+    "corp",
+    "corporation",
+    "co",
+    "plc",
+    "gmbh",
+    "ag",
+    "sa",
+];
+
+/// Normalises a company name for matching, e.g. "Coinbase, Inc." becomes "coinbase".
+pub fn normalize_company(company: &str) -> String {
+    let normalized = normalize_location(company);
+    let mut words = normalized.split(' ').collect::<Vec<&str>>();
+    while words.len() > 1 && COMPANY_SUFFIXES.contains(words.last().unwrap()) {
+        words.pop();
+    }
+    words.join(" ")
+}
+
+/// Returns true if the company is one of the companies, compared after normalisation.
+pub fn company_matches(company: &str, companies: &[&str]) -> bool {
+    let company = normalize_company(company);
+    !company.is_empty() && companies.iter().any(|c| normalize_company(c) == company)
+}
+
+/// Classifies seniority from a job title, e.g. "junior", "mid", "senior", "lead".
+pub fn seniority(title: &str) -> String {
+    let title = title.to_lowercase();
+    let seniority = match () {
+        () if contains_word(&title, LEAD_KEYWORDS) => "lead",
+        () if contains_word(&title, SENIOR_KEYWORDS) => "senior",
+        () if contains_word(&title, JUNIOR_KEYWORDS) => "junior",
+        () => "mid",
+    };
+    seniority.to_string()
+}
+
+/// Classifies the work mode from a job's location, title and tags, e.g. "remote", "hybrid",
+/// "onsite". A job is remote if any of them says so (e.g. "Remote (US only)", "New York / Remote",
+/// "Anywhere"), unless it's hybrid. An empty string is returned when there isn't enough
+/// information to decide.
+pub fn work_mode(title: &str, location: &str, tags: &[String]) -> String {
+    let text = format!("{} {} {}", title, location, tags.join(" ")).to_lowercase();
+    let work_mode = match () {
+        () if contains_word(&text, HYBRID_KEYWORDS) => "hybrid",
+        () if contains_word(&text, REMOTE_KEYWORDS) => "remote",
+        () if !location.trim().is_empty() => "onsite",
+        () => "",
+    };
+    work_mode.to_string()
+}
+
+/// Classifies the employment type from a job's title and tags, e.g. "full-time", "contract".
+pub fn employment_type(title: &str, tags: &[String]) -> String {
+    // "smart contract" describes the work rather than the employment type
+    let text = format!("{} {}", title, tags.join(" "))
+        .to_lowercase()
+        .replace("smart contract", "");
+    let employment_type = match () {
+        () if contains_word(&text, INTERNSHIP_KEYWORDS) => "internship",
+        () if contains_word(&text, CONTRACT_KEYWORDS) => "contract",
+        () if contains_word(&text, PART_TIME_KEYWORDS) => "part-time",
+        () => "full-time",
+    };
+    employment_type.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        category, category_from_tags, company_matches, employment_type, language, location_matches,
+        normalize_company, normalize_location, primary_language, seniority, work_mode,
+    };
+
+    #[test]
+    fn test_company_matches() {
+        assert_eq!(normalize_company("Coinbase, Inc."), "coinbase");
+        assert_eq!(
+            normalize_company("Parity Technologies Ltd"),
+            "parity technologies"
+        );
+        assert_eq!(normalize_company("Co"), "co");
+        assert!(company_matches("Coinbase", &["coinbase"]));
+        assert!(company_matches("Coinbase, Inc.", &["Kraken", "Coinbase"]));
+        assert!(company_matches("  chainlink   labs ", &["Chainlink Labs"]));
+        assert!(!company_matches("Coinbase Wallet", &["Coinbase"]));
+        assert!(!company_matches("", &["Coinbase"]));
+    }
+
+    #[test]
+    fn test_location_matches() {
+        assert_eq!(
+            normalize_location(" San Francisco, CA (US) "),
+            "san francisco ca us"
+        );
+        assert!(location_matches(
+            "London, United Kingdom",
+            &["united kingdom"]
+        ));
+        assert!(location_matches("Berlin; Germany", &["Spain", "germany"]));
+        assert!(location_matches("New York, NY", &["new york"]));
+        assert!(!location_matches("Newark, NJ", &["new york", "ark"]));
+        assert!(!location_matches("", &["london"]));
+    }
+
+    #[test]
+    fn test_seniority() {
+        assert_eq!(seniority("Senior Rust Engineer"), "senior");
+        assert_eq!(seniority("Sr. Backend Developer"), "senior");
+        assert_eq!(seniority("Staff Protocol Engineer"), "lead");
+        assert_eq!(seniority("Junior Frontend Developer"), "junior");
+        assert_eq!(seniority("Smart Contract Engineer"), "mid");
+        assert_eq!(seniority("Engineering Leader"), "mid");
+    }
+
+    #[test]
+    fn test_work_mode() {
+        assert_eq!(work_mode("Rust Engineer", "Remote", &[]), "remote");
+        assert_eq!(work_mode("Rust Engineer (Remote)", "", &[]), "remote");
+        assert_eq!(
+            work_mode("Rust Engineer", "Remote (US only)", &[]),
+            "remote"
+        );
+        assert_eq!(
+            work_mode("Rust Engineer", "New York / Remote", &[]),
+            "remote"
+        );
+        assert_eq!(work_mode("Rust Engineer", "Anywhere", &[]), "remote");
+        assert_eq!(work_mode("Rust Engineer", "London - Hybrid", &[]), "hybrid");
+        assert_eq!(
+            work_mode("Rust Engineer", "Remote or Hybrid", &[]),
+            "hybrid"
+        );
+        assert_eq!(work_mode("Rust Engineer", "New York", &[]), "onsite");
+        assert_eq!(work_mode("Rust Engineer", "", &[]), "");
+        let tags = ["$ 90k-140k".to_string(), "Remote".to_string()];
+        assert_eq!(work_mode("Rust Engineer", "Berlin", &tags), "remote");
+    }
+
+    #[test]
+    fn test_employment_type() {
+        assert_eq!(employment_type("Rust Engineer", &[]), "full-time");
+        assert_eq!(
+            employment_type("Rust Engineer", &["Contract".to_string()]),
+            "contract"
+        );
+        assert_eq!(employment_type("Smart Contract Engineer", &[]), "full-time");
+        assert_eq!(employment_type("Part-time Developer", &[]), "part-time");
+        assert_eq!(employment_type("Engineering Intern", &[]), "internship");
+        assert_eq!(employment_type("Internal Tools Engineer", &[]), "full-time");
+    }
+
+    #[test]
+    fn test_primary_language() {
+        let tags = |tags: &[&str]| tags.iter().map(|t| t.to_string()).collect::<Vec<String>>();
+        assert_eq!(language("Golang"), Some("go"));
+        assert_eq!(language("node"), None);
+        assert_eq!(
+            primary_language("Smart Contract Engineer", &tags(&["Solidity", "Rust"])).as_deref(),
+            Some("solidity")
+        );
+        assert_eq!(
+            primary_language("Rust Engineer", &tags(&["Solidity", "Rust"])).as_deref(),
+            Some("rust")
+        );
+        assert_eq!(
+            primary_language("Senior Golang/TypeScript Developer", &[]).as_deref(),
+            Some("go")
+        );
+        assert_eq!(
+            primary_language("Backend Engineer", &tags(&["web3", "Node JS"])).as_deref(),
+            Some("javascript")
+        );
+        assert_eq!(primary_language("Go-to-market Lead", &[]), None);
+        assert_eq!(primary_language("Product Designer", &tags(&["defi"])), None);
+    }
+
+    #[test]
+    fn test_category() {
+        assert_eq!(category("Engineering").as_deref(), Some("engineering"));
+        assert_eq!(category(" Developer Relations ").as_deref(), Some("devrel"));
+        assert_eq!(
+            category("Ecosystem Growth").as_deref(),
+            Some("ecosystem growth")
+        );
+        assert_eq!(category("  "), None);
+        let tags = |tags: &[&str]| tags.iter().map(|t| t.to_string()).collect::<Vec<String>>();
+        assert_eq!(
+            category_from_tags(&tags(&["rust", "Research", "defi"])).as_deref(),
+            Some("research")
+        );
+        assert_eq!(category_from_tags(&tags(&["rust", "defi"])), None);
+    }
+}
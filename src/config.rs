@@ -0,0 +1,766 @@
+//! Settings that can be changed for the current session (e.g. from the REPL).
+
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{LazyLock, RwLock};
+use std::time::Duration;
+
+use reqwest::header::HeaderValue;
+use serde::Deserialize;
+use terminal_size::{terminal_size, Width};
+
+use crate::repository::DEFAULT_TITLE_KEYWORDS;
+use crate::selectors::SiteSelectors;
+use crate::site::{
+    default_common_boards, site_host, CommonBoard, SiteConfig, SiteGroup, WEB3_CAREERS_MAX_PAGES,
+};
+use crate::ErrorKind;
+
+/// The width of the separator printed after each job on a wide terminal.
+pub const DEFAULT_SEPARATOR_WIDTH: usize = 118;
+
+/// The default size below which a page body is treated as an empty document.
+pub const DEFAULT_MIN_DOCUMENT_BYTES: usize = 256;
+
+/// The default limit on requests in flight at once across all sites.
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 8;
+
+/// The default wall-clock budget for scraping all the sites on a refresh.
+pub const DEFAULT_SCRAPE_BUDGET: Duration = Duration::from_secs(60);
+
+/// The User-Agent sent to every site unless it's configured otherwise - a desktop browser's, since
+/// some sites serve different markup to mobile browsers.
+pub const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36";
+
+/// The default path of the DB file, in the working directory.
+pub const DEFAULT_DB_PATH: &str = "jobs.db";
+
+/// The default timeout for each request to a site.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Configures the separator printed after each job.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Separator {
+    /// Fits the terminal width, up to the default width.
+    #[default]
+    Auto,
+    /// A fixed width.
+    Width(usize),
+    /// No separator.
+    Off,
+}
+
+impl Separator {
+    /// Returns the separator line, or None if the separator is disabled.
+    pub fn line(&self) -> Option<String> {
+        let width = match self {
+            Self::Auto => terminal_size()
+                .map(|(Width(w), _)| (w as usize).min(DEFAULT_SEPARATOR_WIDTH))
+                .unwrap_or(DEFAULT_SEPARATOR_WIDTH),
+            Self::Width(w) => *w,
+            Self::Off => return None,
+        };
+        Some(format!("+{}+", "-".repeat(width.saturating_sub(2))))
+    }
+}
+
+impl FromStr for Separator {
+    type Err = ErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "auto" => Ok(Self::Auto),
+            "off" => Ok(Self::Off),
+            w => match w.parse::<usize>() {
+                Ok(w) if w >= 2 => Ok(Self::Width(w)),
+                _ => Err(ErrorKind::Setting(format!(
+                    "Separator must be \"auto\", \"off\" or a width of at least 2, got \"{w}\"."
+                ))),
+            },
+        }
+    }
+}
+
+/// Configures the title filter applied when the repository is populated.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TitleFilter {
+    /// When disabled, all scraped jobs are added to the repository.
+    pub enabled: bool,
+    /// A job is kept if its title contains any of the keywords.
+    pub keywords: Vec<String>,
+}
+
+impl TitleFilter {
+    /// Returns true if the filter removes any jobs - it's enabled and has keywords to match. An
+    /// empty keyword list means no filter.
+    pub fn is_active(&self) -> bool {
+        self.enabled && !self.keywords.is_empty()
+    }
+}
+
+impl Default for TitleFilter {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            keywords: DEFAULT_TITLE_KEYWORDS
+                .iter()
+                .map(|k| k.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Configures the in-memory cache of scraped HTML. The cache is off by default so that a refresh
+/// always fetches fresh listings - it is intended for repeated refreshes while developing selectors.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Cache {
+    pub enabled: bool,
+    /// How long a cached page is used before it is fetched again.
+    pub ttl: Duration,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl: Duration::from_secs(300),
+        }
+    }
+}
+
+/// Configures how failed requests are retried. The delay before each retry doubles, starting at
+/// `backoff`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Retry {
+    /// The number of retries after the first attempt.
+    pub retries: u32,
+    pub backoff: Duration,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            retries: 2,
+            backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+/// The format jobs are written in when copied or exported.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Format {
+    /// One line per job.
+    #[default]
+    Text,
+    Json,
+    Markdown,
+    Csv,
+    /// A standalone page with a sortable table.
+    Html,
+    /// An RSS 2.0 feed with an item per job.
+    Rss,
+}
+
+impl FromStr for Format {
+    type Err = ErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "markdown" | "md" => Ok(Self::Markdown),
+            "csv" => Ok(Self::Csv),
+            "html" => Ok(Self::Html),
+            "rss" => Ok(Self::Rss),
+            f => Err(ErrorKind::Setting(format!(
+                "Format must be one of text, json, markdown, csv, html or rss, got \"{f}\"."
+            ))),
+        }
+    }
+}
+
+/// The weights used to score jobs for "order by relevance". Each component of the score is between
+/// 0 and 1, so the weights set how much each contributes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Relevance {
+    /// Weights how recently the job was posted. The recency score halves every week.
+    pub recency: f64,
+    /// Weights whether the job lists remuneration.
+    pub salary: f64,
+    /// Weights whether the job is remote (or, at half the score, hybrid).
+    pub remote: f64,
+}
+
+impl Default for Relevance {
+    fn default() -> Self {
+        Self {
+            recency: 1.0,
+            salary: 0.5,
+            remote: 0.5,
+        }
+    }
+}
+
+impl FromStr for Relevance {
+    type Err = ErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let weights = s
+            .split_whitespace()
+            .map(|w| w.parse::<f64>().ok().filter(|w| w.is_finite() && *w >= 0.0))
+            .collect::<Option<Vec<f64>>>();
+        match weights.as_deref() {
+            Some(&[recency, salary, remote]) => Ok(Self {
+                recency,
+                salary,
+                remote,
+            }),
+            _ => Err(ErrorKind::Setting(format!(
+                "Relevance must be three non-negative weights for recency, salary and remote, got \"{s}\"."
+            ))),
+        }
+    }
+}
+
+/// The session settings.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub separator: Separator,
+    pub format: Format,
+    pub title_filter: TitleFilter,
+    /// Only jobs in these regions (e.g. countries or cities) are added to the repository. All
+    /// locations are included when empty. Remote jobs are included if "remote" is listed.
+    pub locations: Vec<String>,
+    /// The followed companies. Their jobs are highlighted in results and, when `follow_only` is
+    /// set, they're the only jobs added on refresh.
+    pub companies: Vec<String>,
+    /// When true (and companies are followed), only jobs from the followed companies are added.
+    pub follow_only: bool,
+    pub cache: Cache,
+    pub retry: Retry,
+    pub relevance: Relevance,
+    /// The job-function filters scraped from web3careers (e.g. "backend-jobs"). The unfiltered
+    /// listing is scraped when empty.
+    pub web3_careers_filters: Vec<String>,
+    /// The maximum number of pages scraped from each web3careers listing.
+    pub web3_careers_pages: u8,
+    /// The maximum number of web3careers detail pages fetched on a refresh to fill in the salaries
+    /// of jobs whose listing row has none. 0 turns this off.
+    pub web3_careers_salary_pages: usize,
+    /// The maximum number of result pages scraped from cryptojobslist.
+    pub crypto_jobs_list_pages: u32,
+    /// The maximum number of result pages scraped from each of the solana, substrate and near
+    /// boards.
+    pub common_pages: u32,
+    /// The tokens of the Greenhouse boards scraped (e.g. "chainlinklabs"). None are scraped when
+    /// empty.
+    pub greenhouse_boards: Vec<String>,
+    /// Pages with a body smaller than this many bytes are treated as empty documents rather than
+    /// parsed.
+    pub min_document_bytes: usize,
+    /// When set, jobs are written to the database in transactions of this many rows rather than
+    /// one transaction.
+    pub insert_batch_size: Option<usize>,
+    /// The wall-clock budget for scraping all the sites on a refresh. Sites still being scraped
+    /// when it runs out are cut off, and the jobs collected so far are imported. None means no
+    /// budget.
+    pub scrape_budget: Option<Duration>,
+    /// How long a single request may take, from connecting to reading the body, before it fails
+    /// as timed out.
+    pub request_timeout: Duration,
+    /// How many times a site that returns no jobs is scraped again before it's warned about.
+    pub empty_retries: u32,
+    /// When true, "refresh" runs in the background so the current jobs can still be queried.
+    pub background_refresh: bool,
+    /// When true, every refresh also appends the scraped jobs to the job history table, so how
+    /// postings change over time is kept.
+    pub history: bool,
+    /// When true, posted dates are displayed relative to today, e.g. "3 days ago".
+    pub relative_dates: bool,
+    /// When true, results are grouped by how recently they were posted, e.g. "This week".
+    pub group_by_recency: bool,
+    /// When true, scraped jobs whose title can't be found are dropped rather than kept untitled.
+    pub strict_titles: bool,
+    /// When true, status messages are suppressed and errors go to stderr, so stdout only has
+    /// results.
+    pub quiet: bool,
+    /// Saved queries, keyed by name.
+    pub presets: BTreeMap<String, String>,
+    /// The directory snapshots of the DB are saved to.
+    pub snapshot_dir: PathBuf,
+    /// The path of the DB file, or ":memory:" for a DB kept in memory for the session.
+    pub db_path: PathBuf,
+    /// Additional jobsites loaded at startup (e.g. from a sites file).
+    pub custom_sites: Vec<SiteConfig>,
+    /// The common (Ashby-style) jobsites scraped - the built-in ones unless a sites file lists
+    /// others.
+    pub common_boards: Vec<CommonBoard>,
+    /// Selectors overriding the defaults of the built-in sites, keyed by site and then field.
+    pub selector_overrides: BTreeMap<String, SiteSelectors>,
+    /// Request limits for groups of sites, keyed by group name. Hosts not in any group are in the
+    /// "default" group, which has the default limits unless it's configured here.
+    pub site_groups: BTreeMap<String, SiteGroup>,
+    /// The maximum number of requests in flight at once across all the site groups.
+    pub max_concurrent_requests: usize,
+    /// The User-Agents sent to the sites, taking turns with each request so sites that rate-limit
+    /// per User-Agent see the requests spread between them. A "User-Agent" in a site's headers
+    /// overrides these.
+    pub user_agents: Vec<String>,
+    /// Request headers for each site, keyed by host (without "www."). These are merged over the
+    /// default headers.
+    pub site_headers: HashMap<String, BTreeMap<String, String>>,
+}
+
+impl Config {
+    /// Returns the configured request headers for the site of a URL.
+    pub fn headers_for(&self, url: &str) -> BTreeMap<String, String> {
+        site_host(url)
+            .and_then(|host| self.site_headers.get(&host).cloned())
+            .unwrap_or_default()
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            separator: Default::default(),
+            format: Default::default(),
+            title_filter: Default::default(),
+            locations: Vec::new(),
+            companies: Vec::new(),
+            follow_only: false,
+            cache: Default::default(),
+            retry: Default::default(),
+            relevance: Default::default(),
+            web3_careers_filters: Vec::new(),
+            web3_careers_pages: WEB3_CAREERS_MAX_PAGES,
+            web3_careers_salary_pages: 0,
+            crypto_jobs_list_pages: 5,
+            common_pages: 5,
+            greenhouse_boards: Vec::new(),
+            min_document_bytes: DEFAULT_MIN_DOCUMENT_BYTES,
+            insert_batch_size: None,
+            scrape_budget: Some(DEFAULT_SCRAPE_BUDGET),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            empty_retries: 1,
+            background_refresh: true,
+            history: false,
+            relative_dates: false,
+            group_by_recency: false,
+            strict_titles: false,
+            quiet: false,
+            presets: BTreeMap::new(),
+            snapshot_dir: PathBuf::from("snapshots"),
+            db_path: PathBuf::from(DEFAULT_DB_PATH),
+            custom_sites: Vec::new(),
+            common_boards: default_common_boards(),
+            selector_overrides: BTreeMap::new(),
+            site_groups: BTreeMap::new(),
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+            user_agents: vec![DEFAULT_USER_AGENT.to_string()],
+            site_headers: HashMap::new(),
+        }
+    }
+}
+
+static CONFIG: LazyLock<RwLock<Config>> = LazyLock::new(Default::default);
+
+/// The settings file loaded at startup. Every setting is optional - those left out keep their
+/// defaults - and the settings it does give can still be overridden by environment variables and
+/// flags. Values are written as they would be after `set` in the REPL where there's a `set`
+/// command for the setting, e.g. `separator = "off"`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    /// A sites file to load, unless one is given with `--sites-file`. A relative path is relative
+    /// to the config file.
+    pub sites_file: Option<PathBuf>,
+    pub separator: Option<String>,
+    pub format: Option<String>,
+    pub relevance: Option<String>,
+    pub title_filter: Option<bool>,
+    pub keywords: Option<Vec<String>>,
+    pub locations: Option<Vec<String>>,
+    pub companies: Option<Vec<String>>,
+    pub follow_only: Option<bool>,
+    pub cache: Option<bool>,
+    pub cache_ttl_secs: Option<u64>,
+    pub retries: Option<u32>,
+    pub backoff_ms: Option<u64>,
+    pub empty_retries: Option<u32>,
+    pub w3c_filters: Option<Vec<String>>,
+    pub w3c_pages: Option<u8>,
+    pub w3c_salary_pages: Option<usize>,
+    pub cjl_pages: Option<u32>,
+    pub common_pages: Option<u32>,
+    pub greenhouse_boards: Option<Vec<String>>,
+    pub min_document_bytes: Option<usize>,
+    pub insert_batch_size: Option<usize>,
+    /// The scrape budget in seconds, or 0 for no budget.
+    pub scrape_budget_secs: Option<u64>,
+    pub timeout_secs: Option<u64>,
+    pub background_refresh: Option<bool>,
+    pub history: Option<bool>,
+    pub relative_dates: Option<bool>,
+    pub group_by_recency: Option<bool>,
+    pub strict_titles: Option<bool>,
+    pub quiet: Option<bool>,
+    pub snapshot_dir: Option<PathBuf>,
+    pub db: Option<PathBuf>,
+    pub max_concurrent_requests: Option<usize>,
+    pub user_agents: Option<Vec<String>>,
+    /// Saved queries, keyed by name.
+    pub presets: Option<BTreeMap<String, String>>,
+}
+
+impl ConfigFile {
+    /// Returns the conventional path of the config file - `$XDG_CONFIG_HOME/jobhunt/config.toml`,
+    /// or `~/.config/jobhunt/config.toml` if `XDG_CONFIG_HOME` isn't set.
+    pub fn default_path() -> Option<PathBuf> {
+        let config_home = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_home.join("jobhunt").join("config.toml"))
+    }
+
+    /// Loads and validates a config file.
+    pub fn load(path: &Path) -> Result<Self, ErrorKind> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ErrorKind::ConfigFile(format!("{}: {e}", path.display())))?;
+        let mut file = Self::parse(&contents)
+            .map_err(|e| ErrorKind::ConfigFile(format!("{}: {e}", path.display())))?;
+        if let (Some(sites_file), Some(dir)) = (&file.sites_file, path.parent()) {
+            file.sites_file = Some(dir.join(sites_file));
+        }
+        Ok(file)
+    }
+
+    /// Parses and validates the contents of a config file.
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let file: Self = toml::from_str(contents).map_err(|e| e.to_string())?;
+        file.apply_to(&mut Config::default())
+            .map_err(|e| e.to_string())?;
+        Ok(file)
+    }
+
+    /// Applies the settings given in the file to the session settings.
+    pub fn apply(&self) -> Result<(), ErrorKind> {
+        let mut config = get();
+        self.apply_to(&mut config)?;
+        update(|c| *c = config);
+        Ok(())
+    }
+
+    /// Applies the settings given in the file over the settings in `config`.
+    fn apply_to(&self, config: &mut Config) -> Result<(), ErrorKind> {
+        let invalid = |msg: &str| Err(ErrorKind::Setting(msg.to_string()));
+        if let Some(separator) = &self.separator {
+            config.separator = separator.parse()?;
+        }
+        if let Some(format) = &self.format {
+            config.format = format.parse()?;
+        }
+        if let Some(relevance) = &self.relevance {
+            config.relevance = relevance.parse()?;
+        }
+        if let Some(enabled) = self.title_filter {
+            config.title_filter.enabled = enabled;
+        }
+        if let Some(keywords) = &self.keywords {
+            config.title_filter.keywords.clone_from(keywords);
+        }
+        if let Some(locations) = &self.locations {
+            config.locations.clone_from(locations);
+        }
+        if let Some(companies) = &self.companies {
+            config.companies.clone_from(companies);
+        }
+        if let Some(follow_only) = self.follow_only {
+            config.follow_only = follow_only;
+        }
+        if let Some(enabled) = self.cache {
+            config.cache.enabled = enabled;
+        }
+        if let Some(ttl) = self.cache_ttl_secs {
+            config.cache.ttl = Duration::from_secs(ttl);
+        }
+        if let Some(retries) = self.retries {
+            config.retry.retries = retries;
+        }
+        if let Some(backoff) = self.backoff_ms {
+            config.retry.backoff = Duration::from_millis(backoff);
+        }
+        if let Some(retries) = self.empty_retries {
+            config.empty_retries = retries;
+        }
+        if let Some(filters) = &self.w3c_filters {
+            config.web3_careers_filters = filters
+                .iter()
+                .map(|f| f.trim().trim_matches('/').to_string())
+                .filter(|f| !f.is_empty())
+                .collect();
+        }
+        if let Some(boards) = &self.greenhouse_boards {
+            config.greenhouse_boards = boards
+                .iter()
+                .map(|b| b.trim().to_string())
+                .filter(|b| !b.is_empty())
+                .collect();
+        }
+        match self.w3c_pages {
+            Some(0) => invalid("w3c_pages must be a positive whole number.")?,
+            Some(pages) => config.web3_careers_pages = pages,
+            None => {}
+        }
+        if let Some(pages) = self.w3c_salary_pages {
+            config.web3_careers_salary_pages = pages;
+        }
+        match self.cjl_pages {
+            Some(0) => invalid("cjl_pages must be a positive whole number.")?,
+            Some(pages) => config.crypto_jobs_list_pages = pages,
+            None => {}
+        }
+        match self.common_pages {
+            Some(0) => invalid("common_pages must be a positive whole number.")?,
+            Some(pages) => config.common_pages = pages,
+            None => {}
+        }
+        if let Some(min_bytes) = self.min_document_bytes {
+            config.min_document_bytes = min_bytes;
+        }
+        match self.insert_batch_size {
+            Some(0) => invalid("insert_batch_size must be a positive whole number.")?,
+            Some(batch_size) => config.insert_batch_size = Some(batch_size),
+            None => {}
+        }
+        match self.scrape_budget_secs {
+            Some(0) => config.scrape_budget = None,
+            Some(secs) => config.scrape_budget = Some(Duration::from_secs(secs)),
+            None => {}
+        }
+        match self.timeout_secs {
+            Some(0) => invalid("timeout_secs must be a positive whole number.")?,
+            Some(secs) => config.request_timeout = Duration::from_secs(secs),
+            None => {}
+        }
+        if let Some(background_refresh) = self.background_refresh {
+            config.background_refresh = background_refresh;
+        }
+        if let Some(history) = self.history {
+            config.history = history;
+        }
+        if let Some(relative_dates) = self.relative_dates {
+            config.relative_dates = relative_dates;
+        }
+        if let Some(group_by_recency) = self.group_by_recency {
+            config.group_by_recency = group_by_recency;
+        }
+        if let Some(strict_titles) = self.strict_titles {
+            config.strict_titles = strict_titles;
+        }
+        if let Some(quiet) = self.quiet {
+            config.quiet = quiet;
+        }
+        if let Some(snapshot_dir) = &self.snapshot_dir {
+            config.snapshot_dir.clone_from(snapshot_dir);
+        }
+        if let Some(db) = &self.db {
+            config.db_path.clone_from(db);
+        }
+        match self.max_concurrent_requests {
+            Some(0) => invalid("max_concurrent_requests must be a positive whole number.")?,
+            Some(max) => config.max_concurrent_requests = max,
+            None => {}
+        }
+        if let Some(user_agents) = &self.user_agents {
+            if user_agents.is_empty() {
+                invalid("user_agents must list at least one User-Agent.")?;
+            }
+            if let Some(ua) = user_agents
+                .iter()
+                .find(|ua| ua.trim().is_empty() || HeaderValue::from_str(ua).is_err())
+            {
+                invalid(&format!("user_agents has an invalid User-Agent \"{ua}\"."))?;
+            }
+            config.user_agents = user_agents.clone();
+        }
+        if let Some(presets) = &self.presets {
+            for (name, query) in presets {
+                if !query.trim().to_lowercase().starts_with("select jobs") {
+                    Err(ErrorKind::Setting(format!(
+                        "Preset \"{name}\" must be a \"select jobs ...\" query."
+                    )))?;
+                }
+                config
+                    .presets
+                    .insert(name.to_lowercase(), query.trim().to_lowercase());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Overrides the session settings from environment variables:
+/// - `JOBHUNT_RETRIES` - the number of request retries
+/// - `JOBHUNT_BACKOFF_MS` - the initial retry backoff in milliseconds
+/// - `JOBHUNT_QUIET` - quiet mode, unless empty, "0" or "false"
+/// - `JOBHUNT_KEYWORDS` - the comma separated title filter keywords, or no filter if empty
+/// - `JOBHUNT_DB` - the path of the DB file, or ":memory:" for a DB kept in memory
+pub fn apply_env() -> Result<(), ErrorKind> {
+    if let Ok(retries) = env::var("JOBHUNT_RETRIES") {
+        let retries = parse_retries(&retries)?;
+        update(|c| c.retry.retries = retries);
+    }
+    if let Ok(backoff) = env::var("JOBHUNT_BACKOFF_MS") {
+        let backoff = parse_backoff(&backoff)?;
+        update(|c| c.retry.backoff = backoff);
+    }
+    if let Ok(keywords) = env::var("JOBHUNT_KEYWORDS") {
+        update(|c| c.title_filter.keywords = parse_keywords(&keywords));
+    }
+    if let Some(db) = env::var_os("JOBHUNT_DB").filter(|db| !db.is_empty()) {
+        update(|c| c.db_path = PathBuf::from(db));
+    }
+    if let Ok(quiet) = env::var("JOBHUNT_QUIET") {
+        if !matches!(quiet.trim(), "" | "0" | "false") {
+            update(|c| c.quiet = true);
+        }
+    }
+    Ok(())
+}
+
+/// Parses a comma separated list of title filter keywords, e.g. "designer, product manager".
+pub fn parse_keywords(keywords: &str) -> Vec<String> {
+    keywords
+        .split(',')
+        .map(|k| k.trim().to_string())
+        .filter(|k| !k.is_empty())
+        .collect()
+}
+
+/// Parses a non-negative number of retries.
+pub fn parse_retries(s: &str) -> Result<u32, ErrorKind> {
+    s.trim().parse::<u32>().map_err(|_| {
+        ErrorKind::Setting(format!(
+            "Retries must be a non-negative whole number, got \"{s}\"."
+        ))
+    })
+}
+
+/// Parses a non-negative backoff in milliseconds.
+pub fn parse_backoff(s: &str) -> Result<Duration, ErrorKind> {
+    s.trim()
+        .parse::<u64>()
+        .map(Duration::from_millis)
+        .map_err(|_| {
+            ErrorKind::Setting(format!(
+                "Backoff must be a non-negative number of milliseconds, got \"{s}\"."
+            ))
+        })
+}
+
+/// Returns a copy of the current session settings.
+pub fn get() -> Config {
+    CONFIG.read().unwrap().clone()
+}
+
+/// Returns true if status messages are suppressed. This is a cheaper check than `get().quiet` for
+/// the print macros.
+pub fn quiet() -> bool {
+    CONFIG.read().unwrap().quiet
+}
+
+/// Updates the current session settings.
+pub fn update<F>(f: F)
+where
+    F: FnOnce(&mut Config),
+{
+    f(&mut CONFIG.write().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{parse_backoff, parse_retries, Config, ConfigFile, Relevance, Separator};
+
+    #[test]
+    fn test_config_file() {
+        let file = ConfigFile::parse(
+            r#"
+            separator = "off"
+            format = "csv"
+            keywords = ["rust", "protocol"]
+            retries = 5
+            scrape_budget_secs = 0
+            timeout_secs = 10
+            cjl_pages = 2
+            db = ":memory:"
+            presets = { remote = "select jobs where location like '%remote%'" }
+            "#,
+        )
+        .unwrap();
+        let mut config = Config::default();
+        file.apply_to(&mut config).unwrap();
+        assert_eq!(config.separator, Separator::Off);
+        assert_eq!(config.title_filter.keywords, ["rust", "protocol"]);
+        assert!(config.title_filter.enabled);
+        assert_eq!(config.retry.retries, 5);
+        assert_eq!(config.retry.backoff, Duration::from_millis(500));
+        assert_eq!(config.scrape_budget, None);
+        assert_eq!(config.request_timeout, Duration::from_secs(10));
+        assert_eq!(config.crypto_jobs_list_pages, 2);
+        assert_eq!(config.db_path.to_str(), Some(":memory:"));
+        assert!(config.presets.contains_key("remote"));
+
+        assert!(ConfigFile::parse("user_agents = []").is_err());
+
+        let err = ConfigFile::parse("retrys = 5").unwrap_err();
+        assert!(err.contains("unknown field `retrys`"), "{err}");
+        let err = ConfigFile::parse(r#"format = "pdf""#).unwrap_err();
+        assert!(err.contains("Format must be one of"));
+        assert!(ConfigFile::parse("cjl_pages = 0").is_err());
+        assert!(ConfigFile::parse(r#"presets = { all = "drop table jobs" }"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_separator() {
+        assert_eq!("auto".parse::<Separator>().unwrap(), Separator::Auto);
+        assert_eq!("off".parse::<Separator>().unwrap(), Separator::Off);
+        assert_eq!("40".parse::<Separator>().unwrap(), Separator::Width(40));
+        assert!("1".parse::<Separator>().is_err());
+        assert!("wide".parse::<Separator>().is_err());
+    }
+
+    #[test]
+    fn test_parse_retry_settings() {
+        assert_eq!(parse_retries("3").unwrap(), 3);
+        assert!(parse_retries("-1").is_err());
+        assert_eq!(parse_backoff("250").unwrap(), Duration::from_millis(250));
+        assert!(parse_backoff("fast").is_err());
+    }
+
+    #[test]
+    fn test_parse_relevance() {
+        assert_eq!(
+            "2 0 1.5".parse::<Relevance>().unwrap(),
+            Relevance {
+                recency: 2.0,
+                salary: 0.0,
+                remote: 1.5
+            }
+        );
+        assert!("1 1".parse::<Relevance>().is_err());
+        assert!("1 -1 1".parse::<Relevance>().is_err());
+    }
+
+    #[test]
+    fn test_separator_line() {
+        assert_eq!(Separator::Width(6).line().unwrap(), "+----+");
+        assert_eq!(Separator::Off.line(), None);
+    }
+}
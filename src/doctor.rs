@@ -0,0 +1,236 @@
+//! Self-checks for diagnosing a broken setup, and (with `--deep`) a broken scraper.
+
+use std::fmt::{Display, Formatter};
+use std::sync::LazyLock;
+
+use colored::Colorize;
+use regex::Regex;
+
+use crate::config;
+use crate::repository::{Job, SoftwareJobs};
+use crate::scraper::{self, Scraper, DATE_REGEX, REM_REGEX, UNTITLED};
+use crate::selectors;
+use crate::site::{CommonSite, CryptoJobsList, CustomSite, GreenhouseJobs, Site, Web3Careers};
+use crate::ErrorKind;
+
+/// The share of a site's jobs (in percent) that must have a valid value for each checked field,
+/// for the site to pass.
+pub const HEALTHY_PERCENT: f64 = 80.0;
+
+/// The fields of each site's jobs checked by the deep check.
+pub const CHECKED_FIELDS: [&str; 5] = ["title", "company", "date", "apply", "remuneration"];
+
+/// The result of one of the setup checks, e.g. that the selectors all parse.
+pub struct Check {
+    pub name: &'static str,
+    pub result: Result<(), String>,
+}
+
+impl Display for Check {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.result {
+            Ok(()) => write!(f, "{} {}", "✔".green(), self.name.green()),
+            Err(err) => write!(f, "{} {}", "✘".red(), format!("{}: {err}", self.name).red()),
+        }
+    }
+}
+
+/// Checks the setup without scraping anything - that the selectors (with any overrides) all parse,
+/// and that the database isn't corrupted.
+pub fn check_setup() -> Vec<Check> {
+    let overrides = config::get().selector_overrides;
+    vec![
+        Check {
+            name: "selectors",
+            result: selectors::merge(selectors::defaults(), &overrides).map(|_| ()),
+        },
+        Check {
+            name: "database",
+            result: SoftwareJobs::check_integrity(&config::get().db_path)
+                .map_err(|e| e.to_string()),
+        },
+    ]
+}
+
+/// Returns true if a job has a valid value for one of the checked fields - a title, a company, a
+/// posted date and an apply link, and any remuneration formatted as expected.
+pub fn valid_field(field: &str, job: &Job) -> bool {
+    static DATE: LazyLock<Regex> = LazyLock::new(|| Regex::new(DATE_REGEX).unwrap());
+    static REM: LazyLock<Regex> = LazyLock::new(|| Regex::new(REM_REGEX).unwrap());
+    match field {
+        "title" => !job.title.is_empty() && job.title != UNTITLED,
+        "company" => !job.company.is_empty(),
+        "date" => DATE.is_match(&job.date_posted),
+        "apply" => job.apply.starts_with("https") || job.apply.starts_with("mailto"),
+        _ => job.remuneration.is_empty() || REM.is_match(&job.remuneration),
+    }
+}
+
+/// How well a site's scraped jobs are populated.
+pub struct SiteHealth {
+    pub site: String,
+    pub jobs: usize,
+    /// The share of jobs (in percent) with a valid value for each checked field.
+    pub fields: Vec<(&'static str, f64)>,
+    /// Why the site couldn't be scraped, if it couldn't.
+    pub error: Option<String>,
+}
+
+impl SiteHealth {
+    /// Checks the key fields of a site's scraped jobs - that each has a title, a company, a posted
+    /// date and an apply link, and that any remuneration is formatted as expected.
+    pub fn from_jobs(site: &str, jobs: &[Job]) -> Self {
+        let fields = CHECKED_FIELDS
+            .iter()
+            .map(|field| {
+                let cnt = jobs.iter().filter(|job| valid_field(field, job)).count();
+                (*field, 100.0 * cnt as f64 / jobs.len().max(1) as f64)
+            })
+            .collect();
+        Self {
+            site: site.to_string(),
+            jobs: jobs.len(),
+            fields,
+            error: None,
+        }
+    }
+
+    /// The health of a site that couldn't be scraped.
+    pub fn failed(site: &str, err: ErrorKind) -> Self {
+        Self {
+            site: site.to_string(),
+            jobs: 0,
+            fields: Vec::new(),
+            error: Some(err.to_string()),
+        }
+    }
+
+    /// The site's health score - the average share of jobs with valid fields, or 0 if no jobs were
+    /// scraped.
+    pub fn score(&self) -> f64 {
+        match self.jobs {
+            0 => 0.0,
+            _ => self.fields.iter().map(|(_, pct)| pct).sum::<f64>() / self.fields.len() as f64,
+        }
+    }
+
+    /// Returns true if jobs were scraped and every checked field is valid often enough.
+    pub fn passed(&self) -> bool {
+        self.jobs > 0 && self.fields.iter().all(|(_, pct)| *pct >= HEALTHY_PERCENT)
+    }
+}
+
+impl Display for SiteHealth {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let summary = match (&self.error, self.jobs) {
+            (Some(err), _) => err.clone(),
+            (None, 0) => "no jobs found".to_string(),
+            (None, jobs) => {
+                let weak = self
+                    .fields
+                    .iter()
+                    .filter(|(_, pct)| *pct < HEALTHY_PERCENT)
+                    .map(|(field, pct)| format!("{field} {pct:.0}%"))
+                    .collect::<Vec<String>>();
+                match weak.is_empty() {
+                    true => format!("{jobs} jobs"),
+                    false => format!("{jobs} jobs, low: {}", weak.join(", ")),
+                }
+            }
+        };
+        let line = format!("{} - score {:.0}% ({summary})", self.site, self.score());
+        match self.passed() {
+            true => write!(f, "{} {}", "✔".green(), line.green()),
+            false => write!(f, "{} {}", "✘".red(), line.red()),
+        }
+    }
+}
+
+/// Scrapes each site (the first page where the number of pages is configurable) and checks the
+/// health of its jobs.
+pub async fn check_sites() -> Vec<SiteHealth> {
+    config::update(|c| {
+        c.crypto_jobs_list_pages = 1;
+        c.common_pages = 1;
+        c.web3_careers_filters.clear();
+        c.web3_careers_salary_pages = 0;
+    });
+    let health = |site: &str, res: Result<Vec<Job>, ErrorKind>| match res {
+        Ok(jobs) => SiteHealth::from_jobs(site, &jobs),
+        Err(err) => SiteHealth::failed(site, err),
+    };
+    let client = scraper::client();
+    let mut sites = vec![
+        health(
+            "web3careers",
+            Web3Careers::new()
+                .with_max_pages(1)
+                .scrape(&client)
+                .await
+                .map(|s| s.jobs),
+        ),
+        health(
+            "cryptojobslist",
+            CryptoJobsList::new().scrape(&client).await.map(|s| s.jobs),
+        ),
+    ];
+    for board in config::get().common_boards {
+        let name = board.name.clone();
+        sites.push(health(
+            &name,
+            CommonSite::new(board).scrape(&client).await.map(|s| s.jobs),
+        ));
+    }
+    for site in config::get().custom_sites {
+        let name = site.name.clone();
+        sites.push(health(
+            &name,
+            CustomSite::new(site).scrape(&client).await.map(|s| s.jobs),
+        ));
+    }
+    for board in config::get().greenhouse_boards {
+        let site = GreenhouseJobs::new().with_board(&board);
+        sites.push(health(&board, site.scrape(&client).await.map(|s| s.jobs)));
+    }
+    sites
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repository::Job;
+    use crate::ErrorKind;
+
+    use super::SiteHealth;
+
+    #[test]
+    fn test_site_health() {
+        let job = |title: &str, date_posted: &str, remuneration: &str| Job {
+            title: title.to_string(),
+            company: "Parity".to_string(),
+            date_posted: date_posted.to_string(),
+            remuneration: remuneration.to_string(),
+            apply: "https://web3.career/rust-engineer/1".to_string(),
+            ..Default::default()
+        };
+        let jobs = [
+            job("Rust Engineer", "2024-05-06", "$90k - $140k"),
+            job("Go Engineer", "2024-05-06 12:05:50", ""),
+            job("Solidity Engineer", "yesterday", ""),
+            job("(untitled)", "2024-05-05", "90-140"),
+        ];
+        let health = SiteHealth::from_jobs("web3careers", &jobs);
+        let pct = |field: &str| health.fields.iter().find(|(f, _)| *f == field).unwrap().1;
+        assert_eq!(pct("title"), 75.0);
+        assert_eq!(pct("company"), 100.0);
+        assert_eq!(pct("date"), 75.0);
+        assert_eq!(pct("remuneration"), 75.0);
+        assert_eq!(health.score(), 85.0);
+        assert!(!health.passed());
+
+        assert!(SiteHealth::from_jobs("near", &jobs[..2]).passed());
+        assert!(!SiteHealth::from_jobs("near", &[]).passed());
+        let failed = SiteHealth::failed("near", ErrorKind::Selector("bad".to_string()));
+        assert_eq!(failed.score(), 0.0);
+        assert!(!failed.passed());
+    }
+}
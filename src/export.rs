@@ -0,0 +1,459 @@
+//! Renders jobs in formats for viewing outside the REPL.
+
+use chrono::NaiveDate;
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use itertools::Itertools;
+
+use crate::config::Format;
+use crate::repository::Job;
+use crate::ErrorKind;
+
+/// Sorts the table by the clicked column, toggling between ascending and descending.
+const SORT_SCRIPT: &str = r#"<script>
+document.querySelectorAll("th").forEach((th, i) => th.addEventListener("click", () => {
+  const tbody = th.closest("table").tBodies[0];
+  const asc = th.dataset.order !== "asc";
+  document.querySelectorAll("th").forEach(h => delete h.dataset.order);
+  th.dataset.order = asc ? "asc" : "desc";
+  const text = row => row.cells[i].textContent.trim();
+  [...tbody.rows]
+    .sort((a, b) => text(a).localeCompare(text(b), undefined, { numeric: true }) * (asc ? 1 : -1))
+    .forEach(row => tbody.appendChild(row));
+}));
+</script>"#;
+
+const STYLE: &str = r#"<style>
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border: 1px solid #ccc; padding: 0.4em 0.6em; text-align: left; }
+th { background: #eee; cursor: pointer; user-select: none; }
+th[data-order="asc"]::after { content: " \25B2"; }
+th[data-order="desc"]::after { content: " \25BC"; }
+tr:nth-child(even) { background: #fafafa; }
+td img { max-width: 32px; max-height: 32px; }
+</style>"#;
+
+/// Escapes the HTML-special characters in a string.
+pub fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Renders jobs in the given format.
+pub fn render(jobs: &[Job], format: Format) -> Result<String, ErrorKind> {
+    match format {
+        Format::Text => Ok(to_text(jobs)),
+        Format::Json => {
+            serde_json::to_string_pretty(jobs).map_err(|e| ErrorKind::Serialisation(e.to_string()))
+        }
+        Format::Markdown => Ok(to_markdown(jobs)),
+        Format::Csv => Ok(to_csv(jobs)),
+        Format::Html => Ok(to_html(jobs)),
+        Format::Rss => Ok(to_rss(jobs)),
+    }
+}
+
+/// Returns the names of the job fields that can be chosen as export columns, in field order.
+pub fn columns() -> Vec<String> {
+    fields(&Job::default())
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Parses a comma separated list of export columns (e.g. "title,company,apply"), checking each is
+/// a job field.
+pub fn parse_columns(list: &str) -> Result<Vec<String>, ErrorKind> {
+    let known = columns();
+    let columns = list
+        .split(',')
+        .map(|c| c.trim().to_lowercase())
+        .filter(|c| !c.is_empty())
+        .collect::<Vec<String>>();
+    if columns.is_empty() {
+        Err(ErrorKind::Export(
+            "At least one column must be given.".to_string(),
+        ))?;
+    }
+    if let Some(unknown) = columns.iter().find(|c| !known.contains(c)) {
+        Err(ErrorKind::Export(format!(
+            "\"{unknown}\" isn't a job field. Columns can be any of: {}",
+            known.join(", ")
+        )))?;
+    }
+    Ok(columns)
+}
+
+/// Returns a job's fields by name, as serialised.
+fn fields(job: &Job) -> Map<String, Value> {
+    match serde_json::to_value(job) {
+        Ok(Value::Object(fields)) => fields,
+        _ => Map::new(),
+    }
+}
+
+/// Renders jobs as CSV or JSON with only the given columns, in the given order.
+pub fn render_columns(
+    jobs: &[Job],
+    format: Format,
+    columns: &[String],
+) -> Result<String, ErrorKind> {
+    let rows = jobs.iter().map(|job| {
+        let mut fields = fields(job);
+        columns
+            .iter()
+            .map(|c| (c.as_str(), fields.remove(c).unwrap_or(Value::Null)))
+            .collect::<Vec<(&str, Value)>>()
+    });
+    match format {
+        Format::Json => {
+            // the rows are written as maps so the columns keep their order
+            let rows = rows.map(Columns).collect::<Vec<_>>();
+            serde_json::to_string_pretty(&rows).map_err(|e| ErrorKind::Serialisation(e.to_string()))
+        }
+        Format::Csv => {
+            let mut csv = columns.join(",");
+            csv.push('\n');
+            for row in rows {
+                let row = row.iter().map(|(_, v)| csv_field(&csv_value(v))).join(",");
+                csv.push_str(&row);
+                csv.push('\n');
+            }
+            Ok(csv)
+        }
+        _ => Err(ErrorKind::Export(
+            "Columns can only be chosen for csv and json exports.".to_string(),
+        )),
+    }
+}
+
+/// A job's chosen columns, serialised as a map in column order.
+struct Columns<'a>(Vec<(&'a str, Value)>);
+
+impl Serialize for Columns<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, value) in &self.0 {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+/// Formats a serialised field as a CSV value. Lists (i.e. tags) are joined with semicolons.
+fn csv_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(values) => values.iter().map(csv_value).join(";"),
+        value => value.to_string(),
+    }
+}
+
+/// Quotes a CSV field if it contains a comma, quote or line break.
+fn csv_field(s: &str) -> String {
+    match s.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", s.replace('"', "\"\"")),
+        false => s.to_string(),
+    }
+}
+
+/// Renders jobs as CSV with a header row. Tags are joined with semicolons.
+pub fn to_csv(jobs: &[Job]) -> String {
+    let mut csv =
+        String::from("title,company,date_posted,location,remuneration,currency,tags,apply,site\n");
+    for job in jobs {
+        let fields = [
+            job.title.as_str(),
+            &job.company,
+            &job.date_posted,
+            &job.location,
+            &job.remuneration,
+            job.currency.as_deref().unwrap_or_default(),
+            &job.tags.join(";"),
+            &job.apply,
+            &job.site,
+        ];
+        let row = fields.map(csv_field).join(",");
+        csv.push_str(&row);
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Renders jobs as an RSS 2.0 feed, with an item per job linking to its apply link.
+pub fn to_rss(jobs: &[Job]) -> String {
+    let mut items = String::new();
+    for job in jobs {
+        let description = [
+            job.location.as_str(),
+            &job.remuneration,
+            &job.tags.join(", "),
+        ]
+        .into_iter()
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" | ");
+        let pub_date = job
+            .date_posted
+            .get(..10)
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+            .map(|d| {
+                format!(
+                    "<pubDate>{}</pubDate>",
+                    d.format("%a, %d %b %Y 00:00:00 +0000")
+                )
+            })
+            .unwrap_or_default();
+        items.push_str(&format!(
+            "<item><title>{}</title><link>{}</link><description>{}</description><guid isPermaLink=\"false\">{}</guid>{pub_date}</item>\n",
+            escape_html(&format!("{} at {}", job.title, job.company)),
+            escape_html(&job.apply),
+            escape_html(&description),
+            escape_html(&job.dedup_key()),
+        ));
+    }
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+<channel>
+<title>Job Hunt</title>
+<link>https://github.com/Yukigeshiki/job-hunt-v2-rust</link>
+<description>Recent jobs from Job Hunt</description>
+{items}</channel>
+</rss>
+"#
+    )
+}
+
+/// Renders jobs compactly, one line per job.
+pub fn to_text(jobs: &[Job]) -> String {
+    let mut text = String::new();
+    for job in jobs {
+        let fields = [
+            &job.title,
+            &job.company,
+            &job.location,
+            &job.remuneration,
+            &job.apply,
+        ];
+        let fields = fields
+            .into_iter()
+            .filter(|f| !f.is_empty())
+            .map(String::as_str)
+            .collect::<Vec<&str>>();
+        text.push_str(&fields.join(" | "));
+        text.push('\n');
+    }
+    text
+}
+
+/// Renders jobs as a Markdown table.
+pub fn to_markdown(jobs: &[Job]) -> String {
+    let cell = |s: &str| s.replace('|', "\\|").replace('\n', " ");
+    let mut md = String::from(
+        "| Title | Company | Location | Remuneration | Date Posted | Apply |\n|---|---|---|---|---|---|\n",
+    );
+    for job in jobs {
+        let apply = match job.apply.as_str() {
+            "" => String::new(),
+            apply => format!("[Apply]({})", apply.replace(' ', "%20").replace(')', "%29")),
+        };
+        md.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} |\n",
+            cell(&job.title),
+            cell(&job.company),
+            cell(&job.location),
+            cell(&job.remuneration),
+            cell(&job.date_posted),
+            apply,
+        ));
+    }
+    md
+}
+
+/// Renders jobs as a standalone HTML page with a table that can be sorted by clicking a heading.
+pub fn to_html(jobs: &[Job]) -> String {
+    let mut rows = String::new();
+    for job in jobs {
+        let apply = match job.apply.as_str() {
+            "" => String::new(),
+            apply => format!(
+                r#"<a href="{}" target="_blank" rel="noopener">{}</a>"#,
+                escape_html(apply),
+                if job.apply_is_fallback() {
+                    "Listing"
+                } else {
+                    "Apply"
+                }
+            ),
+        };
+        let logo = match &job.logo_url {
+            Some(logo_url) => format!(
+                r#"<img src="{}" alt="" loading="lazy">"#,
+                escape_html(logo_url)
+            ),
+            None => String::new(),
+        };
+        rows.push_str(&format!(
+            "<tr><td>{logo}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            escape_html(&job.title),
+            escape_html(&job.company),
+            escape_html(&job.location),
+            escape_html(&job.remuneration),
+            escape_html(&job.date_posted),
+            apply,
+            escape_html(&job.site),
+        ));
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Job Hunt</title>
+{STYLE}
+</head>
+<body>
+<h1>Job Hunt</h1>
+<p>{} jobs</p>
+<table>
+<thead><tr><th></th><th>Title</th><th>Company</th><th>Location</th><th>Remuneration</th><th>Date Posted</th><th>Apply</th><th>Site</th></tr></thead>
+<tbody>
+{rows}</tbody>
+</table>
+{SORT_SCRIPT}
+</body>
+</html>
+"#,
+        jobs.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repository::Job;
+
+    use crate::config::Format;
+
+    use super::{
+        escape_html, parse_columns, render, render_columns, to_csv, to_html, to_markdown, to_rss,
+        to_text,
+    };
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html(r#"<b>R&D</b> "Lead" Rust's"#),
+            "&lt;b&gt;R&amp;D&lt;/b&gt; &quot;Lead&quot; Rust&#39;s"
+        );
+    }
+
+    fn job() -> Job {
+        Job {
+            title: "Rust Engineer".to_string(),
+            company: "Acme | Co".to_string(),
+            location: "Remote".to_string(),
+            apply: "https://acme.com/jobs/1".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_to_text() {
+        assert_eq!(
+            to_text(&[job()]),
+            "Rust Engineer | Acme | Co | Remote | https://acme.com/jobs/1\n"
+        );
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let md = to_markdown(&[job()]);
+        assert!(md.ends_with(
+            "| Rust Engineer | Acme \\| Co | Remote |  |  | [Apply](https://acme.com/jobs/1) |\n"
+        ));
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let mut job = job();
+        job.title = "Rust Engineer, \"Core\"".to_string();
+        job.tags = vec!["rust".to_string(), "p2p".to_string()];
+        assert_eq!(
+            to_csv(&[job]).lines().nth(1).unwrap(),
+            r#""Rust Engineer, ""Core""",Acme | Co,,Remote,,,rust;p2p,https://acme.com/jobs/1,"#
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut job = job();
+        job.tags = vec!["rust".to_string(), "p2p".to_string()];
+        let json = render(&[job.clone(), job], Format::Json).unwrap();
+        // pretty-printed, as an array with every field of each job
+        assert!(json.starts_with("[\n  {\n"));
+        let jobs = serde_json::from_str::<Vec<serde_json::Value>>(&json).unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0]["title"], "Rust Engineer");
+        assert_eq!(jobs[0]["tags"], serde_json::json!(["rust", "p2p"]));
+        assert!(jobs[0].get("source_url").is_some());
+    }
+
+    #[test]
+    fn test_render_columns() {
+        let mut job = job();
+        job.tags = vec!["rust".to_string(), "p2p".to_string()];
+        job.rem_upper = Some(140000);
+        let columns = parse_columns("Apply, title,tags ,rem_upper,currency").unwrap();
+        assert_eq!(
+            render_columns(&[job.clone()], Format::Csv, &columns).unwrap(),
+            "apply,title,tags,rem_upper,currency\nhttps://acme.com/jobs/1,Rust Engineer,rust;p2p,140000,\n"
+        );
+        let json = render_columns(&[job], Format::Json, &columns[..2]).unwrap();
+        assert_eq!(
+            json.split_whitespace().collect::<String>(),
+            r#"[{"apply":"https://acme.com/jobs/1","title":"RustEngineer"}]"#
+        );
+        assert!(parse_columns("title,salary").is_err());
+    }
+
+    #[test]
+    fn test_to_rss() {
+        let mut job = job();
+        job.date_posted = "2024-05-06".to_string();
+        let rss = to_rss(&[job]);
+        assert!(rss.contains("<title>Rust Engineer at Acme | Co</title>"));
+        assert!(rss.contains("<link>https://acme.com/jobs/1</link>"));
+        assert!(rss.contains("<pubDate>Mon, 06 May 2024 00:00:00 +0000</pubDate>"));
+    }
+
+    #[test]
+    fn test_to_html() {
+        let job = Job {
+            title: "Rust <Engineer>".to_string(),
+            company: "Acme & Co".to_string(),
+            apply: "https://acme.com/jobs?id=1&src=jh".to_string(),
+            site: "https://web3.career".to_string(),
+            ..Default::default()
+        };
+        let html = to_html(&[job]);
+        assert!(html.contains("<td></td><td>Rust &lt;Engineer&gt;</td><td>Acme &amp; Co</td>"));
+        assert!(html.contains(r#"<a href="https://acme.com/jobs?id=1&amp;src=jh""#));
+        assert!(html.contains("<p>1 jobs</p>"));
+    }
+}
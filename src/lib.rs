@@ -1,21 +1,36 @@
 use thiserror::Error;
 
+pub mod classify;
+pub mod config;
+pub mod doctor;
+pub mod export;
+pub mod lock;
 pub mod repl;
 pub mod repository;
 pub mod scraper;
+pub mod selectors;
 pub mod site;
+#[cfg(test)]
+mod test_server;
 
+/// Prints a status message, unless in quiet mode.
 #[macro_export]
 macro_rules! green_println {
     ($msg:expr) => {{
-        println!("{}", $msg.bold().green())
+        if !$crate::config::quiet() {
+            println!("{}", $msg.bold().green())
+        }
     }};
 }
 
+/// Prints an error or warning message - to stderr in quiet mode, so stdout only has results.
 #[macro_export]
 macro_rules! red_println {
     ($msg:expr) => {{
-        println!("{}", $msg.bold().red())
+        match $crate::config::quiet() {
+            true => eprintln!("{}", $msg.bold().red()),
+            false => println!("{}", $msg.bold().red()),
+        }
     }};
 }
 
@@ -24,8 +39,26 @@ pub enum ErrorKind {
     #[error("Error retrieving selector group. {0}")]
     Selector(String),
 
+    #[error("Request to '{0}' timed out.")]
+    RequestTimeout(String),
+
+    #[error("Request to '{0}' failed with status code {1}.")]
+    RequestStatus(String, u16),
+
     #[error("Error making request to '{0}'. {1}")]
-    Request(String, String),
+    RequestTransport(String, String),
+
+    #[error("Request to '{0}' was blocked by a bot challenge (e.g. Cloudflare) so no jobs could be scraped.")]
+    Challenged(String),
+
+    #[error("Request to '{0}' returned an empty or near-empty document ({1} bytes), so no jobs could be scraped.")]
+    EmptyDocument(String, usize),
+
+    #[error("The scraper panicked. {0}")]
+    Panicked(String),
+
+    #[error("Every site failed to scrape, so no jobs were added. {0}")]
+    AllSitesFailed(String),
 
     #[error("Error decoding HTML. {0}")]
     Decode(String),
@@ -36,9 +69,47 @@ pub enum ErrorKind {
     #[error("Error querying DB. {0}")]
     SqliteQuery(String),
 
+    #[error(
+        "The local database is corrupted ({0}). Back it up and create a fresh one to continue."
+    )]
+    CorruptDb(String),
+
     #[error("Error serialising/deserialising tags array: {0}")]
     Serialisation(String),
 
     #[error("Error initialising REPL: {0}")]
     Repl(String),
+
+    #[error("Error loading sites file. {0}")]
+    SitesFile(String),
+
+    #[error("Error loading config file. {0}")]
+    ConfigFile(String),
+
+    #[error("Invalid setting. {0}")]
+    Setting(String),
+
+    #[error("Error exporting jobs. {0}")]
+    Export(String),
+
+    #[error("Error copying to the clipboard - is a clipboard available in this session? {0}")]
+    Clipboard(String),
+
+    #[error("Error taking snapshot. {0}")]
+    Snapshot(String),
+
+    #[error("Error locking instance. {0}")]
+    Lock(String),
+}
+
+impl ErrorKind {
+    /// Returns true for request errors that may succeed if retried - timeouts, transport errors,
+    /// and 429 or 5xx status codes.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::RequestTimeout(_) | Self::RequestTransport(_, _) => true,
+            Self::RequestStatus(_, code) => *code == 429 || *code >= 500,
+            _ => false,
+        }
+    }
 }
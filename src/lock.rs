@@ -0,0 +1,107 @@
+//! A lock file that stops two jobhunt processes from writing to the same DB at the same time. It's
+//! keyed to the DB, so it doesn't cover the REPL history file, which is shared by every REPL run
+//! in the same directory.
+//!
+//! The lock is an advisory OS file lock rather than the existence of the file, so it is released
+//! whenever the process exits - including on Ctrl-C or a crash - and a stale lock file is harmless.
+
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+use fs2::FileExt;
+
+use crate::repository::MEMORY_DB;
+use crate::ErrorKind;
+
+/// Returns the lock file held while jobhunt is running on a DB, next to it - e.g. "jobs.db.lock"
+/// for "jobs.db". An in-memory DB is private to its process, so it has no lock file.
+pub fn lock_path(db_path: &Path) -> Option<PathBuf> {
+    if db_path.to_str() == Some(MEMORY_DB) {
+        return None;
+    }
+    let mut path = db_path.as_os_str().to_owned();
+    path.push(".lock");
+    Some(PathBuf::from(path))
+}
+
+/// An exclusive lock on the lock file, released when dropped.
+#[derive(Debug)]
+pub struct InstanceLock {
+    file: File,
+}
+
+impl InstanceLock {
+    /// Acquires the lock at the path. If another process holds it, this either blocks until it is
+    /// released (`wait`) or fails immediately.
+    pub fn acquire(path: &Path, wait: bool) -> Result<Self, ErrorKind> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .map_err(|e| ErrorKind::Lock(format!("Unable to open '{}': {e}", path.display())))?;
+        let locked = if wait {
+            file.lock_exclusive()
+        } else {
+            file.try_lock_exclusive()
+        };
+        match locked {
+            Ok(()) => Ok(Self { file }),
+            Err(e) if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() => {
+                Err(ErrorKind::Lock(format!(
+                    "Another jobhunt instance is running on this DB (lock file '{}'). \
+                     Exit it first, or start with --wait to wait for it.",
+                    path.display()
+                )))
+            }
+            Err(e) => Err(ErrorKind::Lock(format!(
+                "Unable to lock '{}': {e}",
+                path.display()
+            ))),
+        }
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process;
+
+    use std::path::{Path, PathBuf};
+
+    use super::{lock_path, InstanceLock};
+
+    #[test]
+    fn test_lock_path() {
+        assert_eq!(
+            lock_path(Path::new("jobs.db")),
+            Some(PathBuf::from("jobs.db.lock"))
+        );
+        assert_eq!(
+            lock_path(Path::new("/data/jobhunt/jobs.db")),
+            Some(PathBuf::from("/data/jobhunt/jobs.db.lock"))
+        );
+        assert_eq!(lock_path(Path::new(":memory:")), None);
+    }
+
+    #[test]
+    fn test_held_lock() {
+        let path = env::temp_dir().join(format!("jobhunt-test-{}.lock", process::id()));
+
+        let lock = InstanceLock::acquire(&path, false).unwrap();
+        let err = InstanceLock::acquire(&path, false).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Another jobhunt instance is running"));
+
+        drop(lock);
+        assert!(InstanceLock::acquire(&path, false).is_ok());
+        std::fs::remove_file(path).ok();
+    }
+}
@@ -1,12 +1,213 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand};
 use colored::Colorize;
+use tracing_subscriber::EnvFilter;
 
-use jobhunt::red_println;
+use jobhunt::config::{ConfigFile, Format};
+use jobhunt::lock::{lock_path, InstanceLock};
 use jobhunt::repl::Repl;
 use jobhunt::repository::SoftwareJobs;
+use jobhunt::site::SitesFile;
+use jobhunt::{config, doctor, export, green_println, red_println, ErrorKind};
+
+/// A locally run recent job aggregator with a SQLite database and REPL.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// A TOML config file to load instead of $XDG_CONFIG_HOME/jobhunt/config.toml.
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// A TOML or JSON file defining additional job sites to scrape.
+    #[arg(long, global = true)]
+    sites_file: Option<PathBuf>,
+
+    /// Wait for another running instance to exit, rather than exiting straight away.
+    #[arg(long, global = true)]
+    wait: bool,
+
+    /// Only print results: suppress status messages and print errors to stderr.
+    #[arg(long, short, global = true)]
+    quiet: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Refresh the database, export the jobs for a query to a file and exit.
+    Export(ExportArgs),
+    /// Check the setup, and with --deep that each site still scrapes properly.
+    Doctor(DoctorArgs),
+}
+
+#[derive(Args)]
+struct DoctorArgs {
+    /// Also scrape each site and check its jobs' key fields are populated.
+    #[arg(long)]
+    deep: bool,
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    /// The export format: csv, json, html, rss, markdown or text.
+    #[arg(long)]
+    format: Format,
+
+    /// The file to write the export to.
+    #[arg(long)]
+    out: PathBuf,
+
+    /// The columns to export, e.g. "title,company,apply" (csv and json only). All are exported by
+    /// default.
+    #[arg(long)]
+    columns: Option<String>,
+
+    /// The "select jobs ..." query selecting the jobs to export.
+    #[arg(long, default_value = "select jobs")]
+    query: String,
+
+    /// Export the existing database as-is, without refreshing it first.
+    #[arg(long)]
+    no_refresh: bool,
+
+    /// Print errors to stderr as JSON objects, e.g. {"error": "..."}.
+    #[arg(long)]
+    json_errors: bool,
+}
+
+/// Runs an export without entering the REPL. Returns the number of jobs exported.
+async fn export(args: &ExportArgs) -> Result<usize, ErrorKind> {
+    let columns = args
+        .columns
+        .as_deref()
+        .map(export::parse_columns)
+        .transpose()?;
+    let db_path = config::get().db_path;
+    SoftwareJobs::check_integrity(&db_path)?;
+    if args.no_refresh && !db_path.exists() {
+        Err(ErrorKind::SqliteConnection(
+            "No local database found. Run an export without --no-refresh to create one."
+                .to_string(),
+        ))?;
+    }
+    // the refresh writes through the connection the jobs are exported from, so an in-memory DB
+    // works too
+    let mut conn = SoftwareJobs::open_db()?;
+    if !args.no_refresh {
+        SoftwareJobs::init_repo(&mut conn, true).await?;
+    }
+    SoftwareJobs::export_jobs(
+        &conn,
+        &args.query,
+        args.format,
+        columns.as_deref(),
+        &args.out,
+    )
+}
+
+/// Runs the self-checks, printing a line per check. Returns true if they all passed.
+async fn doctor(args: &DoctorArgs) -> bool {
+    let mut passed = true;
+    for check in doctor::check_setup() {
+        passed &= check.result.is_ok();
+        println!("{check}");
+    }
+    if args.deep {
+        for site in doctor::check_sites().await {
+            passed &= site.passed();
+            println!("{site}");
+        }
+    }
+    passed
+}
 
 #[tokio::main]
-async fn main() {
-    if let Err(err) = SoftwareJobs::init_repl().await {
-        red_println!(err.to_string());
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    // logs go to stderr, apart from the REPL's output, and are off unless RUST_LOG is set (e.g.
+    // RUST_LOG=jobhunt=debug)
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("off")),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+    let json_errors = matches!(&cli.command, Some(Command::Export(args)) if args.json_errors);
+    let fail = |err: ErrorKind| {
+        match json_errors {
+            true => eprintln!("{}", serde_json::json!({ "error": err.to_string() })),
+            false => red_println!(err.to_string()),
+        }
+        ExitCode::FAILURE
+    };
+
+    // settings come from the config file, then environment variables, then flags
+    let mut sites_file = cli.sites_file.clone();
+    let config_path = cli
+        .config
+        .clone()
+        .or_else(|| ConfigFile::default_path().filter(|path| path.exists()));
+    if let Some(path) = config_path {
+        match ConfigFile::load(&path).and_then(|file| file.apply().map(|()| file)) {
+            Ok(file) => sites_file = sites_file.or(file.sites_file),
+            Err(err) => return fail(err),
+        }
+    }
+    if let Err(err) = config::apply_env() {
+        return fail(err);
+    }
+    if cli.quiet {
+        config::update(|c| c.quiet = true);
+    }
+    // only the REPL and refreshing exports write to the DB, so reads (doctor and exports with
+    // --no-refresh) can run alongside another instance. Held until main returns
+    let writes = match &cli.command {
+        Some(Command::Export(args)) => !args.no_refresh,
+        Some(Command::Doctor(_)) => false,
+        None => true,
+    };
+    let _lock = match lock_path(&config::get().db_path)
+        .filter(|_| writes)
+        .map(|path| InstanceLock::acquire(&path, cli.wait))
+        .transpose()
+    {
+        Ok(lock) => lock,
+        Err(err) => return fail(err),
+    };
+    if let Some(path) = sites_file {
+        match SitesFile::load(&path) {
+            Ok(file) => config::update(|c| {
+                c.site_headers = file.site_headers();
+                c.custom_sites = file.sites;
+                if !file.common_boards.is_empty() {
+                    c.common_boards = file.common_boards;
+                }
+                c.site_groups = file.groups;
+                c.selector_overrides = file.selectors;
+            }),
+            Err(err) => return fail(err),
+        }
+    }
+
+    match cli.command {
+        Some(Command::Export(args)) => match export(&args).await {
+            Ok(cnt) => {
+                green_println!(format!("{cnt} jobs exported to {}.", args.out.display()));
+                ExitCode::SUCCESS
+            }
+            Err(err) => fail(err),
+        },
+        Some(Command::Doctor(args)) => match doctor(&args).await {
+            true => ExitCode::SUCCESS,
+            false => ExitCode::FAILURE,
+        },
+        None => match SoftwareJobs::init_repl().await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => fail(err),
+        },
     }
 }
@@ -1,11 +1,371 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, BufRead, IsTerminal};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use arboard::Clipboard;
 use chrono::Local;
-use colored::Colorize;
-use rusqlite::Connection;
+use colored::{Color, Colorize};
+use itertools::Itertools;
+use regex::{Captures, Regex};
+use rusqlite::types::Value;
+use rusqlite::{Connection, OpenFlags};
+use rustyline::completion::Completer;
 use rustyline::error::ReadlineError;
-use rustyline::DefaultEditor;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, ExternalPrinter, Helper};
+
+use crate::config::Format;
+use crate::repository::{search_terms, Job, JobsDbBuilder, SalaryRange, SalaryStats, SoftwareJobs};
+use crate::{classify, config, export, green_println, red_println, scraper, ErrorKind};
+
+/// The jobs the last refresh found, which queries are run over unless they ask for all the jobs -
+/// jobs a refresh no longer finds are kept, but flagged stale.
+const LIVE_JOBS: &str = "(select * from jobs where not stale)";
+
+/// The key fields checked by the "incomplete" command, with the condition for each being missing.
+const INCOMPLETE_CONDITIONS: [(&str, &str); 4] = [
+    ("remuneration", "remuneration = ''"),
+    ("location", "location = ''"),
+    ("apply", "apply = ''"),
+    ("tags", "tags = '[]'"),
+];
+
+/// The query run by the "closing soon" command - jobs whose deadline is in the next week, soonest
+/// first.
+const CLOSING_SOON_QUERY: &str = "select jobs where deadline between date('now', 'localtime') \
+    and date('now', 'localtime', '+7 days') order by deadline asc";
+
+/// The query run by the "select jobs new" command - jobs first seen by a refresh in the last 24
+/// hours, newest first.
+const NEW_JOBS_QUERY: &str = "select jobs where first_seen >= datetime('now', 'localtime', \
+    '-1 day') order by first_seen desc";
+
+/// The query run by the "select jobs remote" command.
+const REMOTE_JOBS_QUERY: &str = "select jobs where is_remote";
+
+/// The command listing the jobs saved as favorites with "save <n>", most recently saved first.
+/// It can be used wherever a "select jobs ..." query can, e.g. by "export" after it's run.
+const FAVORITES_QUERY: &str = "select favorites";
+
+/// The start of a "select jobs tagged rust,remote" query, listing the jobs with all the tags.
+const TAGGED_PREFIX: &str = "select jobs tagged ";
+
+/// Parses the comma separated tags of a "select jobs tagged ..." query, lowercased.
+fn parse_tags(tags: &str) -> Result<Vec<String>, ErrorKind> {
+    let tags = tags
+        .trim()
+        .trim_end_matches(';')
+        .split(',')
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect::<Vec<String>>();
+    match tags.is_empty() {
+        true => Err(ErrorKind::SqliteQuery(
+            "Give the tags to match, e.g. \"select jobs tagged rust,remote\".".to_string(),
+        )),
+        false => Ok(tags),
+    }
+}
+
+/// The sort commands, each with the ordering of the jobs it lists. Jobs without a salary have no
+/// upper bound, so "toppay" lists them last.
+const SORT_COMMANDS: &[(&str, &str)] = &[
+    ("latest", "order by date_posted desc"),
+    ("oldest", "order by date_posted asc"),
+    ("toppay", "order by rem_upper desc nulls last"),
+];
+
+/// Returns true if a line is a sort command, e.g. "latest" or "toppay 10".
+fn is_sort_command(l: &str) -> bool {
+    let command = l.split_whitespace().next().unwrap_or_default();
+    SORT_COMMANDS.iter().any(|(name, _)| *name == command)
+}
+
+/// Expands a sort command, optionally followed by a limit (e.g. "latest 10"), into its "select
+/// jobs ..." query.
+fn sort_query(l: &str) -> Result<String, ErrorKind> {
+    let (command, limit) = l.trim().split_once(' ').unwrap_or((l.trim(), ""));
+    let Some((_, order)) = SORT_COMMANDS.iter().find(|(name, _)| *name == command) else {
+        Err(ErrorKind::Repl(format!(
+            "\"{command}\" isn't a sort command."
+        )))?
+    };
+    match limit.trim() {
+        "" => Ok(format!("select jobs {order}")),
+        limit => match limit.parse::<usize>() {
+            Ok(limit) => Ok(format!("select jobs {order} limit {limit}")),
+            Err(_) => Err(ErrorKind::Repl(format!(
+                "\"{command}\" takes a number of jobs to list, e.g. \"{command} 10\", got \"{limit}\"."
+            ))),
+        },
+    }
+}
+
+/// Splits two lists of jobs into the jobs only in the first, the jobs only in the second and the
+/// jobs in both, matched by dedup key.
+fn compare_jobs<'a>(a: &'a [Job], b: &'a [Job]) -> (Vec<&'a Job>, Vec<&'a Job>, Vec<&'a Job>) {
+    let a_keys = a.iter().map(Job::dedup_key).collect::<HashSet<String>>();
+    let b_keys = b.iter().map(Job::dedup_key).collect::<HashSet<String>>();
+    let (both, only_a) = a.iter().partition(|job| b_keys.contains(&job.dedup_key()));
+    let only_b = b
+        .iter()
+        .filter(|job| !a_keys.contains(&job.dedup_key()))
+        .collect();
+    (only_a, only_b, both)
+}
+
+/// The maximum number of companies listed by the "trend companies" command.
+const MAX_COMPANY_TRENDS: usize = 20;
+
+/// The maximum number of companies listed by the "like-company" command.
+const MAX_RELATED_COMPANIES: usize = 10;
+
+/// The number of matched elements whose text is shown by the "test-selectors" command.
+const SELECTOR_SAMPLES: usize = 5;
+
+/// The columns whose stored values are hinted after e.g. `where site = `.
+const HINT_COLUMNS: &[&str] = &[
+    "site",
+    "company",
+    "location",
+    "seniority",
+    "work_mode",
+    "employment_type",
+    "currency",
+    "primary_language",
+    "category",
+];
+
+/// The maximum number of values hinted for a column - the most common values are kept.
+const MAX_HINT_VALUES: usize = 50;
+
+/// Hints the values stored in a column as a filter on it is typed, e.g. `'https://web3.career'`
+/// after `select jobs where site = `. Each column's values are fetched once, when first needed,
+/// through the session's connection.
+struct QueryHelper {
+    conn: Arc<Mutex<Connection>>,
+    values: RefCell<HashMap<String, Vec<String>>>,
+}
+
+impl QueryHelper {
+    fn new(conn: Arc<Mutex<Connection>>) -> Self {
+        Self {
+            conn,
+            values: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the most common values of a column, from the cache if they've been fetched before.
+    /// There are none while the connection is locked (e.g. while a refresh writes its jobs, or at
+    /// a prompt of a command being run), rather than waiting for it.
+    fn column_values(&self, column: &str) -> Vec<String> {
+        if let Some(values) = self.values.borrow().get(column) {
+            return values.clone();
+        }
+        let Ok(conn) = self.conn.try_lock() else {
+            return Vec::new();
+        };
+        let values = fetch_column_values(&conn, column).unwrap_or_default();
+        self.values
+            .borrow_mut()
+            .insert(column.to_string(), values.clone());
+        values
+    }
+
+    /// Forgets the fetched values, e.g. after the jobs have been refreshed.
+    fn clear(&self) {
+        self.values.borrow_mut().clear()
+    }
+}
+
+/// Returns the most common non-empty values of a column of the jobs table.
+fn fetch_column_values(conn: &Connection, column: &str) -> Result<Vec<String>, ErrorKind> {
+    conn.prepare(&format!(
+        "select {column} from jobs where not stale and {column} != '' group by {column} \
+        order by count(*) desc limit {MAX_HINT_VALUES}"
+    ))
+    .and_then(|mut stmt| {
+        stmt.query_map((), |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()
+    })
+    .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))
+}
+
+/// The SQL keywords allowed in the clauses of a "select jobs ..." query. Any other word must be a
+/// column of the jobs table or a function call.
+const QUERY_KEYWORDS: &[&str] = &[
+    "where", "order", "by", "limit", "offset", "asc", "desc", "nulls", "first", "last", "and",
+    "or", "not", "like", "glob", "in", "is", "null", "between", "escape", "collate", "nocase",
+    "case", "when", "then", "else", "end", "cast", "as", "integer", "real", "text", "true",
+    "false",
+];
+
+/// Words that can't appear in a query, since they'd change the database or read other tables.
+const DISALLOWED_QUERY_WORDS: &[&str] = &[
+    "drop",
+    "delete",
+    "insert",
+    "update",
+    "alter",
+    "create",
+    "attach",
+    "detach",
+    "pragma",
+    "vacuum",
+    "reindex",
+    "select",
+    "union",
+    "into",
+    "transaction",
+    "commit",
+    "rollback",
+];
+
+/// Matches a string literal in a query, quoted with ' or ".
+static STRING_LITERAL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"'(?:[^']|'')*'|"(?:[^"]|"")*""#).unwrap());
+
+/// Splits a query at its "order by relevance" clause - the last one outside a string literal, so
+/// e.g. "where title like '%order by relevance%'" isn't split - into the query before the clause
+/// and whatever follows it.
+fn split_relevance(l: &str) -> Option<(&str, &str)> {
+    const CLAUSE: &str = "order by relevance";
+    // literals are blanked out with the same number of bytes, so the clause is found at the same
+    // position in the query
+    let blanked = STRING_LITERAL.replace_all(l, |c: &Captures| "_".repeat(c[0].len()));
+    let i = blanked.rfind(CLAUSE)?;
+    Some((&l[..i], &l[i + CLAUSE.len()..]))
+}
 
-use crate::repository::{Job, SoftwareJobs};
-use crate::{green_println, red_println, ErrorKind};
+/// Checks a "select jobs ..." query and returns it as SQL. Only a where, order by and limit clause
+/// can follow "select jobs", naming the jobs table's columns - so a query can't change the
+/// database (e.g. "select jobs; drop table jobs") or read another table. A trailing ";" is fine.
+/// Stale jobs (those the last refresh didn't find) are left out, unless the query starts with
+/// "select jobs all".
+fn jobs_query_sql(l: &str) -> Result<String, ErrorKind> {
+    static WORD: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\b([a-z_][a-z0-9_]*)\b(\s*\()?").unwrap());
+    let disallowed = |reason: String| {
+        ErrorKind::SqliteQuery(format!(
+            "\"{l}\" isn't an allowed query - {reason}. Queries are \"select jobs\", optionally followed by where, order by and limit clauses."
+        ))
+    };
+    let rest = l
+        .trim()
+        .strip_prefix("select jobs")
+        .ok_or_else(|| disallowed("it doesn't start with \"select jobs\"".to_string()))?
+        .trim();
+    let rest = rest.strip_suffix(';').unwrap_or(rest).trim_end();
+    let (table, rest) = match rest.strip_prefix("all") {
+        Some(r) if r.is_empty() || r.starts_with(char::is_whitespace) => ("jobs", r.trim_start()),
+        _ => (LIVE_JOBS, rest),
+    };
+    // string literals can hold anything, so they're left out of the checks
+    let clauses = STRING_LITERAL.replace_all(rest, "''").to_lowercase();
+    if clauses.contains(';') {
+        return Err(disallowed(
+            "only one query can be run at a time".to_string(),
+        ));
+    }
+    if !(clauses.is_empty()
+        || ["where ", "order by ", "limit "]
+            .iter()
+            .any(|clause| clauses.starts_with(clause)))
+    {
+        return Err(disallowed(
+            "only a where, order by or limit clause can follow \"select jobs\"".to_string(),
+        ));
+    }
+    let columns = Job::column_names();
+    for caps in WORD.captures_iter(&clauses) {
+        let word = &caps[1];
+        if DISALLOWED_QUERY_WORDS.contains(&word) {
+            return Err(disallowed(format!("\"{word}\" isn't allowed")));
+        }
+        let is_call = caps.get(2).is_some();
+        if !is_call && !QUERY_KEYWORDS.contains(&word) && !columns.contains(&word) {
+            return Err(disallowed(format!(
+                "\"{word}\" isn't a column of the jobs table"
+            )));
+        }
+    }
+    Ok(format!("select * from {table} {rest}"))
+}
+
+/// Returns the rest of the first value that completes a filter at the end of the line, e.g.
+/// `eb3.career'` for `where site = 'https://w`. The quote is added if it hasn't been typed.
+fn hint_value<F>(line: &str, values: F) -> Option<String>
+where
+    F: Fn(&str) -> Vec<String>,
+{
+    static FILTER: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r#"(?i)\b(\w+)\s*(?:=|!=|like)\s*(['"]?)([^'"]*)$"#).unwrap());
+    let caps = FILTER.captures(line)?;
+    let column = caps[1].to_lowercase();
+    if !HINT_COLUMNS.contains(&column.as_str()) {
+        return None;
+    }
+    let (quote, typed) = (&caps[2], &caps[3]);
+    if quote.is_empty() && !typed.is_empty() {
+        return None;
+    }
+    let typed_lower = typed.to_lowercase();
+    let value = values(&column)
+        .into_iter()
+        .find(|v| v.to_lowercase().starts_with(&typed_lower) && v.len() > typed.len())?;
+    match quote {
+        "" => Some(format!("'{value}'")),
+        quote => Some(format!("{}{quote}", value.get(typed.len()..)?)),
+    }
+}
+
+impl Hinter for QueryHelper {
+    type Hint = String;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        if pos < line.len() {
+            return None;
+        }
+        hint_value(line, |column| self.column_values(column))
+    }
+}
+
+impl Highlighter for QueryHelper {
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(hint.dimmed().to_string())
+    }
+}
+
+impl Completer for QueryHelper {
+    type Candidate = String;
+}
+
+impl Validator for QueryHelper {}
+
+impl Helper for QueryHelper {}
+
+fn refresh_completed_message() -> String {
+    format!(
+        "Refresh completed successfully at {}",
+        Local::now().format("%d-%m-%Y %H:%M:%S")
+    )
+}
+
+/// Returns the number of jobs stored in the DB, which is 0 if it has no jobs table yet.
+fn stored_jobs(conn: &Connection) -> i64 {
+    conn.query_row("select count(*) from jobs", (), |row| row.get(0))
+        .unwrap_or(0)
+}
 
 /// This trait must be implemented by the specific job repo struct to be used in Job Hunt (e.g. SoftwareJobs).
 #[allow(async_fn_in_trait)]
@@ -14,76 +374,1315 @@ pub trait Repl {
     /// initializes the REPL and parses queries.
     async fn init_repl() -> Result<(), ErrorKind>;
 
-    fn select_and_display_jobs(conn: Connection, l: String) -> Result<(), ErrorKind> {
-        let query = l.replace("select jobs", "select * from jobs");
+    /// Returns the jobs for a "select jobs ..." query.
+    fn select_jobs(conn: &Connection, l: &str) -> Result<Vec<Job>, ErrorKind> {
+        Ok(Self::select_job_rows(conn, l)?
+            .into_iter()
+            .map(|(_, job)| job)
+            .collect())
+    }
+
+    /// Returns the jobs for a "select jobs ..." query with their row ids. The query may end with
+    /// "order by relevance" (and optionally a limit) to rank jobs by their relevance score.
+    fn select_job_rows(conn: &Connection, l: &str) -> Result<Vec<(i64, Job)>, ErrorKind> {
+        if let Some(tags) = l.trim().strip_prefix(TAGGED_PREFIX) {
+            let tags = parse_tags(tags)?;
+            let mut jobs = Self::select_job_rows(conn, "select jobs")?;
+            jobs.retain(|(_, job)| job.has_tags(&tags));
+            return Ok(jobs);
+        }
+        if let Some((query, rest)) = split_relevance(l) {
+            let rest = rest.trim().trim_end_matches(';').trim();
+            let limit = match rest {
+                "" => None,
+                rest => match rest
+                    .strip_prefix("limit ")
+                    .map(|n| n.trim().parse::<usize>())
+                {
+                    Some(Ok(n)) => Some(n),
+                    _ => Err(ErrorKind::SqliteQuery(format!(
+                        "Only a limit can follow \"order by relevance\", got \"{rest}\"."
+                    )))?,
+                },
+            };
+            let weights = config::get().relevance;
+            let mut jobs = Self::select_job_rows(conn, query.trim())?;
+            jobs.sort_by(|(_, a), (_, b)| b.relevance(&weights).total_cmp(&a.relevance(&weights)));
+            jobs.truncate(limit.unwrap_or(jobs.len()));
+            return Ok(jobs);
+        }
+
+        if l.trim() == FAVORITES_QUERY {
+            return SoftwareJobs::favorite_rows(conn);
+        }
+        let query = jobs_query_sql(l)?;
         let mut stmt = conn
             .prepare(&query)
             .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
 
         let jobs = stmt
+            .query_map((), |row| Ok((row.get("id")?, Job::from_row(row)?)))
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?
+            .collect::<Result<Vec<(i64, Job)>, _>>()
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        Ok(jobs)
+    }
+
+    /// Displays the jobs for a query, numbered from 1, grouped by recency if that's turned on.
+    /// Returns the row ids of the displayed jobs, in order, so a job can be referred to by its
+    /// number.
+    fn select_and_display_jobs(conn: &Connection, l: String) -> Result<Vec<i64>, ErrorKind> {
+        let mut jobs = Self::select_job_rows(conn, &l)?;
+        let terms = search_terms(&l);
+        let group_by_recency = config::get().group_by_recency;
+        if group_by_recency {
+            // a stable sort, so the query's order is kept within each group
+            jobs.sort_by_key(|(_, job)| job.recency());
+        }
+        let mut group = None;
+        for (n, (_, job)) in jobs.iter().enumerate() {
+            if group_by_recency && group != Some(job.recency()) {
+                group = Some(job.recency());
+                println!(
+                    "{}",
+                    format!("== {} ==", job.recency()).bold().bright_cyan()
+                );
+            }
+            println!("{}", format!("#{}", n + 1).bold().bright_green());
+            println!("{:?}", job.highlighted(&terms));
+        }
+        green_println!(format!("{} jobs returned.", jobs.len()));
+        if let Some(stats) = SalaryStats::from_jobs(jobs.iter().map(|(_, job)| job)) {
+            green_println!(stats.to_string());
+        }
+
+        Ok(jobs.into_iter().map(|(id, _)| id).collect())
+    }
+
+    /// Runs the "select jobs ..." queries read line by line from a non-interactive input (e.g. a
+    /// pipe), displaying the results of each. Blank lines are skipped, and the other commands need
+    /// a terminal. Returns the number of queries run, or an error if any of them failed.
+    fn run_queries<R: BufRead>(conn: &Connection, input: R) -> Result<usize, ErrorKind> {
+        let (mut run, mut failed) = (0, 0);
+        for line in input.lines() {
+            let line = line.map_err(|e| ErrorKind::Repl(e.to_string()))?;
+            let l = line.trim().to_lowercase();
+            if l.is_empty() {
+                continue;
+            }
+            if l == "exit" {
+                break;
+            }
+            run += 1;
+            let res = match l.starts_with("select jobs") {
+                true => Self::select_and_display_jobs(conn, l).map(|_| ()),
+                false => Err(ErrorKind::Repl(format!(
+                    "\"{l}\" can only be entered in a terminal. Only \"select jobs ...\" queries are read from a pipe - or use the export subcommand with --query."
+                ))),
+            };
+            if let Err(err) = res {
+                red_println!(err.to_string());
+                failed += 1;
+            }
+        }
+        match failed {
+            0 => Ok(run),
+            _ => Err(ErrorKind::Repl(format!(
+                "{failed} of {run} queries failed."
+            ))),
+        }
+    }
+
+    /// Displays each column of the jobs table with its type, as reported by SQLite.
+    fn display_schema(conn: &Connection) -> Result<(), ErrorKind> {
+        let mut stmt = conn
+            .prepare("select name, type from pragma_table_info('jobs')")
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        let columns = stmt
             .query_map((), |row| {
-                let tags: String = row.get(6).unwrap();
-                let tags: Vec<String> = serde_json::from_str(&tags).unwrap();
-                Ok(Job {
-                    title: row.get(1)?,
-                    company: row.get(2)?,
-                    date_posted: row.get(3)?,
-                    location: row.get(4)?,
-                    remuneration: row.get(5)?,
-                    tags,
-                    apply: row.get(7)?,
-                    site: row.get(8)?,
-                    rem_upper: row.get(9)?,
-                    rem_lower: row.get(10)?,
-                })
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?
+            .collect::<Result<Vec<(String, String)>, _>>()
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        if columns.is_empty() {
+            Err(ErrorKind::SqliteQuery(
+                "The jobs table doesn't exist. Enter \"refresh\" to create it.".to_string(),
+            ))?;
+        }
+
+        let width = columns
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+        for (name, ty) in columns {
+            println!(
+                "{} {}",
+                format!("{name:<width$}").bold().bright_green(),
+                ty.green()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Displays the stored row for a job exactly as it is in the database, as column/value pairs.
+    fn display_raw_job(conn: &Connection, id: i64) -> Result<(), ErrorKind> {
+        let mut stmt = conn
+            .prepare("select * from jobs where id = ?1")
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        let names = stmt
+            .column_names()
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<String>>();
+        let values = stmt
+            .query_row([id], |row| {
+                (0..names.len())
+                    .map(|i| row.get::<_, Value>(i))
+                    .collect::<Result<Vec<Value>, _>>()
             })
             .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
 
-        let mut cnt = 0;
-        for job in jobs {
-            let job = job.map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
-            println!("{:?}", job);
-            cnt += 1
+        for (name, value) in names.iter().zip(values) {
+            let value = match value {
+                Value::Null => "NULL".to_string(),
+                Value::Integer(i) => i.to_string(),
+                Value::Real(r) => r.to_string(),
+                Value::Text(t) => t,
+                Value::Blob(b) => format!("<{} byte blob>", b.len()),
+            };
+            println!("{} {}", format!("{name}:").bold().bright_green(), value);
+        }
+
+        Ok(())
+    }
+
+    /// Writes the jobs for a "select jobs ..." query to a file in the given format, with only the
+    /// given columns if any are given (CSV and JSON only). Returns the number of jobs written.
+    fn export_jobs(
+        conn: &Connection,
+        l: &str,
+        format: Format,
+        columns: Option<&[String]>,
+        path: &Path,
+    ) -> Result<usize, ErrorKind> {
+        let query = l.trim().to_lowercase();
+        if !query.starts_with("select jobs") && query != FAVORITES_QUERY {
+            Err(ErrorKind::Export(format!(
+                "Only \"select jobs ...\" queries can be exported, got \"{l}\"."
+            )))?;
+        }
+        let jobs = Self::select_jobs(conn, &query)?;
+        let rendered = match columns {
+            Some(columns) => export::render_columns(&jobs, format, columns)?,
+            None => export::render(&jobs, format)?,
+        };
+        fs::write(path, rendered)
+            .map_err(|e| ErrorKind::Export(format!("Unable to write '{}': {e}", path.display())))?;
+        Ok(jobs.len())
+    }
+
+    /// Copies the jobs for a "select jobs ..." query to the clipboard in the current format. Returns
+    /// the number of jobs copied.
+    fn copy_jobs(conn: &Connection, l: &str) -> Result<usize, ErrorKind> {
+        let jobs = Self::select_jobs(conn, l)?;
+        let text = export::render(&jobs, config::get().format)?;
+        Clipboard::new()
+            .and_then(|mut clipboard| clipboard.set_text(text))
+            .map_err(|e| ErrorKind::Clipboard(e.to_string()))?;
+        Ok(jobs.len())
+    }
+
+    /// Displays jobs missing any of the key fields, grouped by field, with a count per site. A
+    /// single field name can be given to only check that field.
+    fn display_incomplete_jobs(conn: &Connection, field: &str) -> Result<(), ErrorKind> {
+        let fields = match field {
+            "" => INCOMPLETE_CONDITIONS.to_vec(),
+            field => INCOMPLETE_CONDITIONS
+                .iter()
+                .filter(|(f, _)| *f == field)
+                .copied()
+                .collect::<Vec<_>>(),
+        };
+        if fields.is_empty() {
+            Err(ErrorKind::SqliteQuery(format!(
+                "\"{field}\" is not a field that can be checked. Try one of: {}.",
+                INCOMPLETE_CONDITIONS.map(|(f, _)| f).join(", ")
+            )))?;
+        }
+
+        for (field, condition) in fields {
+            green_println!(format!("Jobs missing {field}:"));
+            Self::select_and_display_jobs(
+                conn,
+                format!("select jobs where {condition} order by site"),
+            )?;
+
+            let mut stmt = conn
+                .prepare(&format!(
+                    "select site, count(*) from jobs where not stale and {condition} group by site \
+                    order by site"
+                ))
+                .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+            let counts = stmt
+                .query_map((), |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+                })
+                .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+            for count in counts {
+                let (site, cnt) = count.map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+                green_println!(format!("  {site}: {cnt} jobs missing {field}"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Displays a job's timeline from the history table - when each version was scraped, and the
+    /// fields that changed from the version before.
+    fn display_job_history(conn: &Connection, dedup_key: &str) -> Result<(), ErrorKind> {
+        let history = SoftwareJobs::history(conn, dedup_key)?;
+        let Some((_, first)) = history.first() else {
+            green_println!(format!(
+                "No history for {dedup_key}. Enter \"set history on\" to keep every refresh."
+            ));
+            return Ok(());
+        };
+        println!("{} @ {}", first.title.bold(), first.company);
+        let mut prev: Option<&Job> = None;
+        for (scraped_at, job) in &history {
+            let fields = [
+                (
+                    "remuneration",
+                    &job.remuneration,
+                    prev.map(|p| &p.remuneration),
+                ),
+                (
+                    "date_posted",
+                    &job.date_posted,
+                    prev.map(|p| &p.date_posted),
+                ),
+                ("location", &job.location, prev.map(|p| &p.location)),
+                ("apply", &job.apply, prev.map(|p| &p.apply)),
+            ];
+            let changes = fields
+                .iter()
+                .filter_map(|(name, value, prev)| {
+                    prev.filter(|prev| prev != value)
+                        .map(|prev| format!("{name} {prev:?} → {value:?}"))
+                })
+                .collect::<Vec<String>>();
+            let summary = match (prev, changes.is_empty()) {
+                (None, _) => format!(
+                    "first seen, posted {}, {}",
+                    job.date_posted,
+                    if job.remuneration.is_empty() {
+                        "no salary"
+                    } else {
+                        &job.remuneration
+                    }
+                ),
+                (Some(_), true) => "unchanged".to_string(),
+                (Some(_), false) => changes.join(", ").yellow().to_string(),
+            };
+            println!("{}  {summary}", scraped_at.bright_green());
+            prev = Some(job);
+        }
+        Ok(())
+    }
+
+    /// Displays the companies whose postings share the most tags with a company's.
+    fn display_related_companies(conn: &Connection, company: &str) -> Result<(), ErrorKind> {
+        let profiles = SoftwareJobs::company_profiles(conn)?;
+        let Some(related) = SoftwareJobs::related_companies(&profiles, company) else {
+            red_println!(format!("No jobs found for {company}."));
+            return Ok(());
+        };
+        if related.is_empty() {
+            green_println!(format!("No companies share tags with {company}."));
+            return Ok(());
+        }
+        for company in related.iter().take(MAX_RELATED_COMPANIES) {
+            println!("{company}");
+        }
+        Ok(())
+    }
+
+    /// Displays how many elements a CSS selector matches on a page, with the text of the first few.
+    async fn display_selector_matches(url: &str, selector: &str) {
+        match scraper::test_selector(url, selector, SELECTOR_SAMPLES).await {
+            Ok(matches) => {
+                green_println!(format!(
+                    "{selector} matches {} element{} on {url}.",
+                    matches.count,
+                    if matches.count == 1 { "" } else { "s" }
+                ));
+                for (i, text) in matches.samples.iter().enumerate() {
+                    let text = if text.is_empty() { "(no text)" } else { text };
+                    println!("{}: {text}", i + 1);
+                }
+            }
+            Err(err) => red_println!(err.to_string()),
+        }
+    }
+
+    /// Displays the companies whose number of jobs changed the most between the two latest
+    /// snapshots in a directory.
+    fn display_company_trends(dir: &Path) -> Result<(), ErrorKind> {
+        let snapshots = SoftwareJobs::snapshots(dir)?;
+        let [.., before, after] = snapshots.as_slice() else {
+            green_println!(format!(
+                "Company trends need at least two snapshots in {}, but there {}. Enter \"snapshot\" to take one (one is kept per day).",
+                dir.display(),
+                match snapshots.len() {
+                    1 => "is 1".to_string(),
+                    n => format!("are {n}"),
+                }
+            ));
+            return Ok(());
+        };
+        let counts = |path: &Path| {
+            let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+                .map_err(|e| ErrorKind::SqliteConnection(e.to_string()))?;
+            SoftwareJobs::company_counts(&conn)
+        };
+        let trends = SoftwareJobs::company_trends(&counts(before)?, &counts(after)?);
+        let name = |path: &Path| {
+            path.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+        green_println!(format!(
+            "Companies with the biggest change in jobs from {} to {}:",
+            name(before),
+            name(after)
+        ));
+        if trends.is_empty() {
+            green_println!("No company's number of jobs changed.");
+        }
+        for trend in trends.iter().take(MAX_COMPANY_TRENDS) {
+            println!("{trend}");
         }
-        green_println!(format!("{cnt} jobs returned."));
 
         Ok(())
     }
+
+    /// Returns the query saved as a preset, or an error naming the saved presets.
+    fn preset(name: &str) -> Result<String, ErrorKind> {
+        let presets = config::get().presets;
+        presets.get(name).cloned().ok_or_else(|| {
+            ErrorKind::SqliteQuery(match presets.is_empty() {
+                true => format!(
+                    "There's no preset \"{name}\". Run a query, then enter \"save <name>\" to save it."
+                ),
+                false => format!(
+                    "There's no preset \"{name}\". The saved presets are: {}.",
+                    presets.keys().join(", ")
+                ),
+            })
+        })
+    }
+
+    /// Runs the queries of two presets and displays the jobs only in the results of each, and
+    /// the jobs in both.
+    fn display_comparison(conn: &Connection, a: &str, b: &str) -> Result<(), ErrorKind> {
+        let select = |name: &str| -> Result<Vec<Job>, ErrorKind> {
+            let jobs = Self::select_job_rows(conn, &Self::preset(name)?)?;
+            Ok(jobs.into_iter().map(|(_, job)| job).collect())
+        };
+        let (a_jobs, b_jobs) = (select(a)?, select(b)?);
+        let (only_a, only_b, both) = compare_jobs(&a_jobs, &b_jobs);
+        for (heading, jobs, colour) in [
+            (format!("Only in {a}"), only_a, Color::BrightGreen),
+            (format!("Only in {b}"), only_b, Color::BrightRed),
+            ("In both".to_string(), both, Color::BrightYellow),
+        ] {
+            println!(
+                "{}",
+                format!("{heading} ({} jobs):", jobs.len())
+                    .bold()
+                    .color(colour)
+            );
+            for job in jobs {
+                println!(
+                    "  {}",
+                    format!("{} - {}", job.title, job.company).color(colour)
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Updates a session setting from a "<setting> <value>" string.
+    fn set(args: &str) -> Result<(), ErrorKind> {
+        let (setting, value) = args.trim().split_once(' ').unwrap_or((args.trim(), ""));
+        let value = value.trim();
+        match setting {
+            "separator" => {
+                let separator = value.parse()?;
+                config::update(|c| c.separator = separator);
+                green_println!("Separator updated.");
+            }
+            "cache" => {
+                match value {
+                    "on" => config::update(|c| c.cache.enabled = true),
+                    "off" => config::update(|c| c.cache.enabled = false),
+                    ttl => {
+                        let ttl = ttl.parse::<u64>().map_err(|_| {
+                            ErrorKind::Setting(format!(
+                                "Cache must be \"on\", \"off\" or a TTL in seconds, got \"{ttl}\"."
+                            ))
+                        })?;
+                        config::update(|c| {
+                            c.cache.enabled = true;
+                            c.cache.ttl = Duration::from_secs(ttl);
+                        })
+                    }
+                }
+                let cache = config::get().cache;
+                match cache.enabled {
+                    true => green_println!(format!(
+                        "HTML cache is on with a TTL of {}s.",
+                        cache.ttl.as_secs()
+                    )),
+                    false => green_println!("HTML cache is off."),
+                }
+            }
+            "budget" => {
+                let budget = match value {
+                    "off" => None,
+                    secs => match secs.parse::<u64>() {
+                        Ok(secs) if secs > 0 => Some(Duration::from_secs(secs)),
+                        _ => Err(ErrorKind::Setting(format!(
+                            "Budget must be \"off\" or a positive number of seconds, got \"{secs}\"."
+                        )))?,
+                    },
+                };
+                config::update(|c| c.scrape_budget = budget);
+                match budget {
+                    Some(budget) => green_println!(format!(
+                        "Refreshes will stop scraping after {}s.",
+                        budget.as_secs()
+                    )),
+                    None => green_println!("Refreshes will scrape every site to completion."),
+                }
+            }
+            "timeout" => {
+                let timeout = match value.parse::<u64>() {
+                    Ok(secs) if secs > 0 => Duration::from_secs(secs),
+                    _ => Err(ErrorKind::Setting(format!(
+                        "Timeout must be a positive number of seconds, got \"{value}\"."
+                    )))?,
+                };
+                config::update(|c| c.request_timeout = timeout);
+                green_println!(format!(
+                    "Requests will time out after {}s.",
+                    timeout.as_secs()
+                ));
+            }
+            "empty-retries" => {
+                let retries = value.parse::<u32>().map_err(|_| {
+                    ErrorKind::Setting(format!(
+                        "Empty retries must be a whole number, got \"{value}\"."
+                    ))
+                })?;
+                config::update(|c| c.empty_retries = retries);
+                green_println!(format!(
+                    "Sites that return no jobs will be scraped again up to {retries} times from the next refresh."
+                ));
+            }
+            "retries" => {
+                let retries = config::parse_retries(value)?;
+                config::update(|c| c.retry.retries = retries);
+                green_println!(format!(
+                    "Requests will be retried up to {retries} times from the next refresh."
+                ));
+            }
+            "backoff" => {
+                let backoff = config::parse_backoff(value)?;
+                config::update(|c| c.retry.backoff = backoff);
+                green_println!(format!(
+                    "Retry backoff will start at {}ms from the next refresh.",
+                    backoff.as_millis()
+                ));
+            }
+            "w3c-filters" => {
+                let filters = match value {
+                    "off" => Vec::new(),
+                    filters => filters
+                        .split(',')
+                        .map(|f| f.trim().trim_matches('/').to_string())
+                        .filter(|f| !f.is_empty())
+                        .collect(),
+                };
+                match filters.is_empty() {
+                    true => green_println!("The unfiltered web3careers listing will be scraped."),
+                    false => green_println!(format!(
+                        "These web3careers listings will be scraped: {}",
+                        filters.join(", ")
+                    )),
+                }
+                config::update(|c| c.web3_careers_filters = filters);
+            }
+            "greenhouse" => {
+                let boards = match value {
+                    "off" => Vec::new(),
+                    boards => boards
+                        .split(',')
+                        .map(|b| b.trim().to_string())
+                        .filter(|b| !b.is_empty())
+                        .collect(),
+                };
+                match boards.is_empty() {
+                    true => green_println!("No Greenhouse boards will be scraped."),
+                    false => green_println!(format!(
+                        "These Greenhouse boards will be scraped: {}",
+                        boards.join(", ")
+                    )),
+                }
+                config::update(|c| c.greenhouse_boards = boards);
+            }
+            "w3c-pages" => {
+                let pages = match value.parse::<u8>() {
+                    Ok(n) if n > 0 => n,
+                    _ => Err(ErrorKind::Setting(format!(
+                        "Pages must be a whole number from 1 to 255, got \"{value}\"."
+                    )))?,
+                };
+                config::update(|c| c.web3_careers_pages = pages);
+                green_println!(format!(
+                    "Up to {pages} pages of each web3careers listing will be scraped on refresh."
+                ));
+            }
+            "w3c-salary-pages" => {
+                let pages = value.parse::<usize>().map_err(|_| {
+                    ErrorKind::Setting(format!("Pages must be a whole number, got \"{value}\"."))
+                })?;
+                config::update(|c| c.web3_careers_salary_pages = pages);
+                match pages {
+                    0 => green_println!("Web3careers detail pages won't be fetched for salaries."),
+                    _ => green_println!(format!(
+                        "Up to {pages} web3careers detail pages will be fetched for salaries on refresh."
+                    )),
+                }
+            }
+            "cjl-pages" => {
+                let pages = match value.parse::<u32>() {
+                    Ok(n) if n > 0 => n,
+                    _ => Err(ErrorKind::Setting(format!(
+                        "Pages must be a positive whole number, got \"{value}\"."
+                    )))?,
+                };
+                config::update(|c| c.crypto_jobs_list_pages = pages);
+                green_println!(format!(
+                    "Up to {pages} cryptojobslist pages will be scraped on refresh."
+                ));
+            }
+            "common-pages" => {
+                let pages = match value.parse::<u32>() {
+                    Ok(n) if n > 0 => n,
+                    _ => Err(ErrorKind::Setting(format!(
+                        "Pages must be a positive whole number, got \"{value}\"."
+                    )))?,
+                };
+                config::update(|c| c.common_pages = pages);
+                green_println!(format!(
+                    "Up to {pages} pages of the solana, substrate and near boards will be scraped on refresh."
+                ));
+            }
+            "format" => {
+                let format = value.parse()?;
+                config::update(|c| c.format = format);
+                green_println!("Format updated.");
+            }
+            "relevance" => {
+                let relevance = value.parse()?;
+                config::update(|c| c.relevance = relevance);
+                green_println!("Relevance weights updated.");
+            }
+            "min-bytes" => {
+                let min_bytes = value.parse::<usize>().map_err(|_| {
+                    ErrorKind::Setting(format!(
+                        "Min bytes must be a non-negative whole number, got \"{value}\"."
+                    ))
+                })?;
+                config::update(|c| c.min_document_bytes = min_bytes);
+                green_println!(format!(
+                    "Pages smaller than {min_bytes} bytes will be treated as empty."
+                ));
+            }
+            "history" => {
+                let history = match value {
+                    "on" => true,
+                    "off" => false,
+                    v => Err(ErrorKind::Setting(format!(
+                        "History must be \"on\" or \"off\", got \"{v}\"."
+                    )))?,
+                };
+                config::update(|c| c.history = history);
+                match history {
+                    true => green_println!("Refreshes will be appended to the job history."),
+                    false => green_println!("Refreshes will only replace the current jobs."),
+                }
+            }
+            "background" => {
+                let background = match value {
+                    "on" => true,
+                    "off" => false,
+                    v => Err(ErrorKind::Setting(format!(
+                        "Background must be \"on\" or \"off\", got \"{v}\"."
+                    )))?,
+                };
+                config::update(|c| c.background_refresh = background);
+                match background {
+                    true => green_println!("Refreshes will run in the background."),
+                    false => green_println!("Refreshes will block the prompt until they complete."),
+                }
+            }
+            "batch" => {
+                let batch_size = match value {
+                    "off" => None,
+                    n => match n.parse::<usize>() {
+                        Ok(n) if n > 0 => Some(n),
+                        _ => Err(ErrorKind::Setting(format!(
+                            "Batch must be \"off\" or a positive number of rows, got \"{n}\"."
+                        )))?,
+                    },
+                };
+                config::update(|c| c.insert_batch_size = batch_size);
+                match batch_size {
+                    Some(n) => green_println!(format!("Jobs will be written in batches of {n}.")),
+                    None => green_println!("Jobs will be written in a single transaction."),
+                }
+            }
+            "dates" => {
+                let relative = match value {
+                    "relative" => true,
+                    "absolute" => false,
+                    _ => Err(ErrorKind::Setting(format!(
+                        "Dates must be \"relative\" or \"absolute\", got \"{value}\"."
+                    )))?,
+                };
+                config::update(|c| c.relative_dates = relative);
+                green_println!(format!("Posted dates will be displayed as {value} dates."));
+            }
+            "follow-only" => {
+                let follow_only = match value {
+                    "on" => true,
+                    "off" => false,
+                    _ => Err(ErrorKind::Setting(format!(
+                        "Follow-only must be \"on\" or \"off\", got \"{value}\"."
+                    )))?,
+                };
+                config::update(|c| c.follow_only = follow_only);
+                match follow_only {
+                    true => green_println!(
+                        "Only jobs from followed companies will be added on the next refresh."
+                    ),
+                    false => {
+                        green_println!("Jobs from all companies will be added on the next refresh.")
+                    }
+                }
+            }
+            "strict" => {
+                let strict = match value {
+                    "on" => true,
+                    "off" => false,
+                    _ => Err(ErrorKind::Setting(format!(
+                        "Strict must be \"on\" or \"off\", got \"{value}\"."
+                    )))?,
+                };
+                config::update(|c| c.strict_titles = strict);
+                match strict {
+                    true => {
+                        green_println!("Strict mode is on. Jobs without a title will be dropped.")
+                    }
+                    false => {
+                        green_println!("Strict mode is off. Jobs without a title will be kept.")
+                    }
+                }
+            }
+            _ => Err(ErrorKind::Setting(format!(
+                "\"{setting}\" is not a known setting."
+            )))?,
+        }
+        Ok(())
+    }
+
+    /// Updates the locations of interest applied on the next refresh. Accepts "all", or a comma
+    /// separated list of regions.
+    fn update_locations(args: &str) {
+        let args = args.trim();
+        match args {
+            "" => {}
+            "all" => config::update(|c| c.locations.clear()),
+            regions => {
+                let regions = regions
+                    .split(',')
+                    .map(|r| r.trim().to_string())
+                    .filter(|r| !r.is_empty())
+                    .collect::<Vec<String>>();
+                config::update(|c| c.locations = regions)
+            }
+        }
+        let locations = config::get().locations;
+        if locations.is_empty() {
+            green_println!("Jobs in all locations will be added.");
+        } else {
+            green_println!(format!(
+                "Only jobs in these locations will be added: {}",
+                locations.join(", ")
+            ));
+        }
+        if !args.is_empty() {
+            green_println!("The new locations will be applied on the next refresh.");
+        }
+    }
+
+    /// Updates the followed companies. Accepts "none", or a comma separated list of companies.
+    fn update_followed_companies(args: &str) {
+        match args.trim() {
+            "" => {}
+            "none" => config::update(|c| c.companies.clear()),
+            companies => {
+                let companies = companies
+                    .split(',')
+                    .map(|c| c.trim().to_string())
+                    .filter(|c| !c.is_empty())
+                    .collect::<Vec<String>>();
+                config::update(|c| c.companies = companies)
+            }
+        }
+        let config = config::get();
+        match (config.companies.is_empty(), config.follow_only) {
+            (true, _) => green_println!("No companies are followed."),
+            (false, follow_only) => {
+                green_println!(format!(
+                    "Jobs from these companies will be highlighted: {}",
+                    config.companies.join(", ")
+                ));
+                if follow_only {
+                    green_println!("Only jobs from these companies will be added on refresh.");
+                }
+            }
+        }
+    }
+
+    /// Updates the title filter applied on the next refresh. Accepts "on", "off", or a comma
+    /// separated list of keywords (which also turns the filter on).
+    fn update_title_filter(args: &str) {
+        let args = args.trim();
+        match args {
+            "" => {}
+            "on" => config::update(|c| c.title_filter.enabled = true),
+            "off" => config::update(|c| c.title_filter.enabled = false),
+            keywords => {
+                let keywords = config::parse_keywords(keywords);
+                config::update(|c| {
+                    c.title_filter.enabled = true;
+                    c.title_filter.keywords = keywords;
+                })
+            }
+        }
+        let title_filter = config::get().title_filter;
+        if title_filter.is_active() {
+            green_println!(format!(
+                "Title filter is on with keywords: {}",
+                title_filter.keywords.join(", ")
+            ));
+        } else {
+            green_println!("Title filter is off. All scraped jobs will be added.");
+        }
+        if !args.is_empty() {
+            green_println!("The new filter will be applied on the next refresh.");
+        }
+    }
 }
 
 impl Repl for SoftwareJobs {
     async fn init_repl() -> Result<(), ErrorKind> {
-        let mut rl = DefaultEditor::new().map_err(|e| ErrorKind::Repl(e.to_string()))?;
+        // without a terminal to edit lines in, queries are read straight from stdin
+        if !io::stdin().is_terminal() {
+            SoftwareJobs::check_integrity(&config::get().db_path)?;
+            // the jobs are added through the connection the queries are run on
+            let mut conn = Self::open_db()?;
+            green_println!("Populating local database. This shouldn't take long...");
+            Self::init_repo(&mut conn, true).await?;
+            return Self::run_queries(&conn, io::stdin().lock()).map(|_| ());
+        }
+        let mut rl = Editor::<QueryHelper, DefaultHistory>::new()
+            .map_err(|e| ErrorKind::Repl(e.to_string()))?;
+        if let Err(err) = SoftwareJobs::check_integrity(&config::get().db_path) {
+            let ErrorKind::CorruptDb(_) = err else {
+                return Err(err);
+            };
+            red_println!(err.to_string());
+            let answer = rl
+                .readline("Move the corrupted database aside and create a fresh one? (y/n) ")
+                .unwrap_or_default();
+            if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+                return Err(err);
+            }
+            let backup = SoftwareJobs::set_aside_corrupt_db(&config::get().db_path)?;
+            green_println!(format!(
+                "The corrupted database was moved to {}.",
+                backup.display()
+            ));
+        }
+        // one connection for the session, which refreshes (even background ones) and the hinter go
+        // through too - so an in-memory DB lasts until the REPL exits. It's locked while a line is
+        // run, and while a refresh writes its jobs
+        let db = Arc::new(Mutex::new(Self::open_db()?));
+        rl.set_helper(Some(QueryHelper::new(db.clone())));
         green_println!("Populating local database. This shouldn't take long...");
-        Self::init_repo().await?;
-        green_println!(
-            "Population completed successfully! Welcome, please begin your job hunt by entering a query."
-        );
+        let populated = Self::scrape_jobs(true)
+            .await
+            .and_then(|jobs| jobs.add_to_db(&mut db.lock().unwrap()));
+        match populated {
+            Ok(()) => green_println!(
+                "Population completed successfully! Welcome, please begin your job hunt by entering a query."
+            ),
+            // a failed population doesn't stop the jobs already stored being queried
+            Err(err) if stored_jobs(&db.lock().unwrap()) > 0 => {
+                red_println!(format!("Population failed, so the stored jobs are used. {err}"));
+                green_println!("Welcome, please begin your job hunt by entering a query.");
+            }
+            Err(err) => return Err(err),
+        }
         rl.load_history(".jobhunthistory").ok();
+        // set while a background refresh is running
+        let refreshing = Arc::new(AtomicBool::new(false));
+        // exports use the most recent successful query
+        let mut last_query = "select jobs".to_string();
+        // the row ids of the last displayed jobs, by number
+        let mut last_ids: Vec<i64> = Vec::new();
+
+        // whether a background refresh was running when the last line was read
+        let mut was_refreshing = false;
 
         loop {
+            // the hinted values are fetched again once the jobs have changed
+            let is_refreshing = refreshing.load(Ordering::SeqCst);
+            if was_refreshing && !is_refreshing {
+                if let Some(helper) = rl.helper() {
+                    helper.clear();
+                }
+            }
+            was_refreshing = is_refreshing;
             let readline = rl.readline(">> ");
             match readline {
                 Ok(mut l) => {
                     rl.add_history_entry(&l)
                         .map_err(|e| ErrorKind::Repl(e.to_string()))?;
-                    l = l.trim().to_lowercase();
+                    // paths keep their case
+                    let input = l.trim().to_string();
+                    l = input.to_lowercase();
 
+                    // the commands that scrape run before the connection is locked, as the lock
+                    // can't be held across an await - a refresh only locks it to write the jobs
+                    if l.starts_with("test-selectors ") {
+                        // the url and selector keep their case
+                        let args = input["test-selectors".len()..].trim();
+                        match args.split_once(char::is_whitespace) {
+                            Some((url, selector)) => {
+                                Self::display_selector_matches(url, selector.trim()).await
+                            }
+                            None => red_println!(
+                                "Enter a url and a selector, e.g. test-selectors https://example.com/jobs ul.jobs > li"
+                            ),
+                        }
+                        continue;
+                    }
+                    if l == "refresh" && !config::get().background_refresh {
+                        green_println!("Refreshing local database...");
+                        let refreshed = Self::scrape_jobs(true)
+                            .await
+                            .and_then(|jobs| jobs.add_to_db(&mut db.lock().unwrap()));
+                        match refreshed {
+                            Ok(()) => {
+                                if let Some(helper) = rl.helper() {
+                                    helper.clear();
+                                }
+                                green_println!(refresh_completed_message());
+                            }
+                            Err(err) => red_println!(format!("Refresh failed. {err}")),
+                        }
+                        continue;
+                    }
+                    let conn = db.lock().unwrap();
                     match () {
+                        () if l == "select jobs new" || l == "select jobs remote" => {
+                            let query = match l.as_str() {
+                                "select jobs new" => NEW_JOBS_QUERY,
+                                _ => REMOTE_JOBS_QUERY,
+                            };
+                            match Self::select_and_display_jobs(&conn, query.to_string()) {
+                                Ok(ids) => {
+                                    last_query = query.to_string();
+                                    last_ids = ids;
+                                }
+                                Err(err) => red_println!(err.to_string()),
+                            }
+                        }
                         () if l.starts_with("select jobs") => {
-                            let conn = Connection::open("jobs.db")
-                                .map_err(|e| ErrorKind::SqliteConnection(e.to_string()))?;
-                            if let Err(err) = Self::select_and_display_jobs(conn, l) {
+                            match Self::select_and_display_jobs(&conn, l.clone()) {
+                                Ok(ids) => {
+                                    last_query = l;
+                                    last_ids = ids;
+                                }
+                                Err(err) => red_println!(err.to_string()),
+                            }
+                        }
+                        () if l.starts_with("salary ") => {
+                            let res = l["salary ".len()..]
+                                .parse::<SalaryRange>()
+                                .map(|range| format!("select jobs where {}", range.condition()))
+                                .and_then(|query| {
+                                    Ok((
+                                        Self::select_and_display_jobs(&conn, query.clone())?,
+                                        query,
+                                    ))
+                                });
+                            match res {
+                                Ok((ids, query)) => {
+                                    last_query = query;
+                                    last_ids = ids;
+                                }
+                                Err(err) => red_println!(err.to_string()),
+                            }
+                        }
+                        () if is_sort_command(&l) => {
+                            let res = sort_query(&l).and_then(|query| {
+                                Ok((Self::select_and_display_jobs(&conn, query.clone())?, query))
+                            });
+                            match res {
+                                Ok((ids, query)) => {
+                                    last_query = query;
+                                    last_ids = ids;
+                                }
+                                Err(err) => red_println!(err.to_string()),
+                            }
+                        }
+                        () if l.starts_with("lang ") => {
+                            let lang = l["lang ".len()..].trim();
+                            let Some(lang) = classify::language(lang) else {
+                                red_println!(format!(
+                                    "\"{lang}\" isn't a known language, e.g. rust, go, typescript, solidity or python."
+                                ));
+                                continue;
+                            };
+                            let query = format!("select jobs where primary_language = '{lang}'");
+                            match Self::select_and_display_jobs(&conn, query.clone()) {
+                                Ok(ids) => {
+                                    last_query = query;
+                                    last_ids = ids;
+                                }
+                                Err(err) => red_println!(err.to_string()),
+                            }
+                        }
+                        () if l.starts_with("save ")
+                            && l["save ".len()..].trim().parse::<usize>().is_ok() =>
+                        {
+                            // a job's number in the last results
+                            let n = l["save ".len()..]
+                                .trim()
+                                .parse::<usize>()
+                                .unwrap_or_default();
+                            let res = match last_ids.get(n.wrapping_sub(1)) {
+                                Some(&id) => SoftwareJobs::save_favorite(&conn, id),
+                                None => Err(ErrorKind::Repl(format!(
+                                    "No job #{n} in the last results. Run a \"select jobs\" query first, then use a job's number."
+                                ))),
+                            };
+                            match res {
+                                Ok(job) => green_println!(format!(
+                                    "Saved \"{}\" at {} to your favorites. Enter \"{FAVORITES_QUERY}\" to list them.",
+                                    job.title, job.company
+                                )),
+                                Err(err) => red_println!(err.to_string()),
+                            }
+                        }
+                        () if l == FAVORITES_QUERY => {
+                            match Self::select_and_display_jobs(&conn, l.clone()) {
+                                Ok(ids) => {
+                                    last_query = l;
+                                    last_ids = ids;
+                                }
+                                Err(err) => red_println!(err.to_string()),
+                            }
+                        }
+                        () if l.starts_with("save ") => {
+                            let name = l["save ".len()..].trim();
+                            match !name.is_empty()
+                                && name
+                                    .chars()
+                                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+                            {
+                                true => {
+                                    config::update(|c| {
+                                        c.presets.insert(name.to_string(), last_query.clone());
+                                    });
+                                    green_println!(format!(
+                                        "Saved \"{last_query}\" as preset \"{name}\"."
+                                    ))
+                                }
+                                false => red_println!(
+                                    "A preset name is a single word of letters, digits, - or _."
+                                ),
+                            }
+                        }
+                        () if l == "presets" => {
+                            let presets = config::get().presets;
+                            if presets.is_empty() {
+                                green_println!(
+                                    "No presets are saved. Run a query, then enter \"save <name>\" to save it."
+                                );
+                            }
+                            for (name, query) in presets {
+                                println!("{} {}", name.bold().bright_green(), query.green());
+                            }
+                        }
+                        () if l.starts_with("run ") => {
+                            let res = Self::preset(l["run ".len()..].trim()).and_then(|query| {
+                                Ok((Self::select_and_display_jobs(&conn, query.clone())?, query))
+                            });
+                            match res {
+                                Ok((ids, query)) => {
+                                    last_query = query;
+                                    last_ids = ids;
+                                }
+                                Err(err) => red_println!(err.to_string()),
+                            }
+                        }
+                        () if l.starts_with("compare ") => {
+                            let names =
+                                l["compare ".len()..].split_whitespace().collect::<Vec<_>>();
+                            let [a, b] = names.as_slice() else {
+                                red_println!("Usage: compare <preset> <preset>");
+                                continue;
+                            };
+                            if let Err(err) = Self::display_comparison(&conn, a, b) {
                                 red_println!(err.to_string())
                             }
                         }
+                        () if l == "group by recency" || l == "group by none" => {
+                            let group_by_recency = l == "group by recency";
+                            config::update(|c| c.group_by_recency = group_by_recency);
+                            match group_by_recency {
+                                true => green_println!(
+                                    "Results will be grouped by when they were posted: today, this week, this month, older and unknown."
+                                ),
+                                false => green_println!("Results will be listed without grouping."),
+                            }
+                        }
+                        () if l == "closing soon" => {
+                            match Self::select_and_display_jobs(
+                                &conn,
+                                CLOSING_SOON_QUERY.to_string(),
+                            ) {
+                                Ok(ids) => {
+                                    last_query = CLOSING_SOON_QUERY.to_string();
+                                    last_ids = ids;
+                                }
+                                Err(err) => red_println!(err.to_string()),
+                            }
+                        }
+                        () if l == "copy" => match Self::copy_jobs(&conn, &last_query) {
+                            Ok(cnt) => {
+                                green_println!(format!("{cnt} jobs copied to the clipboard."))
+                            }
+                            Err(err) => red_println!(err.to_string()),
+                        },
+                        () if l.starts_with("raw ") => {
+                            let n = l.trim_start_matches("raw ").trim();
+                            match n.parse::<usize>().ok().and_then(|n| last_ids.get(n.checked_sub(1)?)) {
+                                Some(&id) => {
+                                    if let Err(err) = Self::display_raw_job(&conn, id) {
+                                        red_println!(err.to_string())
+                                    }
+                                }
+                                None => red_println!(format!(
+                                    "No job #{n} in the last results. Run a \"select jobs\" query first, then use a job's number."
+                                )),
+                            }
+                        }
+                        () if l.starts_with("history ") => {
+                            // a job's number in the last results, or a dedup key
+                            let arg = l["history".len()..].trim();
+                            let dedup_key = match arg.parse::<usize>() {
+                                Ok(n) => match last_ids.get(n.wrapping_sub(1)) {
+                                    Some(&id) => SoftwareJobs::dedup_key_of(&conn, id),
+                                    None => Err(ErrorKind::Repl(format!(
+                                        "No job #{n} in the last results. Run a \"select jobs\" query first, then use a job's number."
+                                    ))),
+                                },
+                                Err(_) => Ok(arg.to_string()),
+                            };
+                            if let Err(err) =
+                                dedup_key.and_then(|key| Self::display_job_history(&conn, &key))
+                            {
+                                red_println!(err.to_string());
+                            }
+                        }
+                        () if l.starts_with("export ") => {
+                            let args = input["export ".len()..].trim();
+                            let (args, columns) = match args.split_once(" --columns ") {
+                                Some((args, columns)) => (args, Some(columns)),
+                                None => (args, None),
+                            };
+                            let Some((format, path)) = args.split_once(' ') else {
+                                red_println!("Usage: export <format> <path> [--columns <columns>]");
+                                continue;
+                            };
+                            let path = path.trim();
+                            let res = format.to_lowercase().parse::<Format>().and_then(|format| {
+                                let columns = columns.map(export::parse_columns).transpose()?;
+                                Self::export_jobs(
+                                    &conn,
+                                    &last_query,
+                                    format,
+                                    columns.as_deref(),
+                                    Path::new(path),
+                                )
+                            });
+                            match res {
+                                Ok(cnt) => {
+                                    green_println!(format!("{cnt} jobs exported to {path}."))
+                                }
+                                Err(err) => red_println!(err.to_string()),
+                            }
+                        }
                         () if l == "refresh" => {
-                            green_println!("Refreshing local database...");
-                            Self::init_repo().await?;
-                            green_println!(format!(
-                                "Refresh completed successfully at {}",
-                                Local::now().format("%d-%m-%Y %H:%M:%S")
-                            ))
+                            if refreshing.swap(true, Ordering::SeqCst) {
+                                red_println!("A refresh is already in progress.");
+                                continue;
+                            }
+                            let mut print: Box<dyn FnMut(String) + Send> =
+                                match rl.create_external_printer() {
+                                    Ok(mut printer) => Box::new(move |msg| {
+                                        printer.print(msg).ok();
+                                    }),
+                                    Err(_) => Box::new(|msg| println!("{msg}")),
+                                };
+                            let refreshing = refreshing.clone();
+                            let db = db.clone();
+                            green_println!(
+                                "Refreshing local database in the background. You can keep querying the current jobs until it completes."
+                            );
+                            // the refresh gets its own runtime, as scraped documents can't be sent
+                            // between threads
+                            thread::spawn(move || {
+                                let res = tokio::runtime::Builder::new_current_thread()
+                                    .enable_all()
+                                    .build()
+                                    .map_err(|e| ErrorKind::Repl(e.to_string()))
+                                    .and_then(|rt| {
+                                        // no progress lines, as they'd be printed over the
+                                        // prompt. The session's connection is only locked to
+                                        // write the jobs, so queries can run while it scrapes
+                                        let jobs = rt.block_on(Self::scrape_jobs(false))?;
+                                        jobs.add_to_db(&mut db.lock().unwrap())
+                                    });
+                                refreshing.store(false, Ordering::SeqCst);
+                                let msg = match res {
+                                    Ok(()) => refresh_completed_message().bold().green(),
+                                    Err(err) => format!("Refresh failed. {err}").bold().red(),
+                                };
+                                print(msg.to_string());
+                            });
+                        }
+                        () if l == "incomplete" || l.starts_with("incomplete ") => {
+                            let field = l.trim_start_matches("incomplete").trim();
+                            if let Err(err) = Self::display_incomplete_jobs(&conn, field) {
+                                red_println!(err.to_string())
+                            }
+                        }
+                        () if l.starts_with("explain filter ") => {
+                            let job = Job {
+                                title: l.trim_start_matches("explain filter ").to_string(),
+                                ..Default::default()
+                            };
+                            let explanation = job.explain_title_filter(&config::get().title_filter);
+                            match explanation.is_included() {
+                                true => green_println!(explanation.to_string()),
+                                false => red_println!(explanation.to_string()),
+                            }
+                        }
+                        () if l == "schema" || l == "columns" => {
+                            if let Err(err) = Self::display_schema(&conn) {
+                                red_println!(err.to_string())
+                            }
+                        }
+                        () if l == "reset" => {
+                            let answer = rl
+                                .readline("This will delete all stored jobs, their history and your favorites. Are you sure? (y/n) ")
+                                .unwrap_or_default();
+                            if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+                                green_println!("Reset cancelled.");
+                                continue;
+                            }
+                            match SoftwareJobs::reset(&conn) {
+                                Ok(()) => {
+                                    last_ids.clear();
+                                    if let Some(helper) = rl.helper() {
+                                        helper.clear();
+                                    }
+                                    green_println!(
+                                        "The local database has been reset. Enter \"refresh\" to scrape jobs again."
+                                    )
+                                }
+                                Err(err) => red_println!(err.to_string()),
+                            }
+                        }
+                        () if l == "snapshot" || l.starts_with("snapshot ") => {
+                            // a directory given here is used for later snapshots too
+                            let dir = input["snapshot".len()..].trim();
+                            if !dir.is_empty() {
+                                config::update(|c| c.snapshot_dir = dir.into());
+                            }
+                            let dir = config::get().snapshot_dir;
+                            match SoftwareJobs::snapshot(&conn, &dir, Local::now().date_naive()) {
+                                Ok(path) => {
+                                    green_println!(format!("Snapshot saved to {}.", path.display()))
+                                }
+                                Err(err) => red_println!(err.to_string()),
+                            }
+                        }
+                        () if l.starts_with("like-company ") => {
+                            let company = input["like-company".len()..].trim();
+                            if let Err(err) = Self::display_related_companies(&conn, company) {
+                                red_println!(err.to_string());
+                            }
+                        }
+                        () if l == "trend companies" => {
+                            if let Err(err) =
+                                Self::display_company_trends(&config::get().snapshot_dir)
+                            {
+                                red_println!(err.to_string())
+                            }
+                        }
+                        () if l == "reclassify" => match SoftwareJobs::reclassify(&conn) {
+                            Ok(cnt) => green_println!(format!(
+                                "Reclassification completed. {cnt} jobs changed category."
+                            )),
+                            Err(err) => red_println!(err.to_string()),
+                        },
+                        () if l.starts_with("set ") => {
+                            if let Err(err) = Self::set(l.trim_start_matches("set ")) {
+                                red_println!(err.to_string())
+                            }
+                        }
+                        () if l == "filter" || l.starts_with("filter ") => {
+                            Self::update_title_filter(l.trim_start_matches("filter"))
+                        }
+                        () if l == "locations" || l.starts_with("locations ") => {
+                            Self::update_locations(l.trim_start_matches("locations"))
+                        }
+                        () if l == "follow" || l.starts_with("follow ") => {
+                            Self::update_followed_companies(l.trim_start_matches("follow"))
                         }
                         () if l == "exit" => break,
                         () => {
@@ -115,3 +1714,312 @@ impl Repl for SoftwareJobs {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::sync::{Arc, Mutex};
+    use std::{env, fs, process};
+
+    use rusqlite::Connection;
+
+    use crate::config::Format;
+    use crate::repl::Repl;
+    use crate::repository::{Job, JobsDbBuilder, SoftwareJobs};
+    use crate::ErrorKind;
+
+    use super::{
+        compare_jobs, hint_value, is_sort_command, jobs_query_sql, sort_query, split_relevance,
+        stored_jobs, QueryHelper, CLOSING_SOON_QUERY, NEW_JOBS_QUERY, REMOTE_JOBS_QUERY,
+    };
+
+    /// Returns an in-memory DB with the jobs stored as a refresh would store them.
+    fn db_with(jobs: &[Job]) -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        SoftwareJobs::new()
+            .import(vec![jobs.to_vec()])
+            .add_to_db(&mut conn)
+            .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_run_queries() {
+        let conn = db_with(&[Job {
+            title: "Rust Engineer".to_string(),
+            company: "Parity".to_string(),
+            date_posted: "2024-05-06".to_string(),
+            site: "https://web3.career".to_string(),
+            ..Default::default()
+        }]);
+
+        let input = Cursor::new("select jobs where company = 'parity'\n\n  SELECT JOBS  \n");
+        assert_eq!(SoftwareJobs::run_queries(&conn, input).unwrap(), 2);
+        // commands other than queries need a terminal, but don't stop the later queries
+        let input = Cursor::new("refresh\nselect jobs\nexit\nselect jobs where nonsense\n");
+        match SoftwareJobs::run_queries(&conn, input) {
+            Err(err) => assert!(err.to_string().ends_with("1 of 2 queries failed.")),
+            res => panic!("expected an error, got {res:?}"),
+        }
+    }
+
+    #[test]
+    fn test_jobs_query_sql() {
+        assert_eq!(
+            jobs_query_sql("select jobs").unwrap(),
+            "select * from (select * from jobs where not stale) "
+        );
+        assert_eq!(
+            jobs_query_sql(r#"select jobs all where title like "%senior%" and rem_upper > 100000 order by date_posted;"#)
+                .unwrap(),
+            r#"select * from jobs where title like "%senior%" and rem_upper > 100000 order by date_posted"#
+        );
+        assert_eq!(
+            jobs_query_sql("select jobs all").unwrap(),
+            "select * from jobs "
+        );
+        // anything goes in a string
+        assert!(jobs_query_sql("select jobs where title like '%; drop table jobs%'").is_ok());
+        assert!(jobs_query_sql("select jobs where lower(company) = 'parity' limit 5").is_ok());
+        for query in [CLOSING_SOON_QUERY, NEW_JOBS_QUERY, REMOTE_JOBS_QUERY] {
+            assert!(jobs_query_sql(query).is_ok(), "{query}");
+        }
+
+        let rejected = |query: &str| match jobs_query_sql(query) {
+            Err(ErrorKind::SqliteQuery(msg)) => msg,
+            res => panic!("expected {query} to be rejected, got {res:?}"),
+        };
+        assert!(rejected("select jobs; drop table jobs").contains("only one query"));
+        assert!(rejected("select jobs where id = 1; delete from jobs;").contains("only one query"));
+        assert!(
+            rejected("select jobs where id in (select id from job_history)")
+                .contains("\"select\" isn't allowed")
+        );
+        assert!(rejected("select jobs union select * from job_history")
+            .contains("only a where, order by or limit clause"));
+        assert!(rejected("select jobs where salary > 100")
+            .contains("\"salary\" isn't a column of the jobs table"));
+    }
+
+    #[test]
+    fn test_split_relevance() {
+        assert_eq!(
+            split_relevance("select jobs where rem_upper > 100000 order by relevance limit 5"),
+            Some(("select jobs where rem_upper > 100000 ", " limit 5"))
+        );
+        // the clause is only matched outside string literals
+        let query = "select jobs where title like '%order by relevance%'";
+        assert_eq!(split_relevance(query), None);
+        assert_eq!(
+            split_relevance(&format!("{query} order by relevance")),
+            Some((&format!("{query} ")[..], ""))
+        );
+        assert_eq!(split_relevance("select jobs"), None);
+    }
+
+    #[test]
+    fn test_select_jobs_skips_stale() {
+        let job = |title: &str| Job {
+            title: title.to_string(),
+            company: "Parity".to_string(),
+            ..Default::default()
+        };
+        let mut conn = db_with(&[job("Rust Engineer"), job("Go Engineer")]);
+        // a second refresh no longer finds the go job
+        SoftwareJobs::new()
+            .import(vec![vec![job("Rust Engineer")]])
+            .add_to_db(&mut conn)
+            .unwrap();
+        let titles = |l: &str| {
+            SoftwareJobs::select_jobs(&conn, l)
+                .unwrap()
+                .into_iter()
+                .map(|job| job.title)
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(titles("select jobs"), ["Rust Engineer"]);
+        assert_eq!(
+            titles("select jobs where company = 'Parity'"),
+            ["Rust Engineer"]
+        );
+        assert_eq!(titles("select jobs all"), ["Rust Engineer", "Go Engineer"]);
+        assert_eq!(titles("select jobs all where stale"), ["Go Engineer"]);
+    }
+
+    #[test]
+    fn test_select_tagged() {
+        let job = |title: &str, tags: &[&str]| Job {
+            title: title.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..Default::default()
+        };
+        let conn = db_with(&[
+            job("Rust Engineer", &["Rust", "Remote"]),
+            job("Rust Developer", &["rust"]),
+            job("Go Engineer", &["go", "remote"]),
+        ]);
+        let titles = |l: &str| {
+            SoftwareJobs::select_jobs(&conn, l)
+                .unwrap()
+                .into_iter()
+                .map(|job| job.title)
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            titles("select jobs tagged rust"),
+            ["Rust Engineer", "Rust Developer"]
+        );
+        assert_eq!(titles("select jobs tagged rust, remote"), ["Rust Engineer"]);
+        assert!(titles("select jobs tagged solidity").is_empty());
+        assert!(SoftwareJobs::select_jobs(&conn, "select jobs tagged ,").is_err());
+        // a remote tag makes a job remote
+        assert_eq!(titles(REMOTE_JOBS_QUERY), ["Rust Engineer", "Go Engineer"]);
+    }
+
+    #[test]
+    fn test_sort_query() {
+        assert!(is_sort_command("latest"));
+        assert!(is_sort_command("toppay 10"));
+        assert!(!is_sort_command("latestjobs"));
+        assert_eq!(
+            sort_query("latest").unwrap(),
+            "select jobs order by date_posted desc"
+        );
+        assert_eq!(
+            sort_query("oldest 5").unwrap(),
+            "select jobs order by date_posted asc limit 5"
+        );
+        assert_eq!(
+            sort_query("toppay 10").unwrap(),
+            "select jobs order by rem_upper desc nulls last limit 10"
+        );
+        assert!(sort_query("latest ten").is_err());
+        for command in ["latest 3", "oldest", "toppay 10"] {
+            assert!(jobs_query_sql(&sort_query(command).unwrap()).is_ok());
+        }
+
+        // jobs without a salary are listed after the paid ones
+        let job = |title: &str, rem_upper: Option<i64>| Job {
+            title: title.to_string(),
+            rem_lower: rem_upper,
+            rem_upper,
+            ..Default::default()
+        };
+        let conn = db_with(&[
+            job("Go Engineer", None),
+            job("Rust Engineer", Some(120000)),
+            job("Protocol Engineer", Some(180000)),
+        ]);
+        let titles = SoftwareJobs::select_jobs(&conn, &sort_query("toppay").unwrap())
+            .unwrap()
+            .into_iter()
+            .map(|job| job.title)
+            .collect::<Vec<String>>();
+        assert_eq!(
+            titles,
+            ["Protocol Engineer", "Rust Engineer", "Go Engineer"]
+        );
+    }
+
+    #[test]
+    fn test_export_jobs() {
+        let conn = db_with(&[Job {
+            title: "Rust Engineer".to_string(),
+            company: "Parity".to_string(),
+            tags: vec!["rust".to_string(), "p2p".to_string()],
+            site: "https://web3.career".to_string(),
+            ..Default::default()
+        }]);
+
+        let path = env::temp_dir().join(format!("jobhunt-test-export-{}.csv", process::id()));
+        let cnt = SoftwareJobs::export_jobs(&conn, "select jobs", Format::Csv, None, &path);
+        assert_eq!(cnt.unwrap(), 1);
+        let csv = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(csv.starts_with("title,company,"));
+        assert!(csv.contains("Rust Engineer,Parity,"));
+        assert!(csv.contains("rust;p2p"));
+
+        // an unwritable path is reported rather than panicking
+        let path = env::temp_dir()
+            .join("jobhunt-test-missing-dir")
+            .join("jobs.csv");
+        match SoftwareJobs::export_jobs(&conn, "select jobs", Format::Csv, None, &path) {
+            Err(ErrorKind::Export(msg)) => assert!(msg.starts_with("Unable to write")),
+            res => panic!("expected an export error, got {res:?}"),
+        }
+    }
+
+    #[test]
+    fn test_compare_jobs() {
+        let job = |title: &str| Job {
+            title: title.to_string(),
+            company: "Acme".to_string(),
+            site: "https://web3.career".to_string(),
+            ..Default::default()
+        };
+        let a = [job("Rust Engineer"), job("Go Engineer")];
+        let b = [job("rust  engineer"), job("Solidity Engineer")];
+        let (only_a, only_b, both) = compare_jobs(&a, &b);
+        let titles = |jobs: Vec<&Job>| {
+            jobs.into_iter()
+                .map(|j| j.title.clone())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(titles(only_a), ["Go Engineer"]);
+        assert_eq!(titles(only_b), ["Solidity Engineer"]);
+        assert_eq!(titles(both), ["Rust Engineer"]);
+    }
+
+    #[test]
+    fn test_query_helper_shares_connection() {
+        let db = Arc::new(Mutex::new(db_with(&[Job {
+            title: "Rust Engineer".to_string(),
+            site: "https://web3.career".to_string(),
+            ..Default::default()
+        }])));
+        let helper = QueryHelper::new(db.clone());
+        // nothing is hinted, or cached, while the connection is locked
+        let conn = db.lock().unwrap();
+        assert!(helper.column_values("site").is_empty());
+        drop(conn);
+        assert_eq!(helper.column_values("site"), ["https://web3.career"]);
+    }
+
+    #[test]
+    fn test_stored_jobs() {
+        assert_eq!(stored_jobs(&Connection::open_in_memory().unwrap()), 0);
+        let job = Job {
+            title: "Rust Engineer".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(stored_jobs(&db_with(&[job])), 1);
+    }
+
+    #[test]
+    fn test_hint_value() {
+        let values = |column: &str| match column {
+            "site" => vec![
+                "https://web3.career".to_string(),
+                "https://cryptojobslist.com".to_string(),
+            ],
+            _ => vec![],
+        };
+        let hint = |line: &str| hint_value(line, values);
+        assert_eq!(
+            hint("select jobs where site = ").as_deref(),
+            Some("'https://web3.career'")
+        );
+        assert_eq!(
+            hint("select jobs where SITE = 'https://c").as_deref(),
+            Some("ryptojobslist.com'")
+        );
+        assert_eq!(
+            hint(r#"select jobs where site like "https://w"#).as_deref(),
+            Some(r#"eb3.career""#)
+        );
+        assert_eq!(hint("select jobs where site = 'https://web3.career'"), None);
+        assert_eq!(hint("select jobs where title = "), None);
+        assert_eq!(hint("select jobs where company = "), None);
+    }
+}
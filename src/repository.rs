@@ -1,16 +1,126 @@
-use std::fmt::{Debug, Formatter};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::{Debug, Display, Formatter};
+use std::fs;
+use std::future::{self, Future};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::pin::pin;
+use std::str::FromStr;
+use std::task::Poll;
+use std::time::Duration;
 
+use chrono::{Local, NaiveDate};
 use colored::Colorize;
-use rusqlite::Connection;
+use futures_util::future::join_all;
+use itertools::Itertools;
+use regex::Regex;
+use reqwest::Url;
+use rusqlite::backup::Backup;
+use rusqlite::types::{FromSql, Type, Value};
+use rusqlite::{params_from_iter, Connection, ErrorCode, OpenFlags, Row};
+use serde::Serialize;
 
-use crate::scraper::Scraper;
-use crate::site::{CryptoJobsList, NearJobs, Site, SolanaJobs, SubstrateJobs, Web3Careers};
-use crate::ErrorKind;
+use crate::classify;
+use crate::config::{self, Relevance, TitleFilter};
+use crate::scraper::{self, scrape_retrying_empty, ScrapeBudget, Scraper};
+use crate::site::{
+    site_host, CommonSite, CryptoJobsList, CustomSite, GreenhouseJobs, Site, Web3Careers,
+    CRYPTO_JOBS_LIST_URL, WEB3_CAREERS_URL,
+};
+use crate::{green_println, red_println, ErrorKind};
 
 const NOT_AVAILABLE: &str = "Not available";
 
+/// Applicant tracking systems and job boards that host apply links for many companies, so their
+/// domains can't be used as the company website.
+const HOSTED_APPLY_DOMAINS: &[&str] = &[
+    "greenhouse.io",
+    "lever.co",
+    "ashbyhq.com",
+    "workable.com",
+    "breezy.hr",
+    "smartrecruiters.com",
+    "recruitee.com",
+    "bamboohr.com",
+    "myworkdayjobs.com",
+    "jobvite.com",
+    "teamtailor.com",
+    "personio.de",
+    "wellfound.com",
+    "angel.co",
+    "linkedin.com",
+    "notion.site",
+    "typeform.com",
+    "google.com",
+    "web3.career",
+    "cryptojobslist.com",
+];
+
+/// Subdomains commonly used for careers pages rather than the company website.
+const CAREERS_SUBDOMAINS: &[&str] = &["www.", "jobs.", "careers.", "apply.", "boards."];
+
+/// Query parameters that only track where a click came from, stripped from apply links. Any
+/// parameter starting with "utm_" is also stripped. This is an allowlist so that parameters a link
+/// needs (e.g. Greenhouse's "gh_jid") are never removed.
+const TRACKING_PARAMS: &[&str] = &[
+    "ref", "ref_src", "referrer", "fbclid", "gclid", "dclid", "msclkid", "yclid", "igshid",
+    "mc_cid", "mc_eid", "_hsenc", "_hsmi",
+];
+
+/// The table every refresh is appended to in history mode, with each row stamped with the time of
+/// its scrape.
+pub const HISTORY_TABLE: &str = "job_history";
+
+/// The table of the jobs saved as favorites. They're keyed by dedup key rather than id, so they're
+/// kept across refreshes.
+pub const FAVORITES_TABLE: &str = "favorites";
+
+/// The DB path for a DB kept in memory rather than in a file.
+pub const MEMORY_DB: &str = ":memory:";
+
+/// The shared-cache URI an in-memory DB is opened with, so every connection in the process (e.g.
+/// a background refresh's) sees the same DB. It lasts as long as at least one connection is open.
+const MEMORY_DB_URI: &str = "file:jobhunt?mode=memory&cache=shared";
+
+/// The DB's `user_version` once the remuneration bounds are stored as whole amounts, rather than
+/// in thousands as older versions stored them.
+const WHOLE_SALARIES_VERSION: i64 = 1;
+
+/// The number of days for the recency part of the relevance score to halve.
+const RECENCY_HALF_LIFE_DAYS: f64 = 7.0;
+
+/// The default title filter keywords - the repository is filtered on engineering jobs by default.
+pub const DEFAULT_TITLE_KEYWORDS: &[&str] = &["developer", "engineer", "engineering", "technical"];
+
+/// The columns of the jobs table (after the id), in order, with their SQL types. The table schema,
+/// inserts and reads are all derived from this, so they can't drift apart.
+const JOB_COLUMNS: &[(&str, &str)] = &[
+    ("title", "text not null"),
+    ("company", "text not null"),
+    ("date_posted", "date not null"),
+    ("location", "text"),
+    ("remuneration", "text"),
+    ("tags", "json"),
+    ("apply", "text not null"),
+    ("site", "text not null"),
+    ("rem_lower", "int"),
+    ("rem_upper", "int"),
+    ("seniority", "text"),
+    ("work_mode", "text"),
+    ("employment_type", "text"),
+    ("company_url", "text"),
+    ("currency", "text"),
+    ("logo_url", "text"),
+    ("deadline", "date"),
+    ("applicants", "int"),
+    ("source_url", "text"),
+    ("primary_language", "text"),
+    ("category", "text"),
+    ("is_remote", "bool"),
+];
+
 /// The Job struct is the repository primitive.
-#[derive(Default, Clone, Eq, Hash, PartialEq)]
+#[derive(Default, Clone, Eq, Hash, PartialEq, Serialize)]
 pub struct Job {
     pub title: String,
     pub company: String,
@@ -20,8 +130,33 @@ pub struct Job {
     pub tags: Vec<String>,
     pub apply: String,
     pub site: String,
-    pub rem_lower: u16,
-    pub rem_upper: u16,
+    /// The lower bound of the remuneration (e.g. 90000 for "$90k - $140k"), or None if the listing
+    /// gives no remuneration. Queries can compare it, e.g. "where rem_lower > 100000".
+    pub rem_lower: Option<i64>,
+    /// The upper bound of the remuneration (e.g. 140000 for "$90k - $140k"), or None if the
+    /// listing gives no remuneration.
+    pub rem_upper: Option<i64>,
+    pub seniority: String,
+    pub work_mode: String,
+    pub employment_type: String,
+    pub company_url: Option<String>,
+    /// The ISO code of the remuneration currency, e.g. "USD", or None if there's no remuneration.
+    pub currency: Option<String>,
+    /// The absolute URL of the company logo shown with the job, if the site has one.
+    pub logo_url: Option<String>,
+    /// The application deadline ("%Y-%m-%d"), if the listing gives one.
+    pub deadline: Option<String>,
+    /// The number of applicants (or views) shown on the listing, if the site shows one.
+    pub applicants: Option<u32>,
+    /// The URL of the listing page the job was scraped from.
+    pub source_url: String,
+    /// The main programming language of the job (lowercase, e.g. "rust"), if one is named.
+    pub primary_language: Option<String>,
+    /// The job's category or department (lowercase, e.g. "research"), if the listing gives one.
+    pub category: Option<String>,
+    /// Whether the job is remote, i.e. its work mode is "remote", so queries can filter on it with
+    /// "where is_remote".
+    pub is_remote: bool,
 }
 
 impl Job {
@@ -31,23 +166,806 @@ impl Job {
         }
     }
 
-    fn title_contains_any(&self, v: Vec<&str>) -> bool {
-        for pat in v {
-            if self.title.to_lowercase().contains(pat) {
-                return true;
+    /// Returns the column definitions for the jobs table, e.g. "id integer primary key, title text
+    /// not null, ...".
+    pub fn column_defs() -> String {
+        std::iter::once("id integer primary key".to_string())
+            .chain(JOB_COLUMNS.iter().map(|(name, ty)| format!("{name} {ty}")))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    /// Returns true if the job has every one of the tags, compared case-insensitively.
+    pub fn has_tags(&self, tags: &[String]) -> bool {
+        tags.iter()
+            .all(|tag| self.tags.iter().any(|t| t.trim().eq_ignore_ascii_case(tag)))
+    }
+
+    /// Returns the names of the jobs table's columns - its id, the Job fields, and the columns kept
+    /// by refreshes.
+    pub fn column_names() -> Vec<&'static str> {
+        std::iter::once("id")
+            .chain(JOB_COLUMNS.iter().map(|(name, _)| *name))
+            .chain(["dedup_key", "stale", "first_seen"])
+            .collect()
+    }
+
+    /// Returns the statement inserting a job into a table, with a parameter for each of
+    /// `to_params`.
+    pub fn insert_sql(table: &str) -> String {
+        let names = JOB_COLUMNS
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<&str>>();
+        let placeholders = (1..=names.len())
+            .map(|i| format!("?{i}"))
+            .collect::<Vec<String>>();
+        format!(
+            "insert into {table} ({}) values ({})",
+            names.join(", "),
+            placeholders.join(", ")
+        )
+    }
+
+    /// Returns the statement upserting a job into a table on its dedup key, with a parameter for
+    /// each of `to_params` and then the dedup key and first seen time. A job already in the table
+    /// is updated in place (keeping the time it was first seen) and is no longer stale.
+    pub fn upsert_sql(table: &str) -> String {
+        let names = JOB_COLUMNS.iter().map(|(name, _)| *name).collect_vec();
+        let placeholders = (1..=names.len() + 2).map(|i| format!("?{i}")).join(", ");
+        let updates = names
+            .iter()
+            .map(|name| format!("{name} = excluded.{name}"))
+            .join(", ");
+        format!(
+            "insert into {table} ({}, dedup_key, first_seen) values ({placeholders}) \
+            on conflict (dedup_key) do update set {updates}, stale = 0",
+            names.join(", ")
+        )
+    }
+
+    /// Returns the values to insert for this job, in column order.
+    pub fn to_params(&self) -> Result<Vec<Value>, ErrorKind> {
+        let tags = serde_json::to_string(&self.tags)
+            .map_err(|e| ErrorKind::Serialisation(e.to_string()))?;
+        Ok(vec![
+            self.title.clone().into(),
+            self.company.clone().into(),
+            self.date_posted.clone().into(),
+            self.location.clone().into(),
+            self.remuneration.clone().into(),
+            tags.into(),
+            self.apply.clone().into(),
+            self.site.clone().into(),
+            self.rem_lower.into(),
+            self.rem_upper.into(),
+            self.seniority.clone().into(),
+            self.work_mode.clone().into(),
+            self.employment_type.clone().into(),
+            self.company_url.clone().into(),
+            self.currency.clone().into(),
+            self.logo_url.clone().into(),
+            self.deadline.clone().into(),
+            self.applicants.into(),
+            self.source_url.clone().into(),
+            self.primary_language.clone().into(),
+            self.category.clone().into(),
+            self.is_remote.into(),
+        ])
+    }
+
+    /// Reads a job from a row of the jobs table. Columns are read by name rather than position, so
+    /// the row can come from any query selecting the title, company, date posted, apply link and
+    /// site, whatever the column order. Other columns missing from the query are read as empty.
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        fn optional<T: FromSql>(row: &Row, name: &str) -> rusqlite::Result<Option<T>> {
+            match row.get::<_, Option<T>>(name) {
+                Err(rusqlite::Error::InvalidColumnName(_)) => Ok(None),
+                res => res,
+            }
+        }
+        let text = |name: &str| -> rusqlite::Result<String> {
+            Ok(optional::<String>(row, name)?.unwrap_or_default())
+        };
+        let tags = text("tags")?;
+        let tags = match tags.as_str() {
+            "" => vec![],
+            tags => serde_json::from_str(tags).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    row.as_ref().column_index("tags").unwrap_or_default(),
+                    Type::Text,
+                    Box::new(e),
+                )
+            })?,
+        };
+        Ok(Self {
+            title: row.get("title")?,
+            company: row.get("company")?,
+            date_posted: row.get("date_posted")?,
+            location: text("location")?,
+            remuneration: text("remuneration")?,
+            tags,
+            apply: row.get("apply")?,
+            site: row.get("site")?,
+            rem_lower: optional(row, "rem_lower")?,
+            rem_upper: optional(row, "rem_upper")?,
+            seniority: text("seniority")?,
+            work_mode: text("work_mode")?,
+            employment_type: text("employment_type")?,
+            company_url: optional(row, "company_url")?,
+            currency: optional(row, "currency")?,
+            logo_url: optional(row, "logo_url")?,
+            deadline: optional(row, "deadline")?,
+            applicants: optional(row, "applicants")?,
+            source_url: text("source_url")?,
+            primary_language: optional(row, "primary_language")?,
+            category: optional(row, "category")?,
+            is_remote: optional(row, "is_remote")?.unwrap_or_default(),
+        })
+    }
+
+    /// Sets the derived fields (seniority, work mode, whether it's remote, employment type and
+    /// primary language) from the title, location and tags. Returns true if any of the derived fields changed.
+    pub fn classify(&mut self) -> bool {
+        let seniority = classify::seniority(&self.title);
+        let work_mode = classify::work_mode(&self.title, &self.location, &self.tags);
+        let is_remote = work_mode == "remote";
+        let employment_type = classify::employment_type(&self.title, &self.tags);
+        let primary_language = classify::primary_language(&self.title, &self.tags);
+        let changed = seniority != self.seniority
+            || work_mode != self.work_mode
+            || is_remote != self.is_remote
+            || employment_type != self.employment_type
+            || primary_language != self.primary_language;
+        self.seniority = seniority;
+        self.is_remote = is_remote;
+        self.work_mode = work_mode;
+        self.employment_type = employment_type;
+        self.primary_language = primary_language;
+        changed
+    }
+
+    /// Returns how many days ago the job was posted, or None if the posted date can't be parsed.
+    pub fn age_days(&self) -> Option<i64> {
+        let date = self.date_posted.get(..10)?;
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+        Some((Local::now().date_naive() - date).num_days())
+    }
+
+    /// Scores how relevant the job is, from recency, whether it lists remuneration and whether it's
+    /// remote, weighted by the given weights. Higher is more relevant.
+    pub fn relevance(&self, weights: &Relevance) -> f64 {
+        let recency = self
+            .age_days()
+            .map(|d| 0.5f64.powf(d.max(0) as f64 / RECENCY_HALF_LIFE_DAYS))
+            .unwrap_or_default();
+        let salary = match self.rem_upper.is_some() || !self.remuneration.trim().is_empty() {
+            true => 1.0,
+            false => 0.0,
+        };
+        let remote = match self.work_mode.as_str() {
+            "remote" => 1.0,
+            "hybrid" => 0.5,
+            _ => 0.0,
+        };
+        weights.recency * recency + weights.salary * salary + weights.remote * remote
+    }
+
+    /// Returns the posted date relative to today, e.g. "today", "3 days ago". The raw value is
+    /// returned if it can't be parsed, or "unknown" if it's empty.
+    pub fn relative_date_posted(&self) -> String {
+        match self.age_days() {
+            Some(0) => "today".to_string(),
+            Some(1) => "yesterday".to_string(),
+            Some(d) if d > 1 => format!("{d} days ago"),
+            Some(_) => self.date_posted.clone(),
+            None if self.date_posted.is_empty() => "unknown".to_string(),
+            None => self.date_posted.clone(),
+        }
+    }
+
+    /// Returns the recency bucket of the posted date, e.g. "This week".
+    pub fn recency(&self) -> Recency {
+        Recency::from_age_days(self.age_days())
+    }
+
+    /// Removes tracking parameters (e.g. "utm_source", "ref") from the apply link, keeping all
+    /// others. Links that can't be parsed are left as they are.
+    pub fn strip_tracking_params(&mut self) {
+        let Ok(mut url) = Url::parse(&self.apply) else {
+            return;
+        };
+        let is_tracking = |key: &str| {
+            let key = key.to_lowercase();
+            key.starts_with("utm_") || TRACKING_PARAMS.contains(&key.as_str())
+        };
+        if !url.query_pairs().any(|(key, _)| is_tracking(&key)) {
+            return;
+        }
+
+        let kept = url
+            .query_pairs()
+            .filter(|(key, _)| !is_tracking(key))
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect::<Vec<(String, String)>>();
+        if kept.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(kept);
+        }
+        self.apply = url.to_string();
+    }
+
+    /// Infers the company website from the apply link (or the domain of an apply email address).
+    /// Returns None when the apply link is hosted by an applicant tracking system or job board.
+    pub fn infer_company_url(&self) -> Option<String> {
+        let host = match self.apply.strip_prefix("mailto:") {
+            Some(address) => address.split('@').nth(1)?.split('?').next()?.to_lowercase(),
+            None => Url::parse(&self.apply).ok()?.host_str()?.to_lowercase(),
+        };
+        let site_host = Url::parse(&self.site)
+            .ok()
+            .and_then(|u| u.host_str().map(|h| h.to_lowercase()));
+        let is_hosted = |host: &str| {
+            Some(host) == site_host.as_deref()
+                || HOSTED_APPLY_DOMAINS
+                    .iter()
+                    .any(|d| host == *d || host.ends_with(&format!(".{d}")))
+        };
+        if host.is_empty() || is_hosted(&host) {
+            return None;
+        }
+
+        let domain = CAREERS_SUBDOMAINS
+            .iter()
+            .find_map(|sub| host.strip_prefix(sub))
+            .filter(|d| d.contains('.'))
+            .unwrap_or(&host);
+        Some(format!("https://{domain}"))
+    }
+
+    /// Trims and lowercases the tags, removing any duplicates while preserving their order.
+    fn normalize_tags(&mut self) {
+        let mut tags: Vec<String> = Vec::with_capacity(self.tags.len());
+        for tag in &self.tags {
+            let tag = tag.trim().to_lowercase();
+            if !tag.is_empty() && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        self.tags = tags;
+    }
+
+    /// Returns true if the apply link is the listing page the job was scraped from, because no
+    /// link of its own could be scraped.
+    pub fn apply_is_fallback(&self) -> bool {
+        !self.apply.is_empty() && self.apply == self.source_url
+    }
+
+    /// Completes this job with fields from another representation of the same posting (e.g. from
+    /// another site). Empty fields are filled from `other` and tags are unioned. When both jobs have
+    /// a value for a field, this job's value wins. The identity fields (title, company and site)
+    /// are never changed.
+    pub fn merge(&mut self, other: &Job) {
+        let fill = |field: &mut String, value: &String| {
+            if field.trim().is_empty() {
+                field.clone_from(value);
+            }
+        };
+        fill(&mut self.date_posted, &other.date_posted);
+        fill(&mut self.location, &other.location);
+        fill(&mut self.apply, &other.apply);
+        fill(&mut self.seniority, &other.seniority);
+        fill(&mut self.work_mode, &other.work_mode);
+        fill(&mut self.employment_type, &other.employment_type);
+        fill(&mut self.source_url, &other.source_url);
+        // the remuneration bounds are parsed from the remuneration, so they're taken together
+        if self.remuneration.trim().is_empty() && !other.remuneration.trim().is_empty() {
+            self.remuneration.clone_from(&other.remuneration);
+            self.rem_lower = other.rem_lower;
+            self.rem_upper = other.rem_upper;
+            self.currency.clone_from(&other.currency);
+        }
+        if self.company_url.is_none() {
+            self.company_url.clone_from(&other.company_url);
+        }
+        if self.logo_url.is_none() {
+            self.logo_url.clone_from(&other.logo_url);
+        }
+        if self.category.is_none() {
+            self.category.clone_from(&other.category);
+        }
+        if self.deadline.is_none() {
+            self.deadline.clone_from(&other.deadline);
+        }
+        if self.applicants.is_none() {
+            self.applicants = other.applicants;
+        }
+        for tag in &other.tags {
+            if !self.tags.contains(tag) {
+                self.tags.push(tag.clone());
+            }
+        }
+    }
+
+    /// The identity of a job posting across sites - its normalised title and company.
+    fn identity(&self) -> (String, String) {
+        (normalize(&self.title), normalize(&self.company))
+    }
+
+    /// The canonical identity of a posting on its site, e.g. for matching a stored job with a newly
+    /// scraped one. The key is the site followed by the normalised title and company, so two
+    /// representations of the same posting (differing only in case or whitespace) share a key. None
+    /// of the scraped sites give postings an id of their own, so the key is built from these fields.
+    pub fn dedup_key(&self) -> String {
+        let (title, company) = self.identity();
+        format!("{}|{title}|{company}", normalize(&self.site))
+    }
+
+    fn title_contains_any<S>(&self, v: &[S]) -> bool
+    where
+        S: AsRef<str>,
+    {
+        self.title_match(v).is_some()
+    }
+
+    /// Returns the first of the patterns found in the (lowercase) title.
+    fn title_match<'a, S>(&self, v: &'a [S]) -> Option<&'a str>
+    where
+        S: AsRef<str>,
+    {
+        let title = self.title.to_lowercase();
+        v.iter()
+            .map(|pat| pat.as_ref())
+            .find(|pat| title.contains(pat))
+    }
+
+    /// Explains whether the title filter includes or excludes this job, and why.
+    pub fn explain_title_filter(&self, title_filter: &TitleFilter) -> FilterExplanation {
+        if !title_filter.is_active() {
+            return FilterExplanation::FilterOff;
+        }
+        match self.title_match(&title_filter.keywords) {
+            Some(keyword) => FilterExplanation::Matched(keyword.to_string()),
+            None => FilterExplanation::NoMatch(title_filter.keywords.clone()),
+        }
+    }
+}
+
+/// The result of scraping a site on a refresh, with None for a site cut off by the scrape budget.
+type SiteResult = Result<Option<Vec<Job>>, ErrorKind>;
+
+/// Returns the line printed as a site's scrape completes, e.g. "✓ web3.career: 120 jobs". Sites
+/// are named by their host where they have one.
+fn progress_line(site: &str, jobs: usize) -> String {
+    let name = site_host(site).unwrap_or_else(|| site.to_string());
+    format!("✓ {name}: {jobs} jobs")
+}
+
+/// Runs a site's scrape, printing a progress line as soon as it completes if `progress` is set -
+/// they complete in any order, as the sites are scraped concurrently. A panic in the scrape is
+/// caught and returned as the site's error, so it doesn't take the other sites down with it.
+async fn with_progress<F>(site: &str, progress: bool, scrape: F) -> SiteResult
+where
+    F: Future<Output = SiteResult>,
+{
+    let mut scrape = pin!(scrape);
+    let res = future::poll_fn(|cx| {
+        panic::catch_unwind(AssertUnwindSafe(|| scrape.as_mut().poll(cx))).unwrap_or_else(
+            |payload| {
+                let msg = payload
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                Poll::Ready(Err(ErrorKind::Panicked(msg)))
+            },
+        )
+    })
+    .await;
+    if let (true, Ok(Some(jobs))) = (progress, &res) {
+        green_println!(progress_line(site, jobs.len()));
+    }
+    res
+}
+
+/// Collects the jobs of the sites scraped on a refresh, keyed by site. A site that failed is
+/// warned about and skipped, so one broken scraper doesn't stop the others' jobs being added, and
+/// sites cut off by the scrape budget are skipped too. Returns an error only if every site
+/// failed.
+fn site_jobs(results: Vec<(String, SiteResult)>) -> Result<Vec<Vec<Job>>, ErrorKind> {
+    let sites = results.len();
+    let mut job_vecs = Vec::with_capacity(sites);
+    let mut errors = Vec::new();
+    for (site, res) in results {
+        match res {
+            Ok(jobs) => {
+                if let Some(jobs) = &jobs {
+                    tracing::info!(site, jobs = jobs.len(), "scraped site");
+                }
+                job_vecs.extend(jobs)
+            }
+            Err(err) => {
+                red_println!(format!("Warning: {site} failed to scrape. {err}"));
+                errors.push(format!("{site}: {err}"));
+            }
+        }
+    }
+    match sites > 0 && errors.len() == sites {
+        true => Err(ErrorKind::AllSitesFailed(errors.join(" "))),
+        false => Ok(job_vecs),
+    }
+}
+
+/// Normalises a field for comparison - collapsing whitespace and lowercasing.
+fn normalize(s: &str) -> String {
+    s.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Why the title filter includes or excludes a job.
+#[derive(Debug, Eq, PartialEq)]
+pub enum FilterExplanation {
+    /// The filter is off, so every job is included.
+    FilterOff,
+    /// The title contains this keyword, so the job is included.
+    Matched(String),
+    /// The title contains none of these keywords, so the job is excluded.
+    NoMatch(Vec<String>),
+}
+
+impl FilterExplanation {
+    pub fn is_included(&self) -> bool {
+        !matches!(self, Self::NoMatch(_))
+    }
+}
+
+impl Display for FilterExplanation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FilterOff => write!(f, "Included - the title filter is off."),
+            Self::Matched(keyword) => {
+                write!(
+                    f,
+                    "Included - the title contains the keyword \"{keyword}\"."
+                )
+            }
+            Self::NoMatch(keywords) => write!(
+                f,
+                "Excluded - the title contains none of the keywords: {}.",
+                keywords.join(", ")
+            ),
+        }
+    }
+}
+
+/// Salary statistics over a set of jobs, in thousands. Each job's salary is the midpoint of its
+/// remuneration range.
+#[derive(Debug, PartialEq)]
+pub struct SalaryStats {
+    pub min: f64,
+    pub median: f64,
+    pub max: f64,
+    /// The number of jobs with salary info.
+    pub with_salary: usize,
+    pub total: usize,
+}
+
+impl SalaryStats {
+    /// Computes the stats for the jobs, or None if none of them have salary info.
+    pub fn from_jobs<'a, I>(jobs: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = &'a Job>,
+    {
+        let mut total = 0;
+        let mut salaries = Vec::new();
+        for job in jobs {
+            total += 1;
+            if let Some(upper) = job.rem_upper {
+                let lower = job.rem_lower.unwrap_or(upper);
+                salaries.push((lower + upper) as f64 / 2000.0);
+            }
+        }
+        salaries.sort_by(f64::total_cmp);
+        let n = salaries.len();
+        let median = match n {
+            0 => return None,
+            n if n % 2 == 0 => (salaries[n / 2 - 1] + salaries[n / 2]) / 2.0,
+            n => salaries[n / 2],
+        };
+        Some(Self {
+            min: salaries[0],
+            median,
+            max: salaries[n - 1],
+            with_salary: n,
+            total,
+        })
+    }
+}
+
+impl Display for SalaryStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Salary: min {:.0}k, median {:.0}k, max {:.0}k. {} of {} jobs ({:.0}%) list a salary.",
+            self.min,
+            self.median,
+            self.max,
+            self.with_salary,
+            self.total,
+            self.with_salary as f64 / self.total as f64 * 100.0
+        )
+    }
+}
+
+/// How recently a job was posted, for grouping results. Ordered from most to least recent.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Recency {
+    Today,
+    ThisWeek,
+    ThisMonth,
+    Older,
+    Unknown,
+}
+
+impl Recency {
+    /// Buckets a job's age in days. Dates in the future count as today, and a missing or
+    /// unparseable date is unknown.
+    pub fn from_age_days(age_days: Option<i64>) -> Self {
+        match age_days {
+            None => Self::Unknown,
+            Some(d) if d <= 0 => Self::Today,
+            Some(d) if d < 7 => Self::ThisWeek,
+            Some(d) if d < 30 => Self::ThisMonth,
+            Some(_) => Self::Older,
+        }
+    }
+}
+
+impl Display for Recency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Today => "Today",
+            Self::ThisWeek => "This week",
+            Self::ThisMonth => "This month",
+            Self::Older => "Older",
+            Self::Unknown => "Unknown date",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// The change in a company's number of jobs between two snapshots.
+#[derive(Debug, Eq, PartialEq)]
+pub struct CompanyTrend {
+    pub company: String,
+    pub before: i64,
+    pub after: i64,
+}
+
+impl CompanyTrend {
+    pub fn delta(&self) -> i64 {
+        self.after - self.before
+    }
+}
+
+impl Display for CompanyTrend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let line = format!(
+            "{:+4}  {} ({} → {})",
+            self.delta(),
+            self.company,
+            self.before,
+            self.after
+        );
+        match self.delta() >= 0 {
+            true => write!(f, "{} {}", "▲".green(), line.green()),
+            false => write!(f, "{} {}", "▼".red(), line.red()),
+        }
+    }
+}
+
+/// A company's open roles and the tags across them, keyed by normalized company name.
+#[derive(Debug, Default)]
+pub struct CompanyProfile {
+    pub company: String,
+    pub jobs: i64,
+    pub tags: BTreeSet<String>,
+}
+
+/// A company whose postings share tags with another company's.
+#[derive(Debug, PartialEq)]
+pub struct RelatedCompany {
+    pub company: String,
+    pub jobs: i64,
+    pub shared_tags: Vec<String>,
+    /// The share of the two companies' tags they have in common, from 0 to 1.
+    pub score: f64,
+}
+
+impl Display for RelatedCompany {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.2}  {} ({} open role{}) - {}",
+            self.score,
+            self.company.bold(),
+            self.jobs,
+            if self.jobs == 1 { "" } else { "s" },
+            self.shared_tags.join(", ")
+        )
+    }
+}
+
+/// A salary range, e.g. "100k-200k", ">150k" or "<80k", held as whole amounts (e.g. 100000).
+/// Either end may be open.
+#[derive(Debug, Eq, PartialEq)]
+pub struct SalaryRange {
+    pub lower: Option<i64>,
+    pub upper: Option<i64>,
+}
+
+impl SalaryRange {
+    /// Returns the SQL condition matching jobs whose remuneration range overlaps this range. Jobs
+    /// without a remuneration never match.
+    pub fn condition(&self) -> String {
+        let mut conditions = vec!["rem_upper is not null".to_string()];
+        if let Some(lower) = self.lower {
+            conditions.push(format!("rem_upper >= {lower}"));
+        }
+        if let Some(upper) = self.upper {
+            conditions.push(format!("rem_lower <= {upper}"));
+        }
+        conditions.join(" and ")
+    }
+
+    /// Parses an amount such as "150k", "$150k" or "150000" into a whole amount, e.g. 150000.
+    fn parse_amount(s: &str) -> Option<i64> {
+        let s = s
+            .trim()
+            .trim_start_matches(['$', '€', '£'])
+            .replace(',', "");
+        let (n, thousands) = match s.strip_suffix(['k', 'K']) {
+            Some(n) => (n.trim().parse::<i64>().ok()?, true),
+            None => (s.parse::<i64>().ok()?, false),
+        };
+        // plain amounts under 1000 are in thousands
+        match thousands || n < 1000 {
+            true => n.checked_mul(1000),
+            false => Some(n),
+        }
+    }
+}
+
+impl FromStr for SalaryRange {
+    type Err = ErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || {
+            ErrorKind::SqliteQuery(format!(
+                "Salary must be a range like \"100k-200k\", \">150k\" or \"<80k\", got \"{}\".",
+                s.trim()
+            ))
+        };
+        let s = s.trim();
+        let amount = |a: &str| Self::parse_amount(a).ok_or_else(err);
+        let range = if let Some(lower) = s.strip_prefix('>') {
+            Self {
+                lower: Some(amount(lower.trim_start_matches('='))?),
+                upper: None,
+            }
+        } else if let Some(upper) = s.strip_prefix('<') {
+            Self {
+                lower: None,
+                upper: Some(amount(upper.trim_start_matches('='))?),
+            }
+        } else if let Some((lower, upper)) = s.split_once('-') {
+            Self {
+                lower: Some(amount(lower)?),
+                upper: Some(amount(upper)?),
+            }
+        } else {
+            Err(err())?
+        };
+        if let (Some(lower), Some(upper)) = (range.lower, range.upper) {
+            if lower > upper {
+                Err(err())?;
+            }
+        }
+        Ok(range)
+    }
+}
+
+/// Returns the words searched for with "like" in a query, e.g. "rust" for
+/// `title like "%rust%"`.
+pub fn search_terms(query: &str) -> Vec<String> {
+    let like = Regex::new(r#"(?i)\blike\s+(?:'([^']*)'|"([^"]*)")"#).unwrap();
+    let mut terms = Vec::new();
+    for cap in like.captures_iter(query) {
+        let pattern = cap.get(1).or(cap.get(2)).map_or("", |m| m.as_str());
+        for term in pattern.split(['%', '_']) {
+            let term = term.trim().to_lowercase();
+            if !term.is_empty() && !terms.contains(&term) {
+                terms.push(term);
             }
         }
-        false
+    }
+    terms
+}
+
+/// Colours text green, with any (case-insensitive) matches of the terms highlighted. Colours are
+/// left out when colour is turned off (e.g. with NO_COLOR), leaving the plain text.
+pub fn highlight(text: &str, terms: &[String]) -> String {
+    highlight_spans(text, terms)
+        .into_iter()
+        .map(|(span, matched)| match matched {
+            true => span.bold().yellow().to_string(),
+            false => span.green().to_string(),
+        })
+        .collect()
+}
+
+/// Splits text into the spans to highlight, with whether each is a (case-insensitive) match of one
+/// of the terms.
+fn highlight_spans<'a>(text: &'a str, terms: &[String]) -> Vec<(&'a str, bool)> {
+    let terms = terms
+        .iter()
+        .filter(|t| !t.is_empty())
+        .map(|t| regex::escape(t))
+        .collect::<Vec<String>>();
+    if terms.is_empty() {
+        return vec![(text, false)];
+    }
+    let re = Regex::new(&format!("(?i){}", terms.join("|"))).unwrap();
+
+    let mut spans = Vec::new();
+    let mut last = 0;
+    for m in re.find_iter(text) {
+        spans.push((&text[last..m.start()], false));
+        spans.push((m.as_str(), true));
+        last = m.end();
+    }
+    spans.push((&text[last..], false));
+    spans
+}
+
+/// A Job displayed with the search terms highlighted in its title.
+pub struct Highlighted<'a> {
+    job: &'a Job,
+    terms: &'a [String],
+}
+
+impl Debug for Highlighted<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.job.fmt_with_highlights(f, self.terms)
     }
 }
 
 /// Pretty print Job for debug.
 impl Debug for Job {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let remuneration = if self.remuneration.is_empty() {
-            NOT_AVAILABLE
+        self.fmt_with_highlights(f, &[])
+    }
+}
+
+impl Job {
+    /// Returns the job for display with the search terms highlighted in its title.
+    pub fn highlighted<'a>(&'a self, terms: &'a [String]) -> Highlighted<'a> {
+        Highlighted { job: self, terms }
+    }
+
+    fn fmt_with_highlights(&self, f: &mut Formatter<'_>, terms: &[String]) -> std::fmt::Result {
+        let remuneration = match (self.remuneration.as_str(), &self.currency) {
+            ("", _) => NOT_AVAILABLE.to_string(),
+            (remuneration, Some(currency)) => format!("{remuneration} ({currency})"),
+            (remuneration, None) => remuneration.to_string(),
+        };
+        let date_posted = if config::get().relative_dates {
+            self.relative_date_posted()
         } else {
-            &self.remuneration
+            self.date_posted.clone()
         };
         let location = if self.location.is_empty() {
             NOT_AVAILABLE
@@ -59,20 +977,69 @@ impl Debug for Job {
         } else {
             NOT_AVAILABLE.to_string()
         };
+        // followed companies stand out in results
+        let companies = config::get().companies;
+        let followed = companies.iter().map(String::as_str).collect::<Vec<&str>>();
+        let company = match classify::company_matches(&self.company, &followed) {
+            true => format!("{} ★", self.company).bold().yellow(),
+            false => self.company.green(),
+        };
         let apply = if self.apply.is_empty() {
             NOT_AVAILABLE.green()
+        } else if self.apply_is_fallback() {
+            format!("{} (listing page)", self.apply).bright_blue()
         } else {
             self.apply.bright_blue()
         };
         write!(
             f,
-            "{} {}\n{} {}\n{} {}\n{} {}\n{} {}\n{} {}\n{} {}\n{} {}\n{}",
+            "{} {}\n{} {}\n",
             "Title:".bold().bright_green(),
-            self.title.green(),
+            highlight(&self.title, terms),
             "Company:".bold().bright_green(),
-            self.company.green(),
+            company,
+        )?;
+        if let Some(company_url) = &self.company_url {
+            writeln!(
+                f,
+                "{} {}",
+                "Company Website:".bold().bright_green(),
+                company_url.bright_blue()
+            )?;
+        }
+        writeln!(
+            f,
+            "{} {}",
             "Date Posted:".bold().bright_green(),
-            self.date_posted.green(),
+            date_posted.green()
+        )?;
+        if let Some(deadline) = &self.deadline {
+            writeln!(
+                f,
+                "{} {}",
+                "Deadline:".bold().bright_green(),
+                deadline.green()
+            )?;
+        }
+        if let Some(applicants) = self.applicants {
+            writeln!(
+                f,
+                "{} {}",
+                "Applicants:".bold().bright_green(),
+                applicants.to_string().green()
+            )?;
+        }
+        if let Some(category) = &self.category {
+            writeln!(
+                f,
+                "{} {}",
+                "Category:".bold().bright_green(),
+                category.green()
+            )?;
+        }
+        write!(
+            f,
+            "{} {}\n{} {}\n{} {}\n{} {}\n{} {}",
             "Location:".bold().bright_green(),
             location.green(),
             "Remuneration:".bold().bright_green(),
@@ -83,10 +1050,11 @@ impl Debug for Job {
             apply,
             "Site:".bold().bright_green(),
             self.site.bright_blue(),
-            "+-----------------------------------------------------------------------------------\
-            ---------------------------------+"
-                .green()
-        )
+        )?;
+        match config::get().separator.line() {
+            Some(separator) => write!(f, "\n{}", separator.green()),
+            None => Ok(()),
+        }
     }
 }
 
@@ -104,13 +1072,17 @@ pub trait JobsDbBuilder {
     where
         Self: Sized;
 
+    /// Merges duplicate postings of the same job (e.g. the same job listed on more than one
+    /// jobsite) into a single Job, keeping the first and taking the union of all their tags.
+    fn dedup(self) -> Self;
+
     /// An optional filter to include only jobs of interest.
     fn filter<F>(self, condition: F) -> Self
     where
         F: Fn(&Job) -> bool;
 
-    /// Adds jobs to the SQLite database. This is the completing method.
-    fn add_to_db(self) -> Result<(), Self::Error>;
+    /// Adds jobs to the SQLite database through a connection. This is the completing method.
+    fn add_to_db(self, conn: &mut Connection) -> Result<(), Self::Error>;
 }
 
 /// Type alias for a job vector.
@@ -121,110 +1093,1782 @@ type Jobs = Vec<Job>;
 pub struct SoftwareJobs(Jobs);
 
 impl SoftwareJobs {
-    pub async fn init_repo() -> Result<(), ErrorKind> {
-        let web3_careers = Web3Careers::new().scrape().await?.jobs;
-        let crypto_jobs_list = CryptoJobsList::new().scrape().await?.jobs;
-        let solana_jobs = SolanaJobs::new().scrape().await?.jobs;
-        let substrate_jobs = SubstrateJobs::new().scrape().await?.jobs;
-        let near_jobs = NearJobs::new().scrape().await?.jobs;
+    /// Sets the company website for each job, inferred from its apply link. Only the apply link
+    /// is used, so this makes no extra requests.
+    pub fn with_company_urls(mut self) -> Self {
+        for job in &mut self.0 {
+            job.company_url = job.infer_company_url();
+        }
+        self
+    }
 
-        SoftwareJobs::new()
-            .import(vec![
-                web3_careers,
-                crypto_jobs_list,
-                solana_jobs,
-                substrate_jobs,
-                near_jobs,
-            ])
-            .filter(|job| {
-                job.title_contains_any(vec!["developer", "engineer", "engineering", "technical"])
-            }) // optional filter - in this case filter on engineering jobs
-            .add_to_db()?;
+    /// Uses the listing page a job was scraped from as its apply link when no apply link of its
+    /// own could be scraped, so the posting can still be reached.
+    pub fn with_fallback_apply_links(mut self) -> Self {
+        for job in &mut self.0 {
+            if job.apply.trim().is_empty() {
+                job.apply.clone_from(&job.source_url);
+            }
+        }
+        self
+    }
 
-        Ok(())
+    /// Keeps only jobs whose location matches one of the regions. If "remote" is one of the
+    /// regions, remote jobs are kept wherever they're located.
+    pub fn locations(self, regions: Vec<&str>) -> Self {
+        let include_remote = regions
+            .iter()
+            .any(|r| r.trim().eq_ignore_ascii_case("remote"));
+        self.filter(|job| {
+            (include_remote && job.work_mode == "remote")
+                || classify::location_matches(&job.location, &regions)
+        })
     }
-}
 
-impl JobsDbBuilder for SoftwareJobs {
-    type Error = ErrorKind;
+    /// Keeps only the jobs from the companies, compared after normalisation (so "Coinbase" matches
+    /// "Coinbase, Inc.").
+    pub fn companies(self, companies: Vec<&str>) -> Self {
+        self.filter(|job| classify::company_matches(&job.company, &companies))
+    }
 
-    fn new() -> Self {
-        Self(Default::default())
+    /// Scrapes every site and adds the jobs to the DB through the connection. With `progress`, a
+    /// line is printed as each site completes.
+    pub async fn init_repo(conn: &mut Connection, progress: bool) -> Result<(), ErrorKind> {
+        Self::scrape_jobs(progress).await?.add_to_db(conn)
     }
 
-    fn import(mut self, job_vecs: Vec<Vec<Job>>) -> Self
-    where
-        Self: Sized,
-    {
-        for vec in job_vecs {
-            self.0.extend(vec)
+    /// Scrapes every site, returning the jobs to add to the DB - deduplicated and filtered by the
+    /// configured title filter, locations and followed companies. With `progress`, a line is
+    /// printed as each site completes.
+    pub async fn scrape_jobs(progress: bool) -> Result<Self, ErrorKind> {
+        let budget = ScrapeBudget::start(config::get().scrape_budget);
+        let retries = config::get().empty_retries;
+        let max_pages = config::get().web3_careers_pages;
+        // one client for every site, so connections are pooled and kept alive across them
+        let client = scraper::client();
+        let client = &client;
+        // the sites are scraped concurrently, so a refresh takes about as long as the slowest site.
+        // The rate limiter still bounds the requests in flight to each host.
+        let custom_sites = config::get().custom_sites;
+        let custom_urls = custom_sites
+            .iter()
+            .map(|s| s.site_url())
+            .collect::<Vec<_>>();
+        let custom_scrapes = custom_sites.iter().zip(&custom_urls).map(|(site, url)| {
+            let scrape = scrape_retrying_empty(url, retries, || async {
+                Ok(CustomSite::new(site.clone()).scrape(client).await?.jobs)
+            });
+            with_progress(&site.name, progress, budget.run(&site.name, scrape))
+        });
+        let boards = config::get().greenhouse_boards;
+        let board_urls = boards
+            .iter()
+            .map(|board| GreenhouseJobs::new().with_board(board).board_url())
+            .collect::<Vec<_>>();
+        let board_scrapes = boards.iter().zip(&board_urls).map(|(board, url)| {
+            let scrape = scrape_retrying_empty(url, retries, || async {
+                let site = GreenhouseJobs::new().with_board(board);
+                Ok(site.scrape(client).await?.jobs)
+            });
+            with_progress(url, progress, budget.run(url, scrape))
+        });
+        let common_boards = config::get().common_boards;
+        let common_scrapes = common_boards.iter().map(|board| {
+            let scrape = scrape_retrying_empty(&board.url, retries, || async {
+                Ok(CommonSite::new(board.clone()).scrape(client).await?.jobs)
+            });
+            with_progress(&board.url, progress, budget.run(&board.url, scrape))
+        });
+        let (web3_careers, crypto_jobs_list, common, custom, greenhouse) = tokio::join!(
+            with_progress(
+                WEB3_CAREERS_URL,
+                progress,
+                budget.run(
+                    WEB3_CAREERS_URL,
+                    scrape_retrying_empty(WEB3_CAREERS_URL, retries, || async {
+                        Ok(Web3Careers::new()
+                            .with_max_pages(max_pages)
+                            .scrape(client)
+                            .await?
+                            .jobs)
+                    }),
+                ),
+            ),
+            with_progress(
+                CRYPTO_JOBS_LIST_URL,
+                progress,
+                budget.run(
+                    CRYPTO_JOBS_LIST_URL,
+                    scrape_retrying_empty(CRYPTO_JOBS_LIST_URL, retries, || async {
+                        Ok(CryptoJobsList::new().scrape(client).await?.jobs)
+                    }),
+                ),
+            ),
+            join_all(common_scrapes),
+            join_all(custom_scrapes),
+            join_all(board_scrapes),
+        );
+
+        let results = [
+            (WEB3_CAREERS_URL.to_string(), web3_careers),
+            (CRYPTO_JOBS_LIST_URL.to_string(), crypto_jobs_list),
+        ]
+        .into_iter()
+        .chain(
+            common_boards
+                .iter()
+                .map(|board| board.url.clone())
+                .zip(common),
+        )
+        .chain(
+            custom_sites
+                .iter()
+                .map(|site| site.name.clone())
+                .zip(custom),
+        )
+        .chain(board_urls.iter().cloned().zip(greenhouse))
+        .collect();
+        let job_vecs = site_jobs(results)?;
+        if !budget.cut_off().is_empty() {
+            red_println!(format!(
+                "Warning: the scrape budget ran out, so these sites were cut off: {}",
+                budget.cut_off().join(", ")
+            ));
         }
-        self
-    }
 
-    fn filter<F>(mut self, condition: F) -> Self
-    where
+        let title_filter = config::get().title_filter;
+        let jobs = SoftwareJobs::new()
+            .import(job_vecs)
+            .dedup()
+            .with_company_urls()
+            .with_fallback_apply_links();
+        let jobs = if title_filter.is_active() {
+            jobs.filter(|job| job.title_contains_any(&title_filter.keywords))
+        } else {
+            jobs
+        };
+        let locations = config::get().locations;
+        let jobs = if locations.is_empty() {
+            jobs
+        } else {
+            jobs.locations(locations.iter().map(String::as_str).collect())
+        };
+        let config = config::get();
+        let jobs = if config.follow_only && !config.companies.is_empty() {
+            jobs.companies(config.companies.iter().map(String::as_str).collect())
+        } else {
+            jobs
+        };
+
+        Ok(jobs)
+    }
+
+    /// Opens the configured DB - the file at the DB path, or the in-memory DB for ":memory:".
+    pub fn open_db() -> Result<Connection, ErrorKind> {
+        Self::open_db_at(&config::get().db_path)
+    }
+
+    /// Opens the DB at a path, or the in-memory DB for ":memory:".
+    pub fn open_db_at(path: &Path) -> Result<Connection, ErrorKind> {
+        match path.to_str() == Some(MEMORY_DB) {
+            true => Connection::open_with_flags(
+                MEMORY_DB_URI,
+                OpenFlags::SQLITE_OPEN_READ_WRITE
+                    | OpenFlags::SQLITE_OPEN_CREATE
+                    | OpenFlags::SQLITE_OPEN_URI,
+            ),
+            false => Connection::open(path),
+        }
+        .map_err(|e| ErrorKind::SqliteConnection(e.to_string()))
+    }
+
+    /// Checks the DB file isn't corrupted, by opening it and running SQLite's integrity check. A
+    /// file that doesn't exist yet is fine. Returns ErrorKind::CorruptDb if it's corrupted (or
+    /// isn't a database at all), so it can be told apart from other connection errors.
+    pub fn check_integrity(path: &Path) -> Result<(), ErrorKind> {
+        if !path.exists() {
+            return Ok(());
+        }
+        let corrupt = |e: rusqlite::Error| match e.sqlite_error_code() {
+            Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase) => {
+                ErrorKind::CorruptDb(e.to_string())
+            }
+            _ => ErrorKind::SqliteConnection(e.to_string()),
+        };
+        let conn = Connection::open(path).map_err(corrupt)?;
+        let result: String = conn
+            .query_row("pragma integrity_check", (), |row| row.get(0))
+            .map_err(corrupt)?;
+        match result.as_str() {
+            "ok" => Ok(()),
+            problem => Err(ErrorKind::CorruptDb(problem.to_string())),
+        }
+    }
+
+    /// Moves a corrupted DB file (and its WAL files) aside to "<file>.corrupt-<timestamp>", so a
+    /// fresh DB is created in its place on the next refresh. Returns the backup's path.
+    pub fn set_aside_corrupt_db(path: &Path) -> Result<PathBuf, ErrorKind> {
+        let suffix = format!(".corrupt-{}", Local::now().format("%Y%m%d%H%M%S"));
+        let with_suffix = |path: &Path, suffix: &str| {
+            let mut name = path.as_os_str().to_owned();
+            name.push(suffix);
+            PathBuf::from(name)
+        };
+        let backup = with_suffix(path, &suffix);
+        fs::rename(path, &backup)
+            .map_err(|e| ErrorKind::SqliteConnection(format!("Can't back up the DB. {e}")))?;
+        for wal_suffix in ["-wal", "-shm"] {
+            let wal = with_suffix(path, wal_suffix);
+            if wal.exists() {
+                fs::rename(&wal, with_suffix(&backup, wal_suffix)).map_err(|e| {
+                    ErrorKind::SqliteConnection(format!("Can't back up the DB. {e}"))
+                })?;
+            }
+        }
+        Ok(backup)
+    }
+
+    /// Drops the jobs table and recreates it empty, and empties the history and favorites tables
+    /// (if they exist), so no job from before the reset is kept.
+    pub fn reset(conn: &Connection) -> Result<(), ErrorKind> {
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        tx.execute("drop table if exists jobs", ())
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        Self::create_table(&tx, "jobs")?;
+        for table in [HISTORY_TABLE, FAVORITES_TABLE] {
+            if !Self::table_columns(&tx, table)?.is_empty() {
+                tx.execute(&format!("delete from {table}"), ())
+                    .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+            }
+        }
+        tx.commit()
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))
+    }
+
+    /// Copies the DB to a file named for the given date (e.g. "jobs-2024-05-06.db") in a directory,
+    /// creating the directory if needed. A snapshot already taken that day is replaced. The copy
+    /// is made with SQLite's backup API, so it's consistent even while the DB is being written.
+    /// Returns the snapshot's path.
+    pub fn snapshot(conn: &Connection, dir: &Path, date: NaiveDate) -> Result<PathBuf, ErrorKind> {
+        fs::create_dir_all(dir)
+            .map_err(|e| ErrorKind::Snapshot(format!("Can't create \"{}\". {e}", dir.display())))?;
+        let path = dir.join(format!("jobs-{}.db", date.format("%Y-%m-%d")));
+        let mut dest = Connection::open(&path).map_err(|e| ErrorKind::Snapshot(e.to_string()))?;
+        Backup::new(conn, &mut dest)
+            .and_then(|backup| backup.run_to_completion(256, Duration::ZERO, None))
+            .map_err(|e| ErrorKind::Snapshot(e.to_string()))?;
+        Ok(path)
+    }
+
+    /// Returns the snapshots (e.g. "jobs-2024-05-06.db") in a directory, oldest first. A
+    /// directory that doesn't exist has no snapshots.
+    pub fn snapshots(dir: &Path) -> Result<Vec<PathBuf>, ErrorKind> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => Err(ErrorKind::Snapshot(format!(
+                "Can't read \"{}\". {e}",
+                dir.display()
+            )))?,
+        };
+        let mut snapshots = entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let name = path.file_name()?.to_str()?;
+                let date = name.strip_prefix("jobs-")?.strip_suffix(".db")?;
+                let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+                Some((date, path))
+            })
+            .collect::<Vec<_>>();
+        snapshots.sort();
+        Ok(snapshots.into_iter().map(|(_, path)| path).collect())
+    }
+
+    /// Returns the condition for the jobs the last refresh found. Jobs a refresh no longer finds
+    /// are kept but flagged stale - except in DBs (e.g. old snapshots) from before they were kept.
+    fn live_condition(conn: &Connection) -> Result<&'static str, ErrorKind> {
+        match Self::table_columns(conn, "jobs")?.contains("stale") {
+            true => Ok("not stale"),
+            false => Ok("1"),
+        }
+    }
+
+    /// Counts each company's jobs, keyed by normalised company name so different spellings (e.g.
+    /// "Acme" and "Acme, Inc.") are counted together. Each count has the most common spelling.
+    /// Stale jobs aren't counted.
+    pub fn company_counts(conn: &Connection) -> Result<HashMap<String, (String, i64)>, ErrorKind> {
+        let mut stmt = conn
+            .prepare(&format!(
+                "select company, count(*) from jobs where {} group by company \
+                order by count(*) desc",
+                Self::live_condition(conn)?
+            ))
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        let rows = stmt
+            .query_map((), |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        let mut counts: HashMap<String, (String, i64)> = HashMap::new();
+        for row in rows {
+            let (company, cnt) = row.map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+            let key = classify::normalize_company(&company);
+            if key.is_empty() {
+                continue;
+            }
+            counts.entry(key).or_insert((company, 0)).1 += cnt;
+        }
+        Ok(counts)
+    }
+
+    /// Returns the profile of each company, keyed by normalized company name. The tags are stored
+    /// normalized, so the same tag from different sites is counted once. Stale jobs are left out.
+    pub fn company_profiles(
+        conn: &Connection,
+    ) -> Result<HashMap<String, CompanyProfile>, ErrorKind> {
+        let mut stmt = conn
+            .prepare(&format!(
+                "select company, tags from jobs where {}",
+                Self::live_condition(conn)?
+            ))
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        let rows = stmt
+            .query_map((), |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        let mut profiles: HashMap<String, CompanyProfile> = HashMap::new();
+        for row in rows {
+            let (company, tags) = row.map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+            let key = classify::normalize_company(&company);
+            if key.is_empty() {
+                continue;
+            }
+            let tags: Vec<String> = serde_json::from_str(&tags).unwrap_or_default();
+            let profile = profiles.entry(key).or_insert_with(|| CompanyProfile {
+                company,
+                ..Default::default()
+            });
+            profile.jobs += 1;
+            profile.tags.extend(tags);
+        }
+        Ok(profiles)
+    }
+
+    /// Returns the companies sharing tags with the given company, most similar first, or None if
+    /// the company has no jobs. Similarity is the share of the two companies' combined tags that
+    /// they have in common.
+    pub fn related_companies(
+        profiles: &HashMap<String, CompanyProfile>,
+        company: &str,
+    ) -> Option<Vec<RelatedCompany>> {
+        let key = classify::normalize_company(company);
+        let target = profiles.get(&key)?;
+        let mut related = profiles
+            .iter()
+            .filter(|(other_key, _)| **other_key != key)
+            .filter_map(|(_, profile)| {
+                let shared_tags: Vec<String> =
+                    target.tags.intersection(&profile.tags).cloned().collect();
+                if shared_tags.is_empty() {
+                    return None;
+                }
+                let combined = target.tags.union(&profile.tags).count();
+                Some(RelatedCompany {
+                    company: profile.company.clone(),
+                    jobs: profile.jobs,
+                    score: shared_tags.len() as f64 / combined as f64,
+                    shared_tags,
+                })
+            })
+            .collect::<Vec<_>>();
+        related.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then(b.shared_tags.len().cmp(&a.shared_tags.len()))
+                .then_with(|| a.company.cmp(&b.company))
+        });
+        Some(related)
+    }
+
+    /// Returns the companies whose number of jobs changed from one set of counts to another,
+    /// biggest change first.
+    pub fn company_trends(
+        before: &HashMap<String, (String, i64)>,
+        after: &HashMap<String, (String, i64)>,
+    ) -> Vec<CompanyTrend> {
+        let mut trends = before
+            .keys()
+            .chain(after.keys().filter(|key| !before.contains_key(*key)))
+            .map(|key| {
+                let (company, after_cnt) = after
+                    .get(key)
+                    .cloned()
+                    .unwrap_or_else(|| (before[key].0.clone(), 0));
+                CompanyTrend {
+                    company,
+                    before: before.get(key).map(|(_, cnt)| *cnt).unwrap_or_default(),
+                    after: after_cnt,
+                }
+            })
+            .filter(|trend| trend.delta() != 0)
+            .collect::<Vec<_>>();
+        trends.sort_by(|a, b| {
+            b.delta()
+                .abs()
+                .cmp(&a.delta().abs())
+                .then(b.delta().cmp(&a.delta()))
+                .then_with(|| a.company.cmp(&b.company))
+        });
+        trends
+    }
+
+    /// Creates an empty table for jobs. Each job is stored once under its dedup key, which a
+    /// refresh upserts on, with the time of the refresh that first found it, and is flagged stale
+    /// once a refresh no longer finds it.
+    fn create_table(conn: &Connection, table: &str) -> Result<(), ErrorKind> {
+        conn.execute(
+            &format!(
+                "create table {table} ({}, dedup_key text not null unique, \
+                stale integer not null default 0, first_seen datetime)",
+                Job::column_defs()
+            ),
+            (),
+        )
+        .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Returns the names of a table's columns, which are none if it doesn't exist.
+    fn table_columns(conn: &Connection, table: &str) -> Result<HashSet<String>, ErrorKind> {
+        conn.prepare(&format!("select name from pragma_table_info('{table}')"))
+            .and_then(|mut stmt| {
+                stmt.query_map((), |row| row.get::<_, String>(0))?
+                    .collect::<Result<HashSet<String>, _>>()
+            })
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))
+    }
+
+    /// Converts the remuneration bounds stored by older versions in the jobs and history tables -
+    /// in thousands, with 0 for no remuneration - to whole amounts, with NULL for none. The DB's
+    /// `user_version` is then set to WHOLE_SALARIES_VERSION, so they're only converted once.
+    fn migrate_salaries(conn: &Connection) -> Result<(), ErrorKind> {
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        let version: i64 = tx
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        if version >= WHOLE_SALARIES_VERSION {
+            return Ok(());
+        }
+        for table in ["jobs", HISTORY_TABLE] {
+            if Self::table_columns(&tx, table)?.contains("rem_upper") {
+                tx.execute(
+                    &format!(
+                        "update {table} set rem_lower = nullif(rem_lower, 0) * 1000, \
+                        rem_upper = nullif(rem_upper, 0) * 1000"
+                    ),
+                    (),
+                )
+                .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+            }
+        }
+        tx.pragma_update(None, "user_version", WHOLE_SALARIES_VERSION)
+            .and_then(|_| tx.commit())
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))
+    }
+
+    /// Adds the job columns a table is missing, e.g. those added to jobs since it was created.
+    fn add_missing_columns(conn: &Connection, table: &str) -> Result<(), ErrorKind> {
+        let existing = Self::table_columns(conn, table)?;
+        for (name, ty) in JOB_COLUMNS
+            .iter()
+            .filter(|(name, _)| !existing.contains(*name))
+        {
+            // older rows have no value for the new column
+            let ty = ty.trim_end_matches(" not null");
+            conn.execute(&format!("alter table {table} add column {name} {ty}"), ())
+                .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Re-runs the classifiers over every stored job and updates the derived columns in place.
+    /// Returns the number of rows whose classification changed.
+    pub fn reclassify(conn: &Connection) -> Result<usize, ErrorKind> {
+        let mut stmt = conn
+            .prepare("select * from jobs")
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        let rows = stmt
+            .query_map((), |row| {
+                Ok((row.get::<_, i64>("id")?, Job::from_row(row)?))
+            })
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+
+        let mut changed = 0;
+        for (id, mut job) in rows {
+            if job.classify() {
+                conn.execute(
+                    "update jobs set seniority = ?1, work_mode = ?2, employment_type = ?3, \
+                    primary_language = ?4, is_remote = ?5 where id = ?6",
+                    (
+                        &job.seniority,
+                        &job.work_mode,
+                        &job.employment_type,
+                        &job.primary_language,
+                        job.is_remote,
+                        id,
+                    ),
+                )
+                .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+                changed += 1;
+            }
+        }
+
+        Ok(changed)
+    }
+}
+
+impl JobsDbBuilder for SoftwareJobs {
+    type Error = ErrorKind;
+
+    fn new() -> Self {
+        Self(Default::default())
+    }
+
+    fn import(mut self, job_vecs: Vec<Vec<Job>>) -> Self
+    where
+        Self: Sized,
+    {
+        for vec in job_vecs {
+            self.0.extend(vec.into_iter().map(|mut job| {
+                job.normalize_tags();
+                job.strip_tracking_params();
+                job.classify();
+                job
+            }))
+        }
+        self
+    }
+
+    fn dedup(mut self) -> Self {
+        let mut seen: HashMap<(String, String), usize> = HashMap::new();
+        let mut jobs: Jobs = Vec::with_capacity(self.0.len());
+        for job in self.0 {
+            match seen.get(&job.identity()) {
+                Some(&i) => {
+                    let survivor = &mut jobs[i];
+                    survivor.merge(&job);
+                    survivor.classify();
+                }
+                None => {
+                    seen.insert(job.identity(), jobs.len());
+                    jobs.push(job);
+                }
+            }
+        }
+        self.0 = jobs;
+        self
+    }
+
+    fn filter<F>(mut self, condition: F) -> Self
+    where
         F: Fn(&Job) -> bool,
     {
         self.0.retain(|job| condition(job));
         self
     }
 
-    fn add_to_db(self) -> Result<(), Self::Error> {
-        let conn =
-            Connection::open("jobs.db").map_err(|e| ErrorKind::SqliteConnection(e.to_string()))?;
-        conn.execute("drop table if exists jobs", ())
+    fn add_to_db(self, conn: &mut Connection) -> Result<(), Self::Error> {
+        // WAL mode lets the REPL keep reading while a refresh writes
+        conn.pragma_update(None, "journal_mode", "WAL")
             .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        let config = config::get();
+        let scraped_at = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        Self::write_jobs(conn, &self.0, config.insert_batch_size, &scraped_at)?;
+        if config.history {
+            Self::append_history(conn, &self.0, &scraped_at)?;
+        }
+        Ok(())
+    }
+}
+
+impl SoftwareJobs {
+    /// Updates the stored jobs with those from a refresh. Each job is upserted on its dedup key,
+    /// so a job already stored is updated in place (keeping its id), and stored jobs the refresh
+    /// didn't find - e.g. from a site that failed to scrape - are kept but flagged stale rather
+    /// than deleted. New jobs are stamped as first seen at `scraped_at`.
+    fn write_jobs(
+        conn: &mut Connection,
+        jobs: &[Job],
+        batch_size: Option<usize>,
+        scraped_at: &str,
+    ) -> Result<(), ErrorKind> {
+        if !Self::table_columns(conn, "jobs")?.contains("dedup_key") {
+            // a jobs table from before upserts has no dedup keys to upsert on, so it's replaced
+            conn.execute("drop table if exists jobs", ())
+                .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+            Self::create_table(conn, "jobs")?;
+        }
+        Self::add_missing_columns(conn, "jobs")?;
+        Self::migrate_salaries(conn)?;
+        if !Self::table_columns(conn, "jobs")?.contains("first_seen") {
+            // jobs stored before first seen times were kept have none
+            conn.execute("alter table jobs add column first_seen datetime", ())
+                .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        }
+
+        // jobs are upserted in a single transaction, or in one transaction per batch if a batch
+        // size is set, so a big refresh doesn't hold the write lock for its whole duration
+        let total = jobs.len();
+        let mut written = 0;
+        for batch in jobs.chunks(batch_size.unwrap_or(total).max(1)) {
+            let tx = conn
+                .transaction()
+                .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+            {
+                let mut stmt = tx
+                    .prepare_cached(&Job::upsert_sql("jobs"))
+                    .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+                for job in batch {
+                    let mut params = job.to_params()?;
+                    params.push(Value::Text(job.dedup_key()));
+                    params.push(Value::Text(scraped_at.to_string()));
+                    stmt.execute(params_from_iter(params))
+                        .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+                }
+            }
+            tx.commit()
+                .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+
+            written += batch.len();
+            if batch_size.is_some() {
+                green_println!(format!("{written}/{total} jobs written."));
+            }
+        }
+
+        let keys = serde_json::to_string(&jobs.iter().map(Job::dedup_key).collect::<Vec<_>>())
+            .map_err(|e| ErrorKind::Serialisation(e.to_string()))?;
         conn.execute(
-            "create table jobs (
-                id integer primary key,
-                title text not null,
-                company text not null,
-                date_posted date not null,
-                location text,
-                remuneration text,
-                tags json,
-                apply text not null,
-                site text not null,
-                rem_lower int,
-                rem_upper int
-            )",
+            "update jobs set stale = dedup_key not in (select value from json_each(?1))",
+            [keys],
+        )
+        .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        Ok(())
+    }
+}
+
+impl SoftwareJobs {
+    /// Appends jobs to the history table, stamped with the time of the scrape and their dedup key.
+    /// Unlike the jobs table the history table is never replaced, so it keeps every version of a
+    /// posting, and the `latest_jobs` view has the latest version of each. Columns added to jobs
+    /// since the table was created are added to it first.
+    fn append_history(
+        conn: &mut Connection,
+        jobs: &[Job],
+        scraped_at: &str,
+    ) -> Result<(), ErrorKind> {
+        Self::migrate_salaries(conn)?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        tx.execute(
+            &format!(
+                "create table if not exists {HISTORY_TABLE} ({}, scraped_at text not null, \
+                dedup_key text not null)",
+                Job::column_defs()
+            ),
             (),
         )
         .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        Self::add_missing_columns(&tx, HISTORY_TABLE)?;
+        tx.execute_batch(&format!(
+            "create index if not exists {HISTORY_TABLE}_dedup_key on {HISTORY_TABLE} (dedup_key);
+            create view if not exists latest_jobs as select * from {HISTORY_TABLE} h
+            where id = (select max(id) from {HISTORY_TABLE} where dedup_key = h.dedup_key);"
+        ))
+        .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        {
+            let names = JOB_COLUMNS.iter().map(|(name, _)| *name).join(", ");
+            let placeholders = (1..=JOB_COLUMNS.len() + 2)
+                .map(|i| format!("?{i}"))
+                .join(", ");
+            let mut stmt = tx
+                .prepare(&format!(
+                    "insert into {HISTORY_TABLE} ({names}, scraped_at, dedup_key) \
+                    values ({placeholders})"
+                ))
+                .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+            for job in jobs {
+                let mut params = job.to_params()?;
+                params.push(Value::Text(scraped_at.to_string()));
+                params.push(Value::Text(job.dedup_key()));
+                stmt.execute(params_from_iter(params))
+                    .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+            }
+        }
+        tx.commit()
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))
+    }
+
+    /// Returns every version of a job kept in the history table, oldest first, with the time each
+    /// was scraped. There's no history (rather than an error) if history mode was never on.
+    pub fn history(conn: &Connection, dedup_key: &str) -> Result<Vec<(String, Job)>, ErrorKind> {
+        let exists: bool = conn
+            .query_row(
+                "select count(*) > 0 from sqlite_master where type = 'table' and name = ?1",
+                [HISTORY_TABLE],
+                |row| row.get(0),
+            )
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        if !exists {
+            return Ok(Vec::new());
+        }
+        let mut stmt = conn
+            .prepare(&format!(
+                "select * from {HISTORY_TABLE} where dedup_key = ?1 order by id"
+            ))
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        let rows = stmt
+            .query_map([dedup_key], |row| {
+                Ok((row.get::<_, String>("scraped_at")?, Job::from_row(row)?))
+            })
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))
+    }
 
-        for job in &self.0 {
-            let tags = serde_json::to_string(&job.tags)
-                .map_err(|e| ErrorKind::Serialisation(e.to_string()))?;
+    /// Saves a stored job as a favorite, creating the favorites table if needed. Saving a favorite
+    /// again leaves it as it is. Returns the job saved.
+    pub fn save_favorite(conn: &Connection, id: i64) -> Result<Job, ErrorKind> {
+        let job = conn
+            .query_row("select * from jobs where id = ?1", [id], Job::from_row)
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => {
+                    ErrorKind::SqliteQuery(format!("No job with id {id}."))
+                }
+                e => ErrorKind::SqliteQuery(e.to_string()),
+            })?;
+        conn.execute_batch(&format!(
+            "create table if not exists {FAVORITES_TABLE} (dedup_key text primary key, \
+            saved_at datetime not null)"
+        ))
+        .and_then(|()| {
             conn.execute(
-                "insert into jobs (
-                 title,
-                 company,
-                 date_posted,
-                 location,
-                 remuneration,
-                 tags,
-                 apply,
-                 site,
-                 rem_lower,
-                 rem_upper
-            ) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-                [
-                    &job.title,
-                    &job.company,
-                    &job.date_posted,
-                    &job.location,
-                    &job.remuneration,
-                    &tags,
-                    &job.apply,
-                    &job.site,
-                    &job.rem_lower.to_string(),
-                    &job.rem_upper.to_string(),
-                ],
+                &format!(
+                    "insert or ignore into {FAVORITES_TABLE} (dedup_key, saved_at) \
+                    values (?1, datetime('now', 'localtime'))"
+                ),
+                [job.dedup_key()],
             )
+        })
+        .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        Ok(job)
+    }
+
+    /// Returns the stored jobs saved as favorites with their row ids, most recently saved first.
+    /// There are none (rather than an error) if no favorite was ever saved.
+    pub fn favorite_rows(conn: &Connection) -> Result<Vec<(i64, Job)>, ErrorKind> {
+        if Self::table_columns(conn, FAVORITES_TABLE)?.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut stmt = conn
+            .prepare(&format!(
+                "select jobs.* from jobs join {FAVORITES_TABLE} f using (dedup_key) \
+                order by f.saved_at desc, jobs.id"
+            ))
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        let rows = stmt
+            .query_map((), |row| Ok((row.get("id")?, Job::from_row(row)?)))
             .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))
+    }
+
+    /// Returns the dedup key of a stored job.
+    pub fn dedup_key_of(conn: &Connection, id: i64) -> Result<String, ErrorKind> {
+        conn.query_row("select * from jobs where id = ?1", [id], |row| {
+            Job::from_row(row)
+        })
+        .map(|job| job.dedup_key())
+        .map_err(|e| ErrorKind::SqliteQuery(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::{env, fs, process};
+
+    use chrono::{Duration, Local, NaiveDate};
+    use rusqlite::{params_from_iter, Connection};
+
+    use crate::site::{CryptoJobsList, DateFormatter};
+
+    use crate::config::{Relevance, TitleFilter};
+    use crate::ErrorKind;
+
+    use super::{
+        highlight_spans, progress_line, search_terms, site_jobs, with_progress, CompanyTrend,
+        FilterExplanation, Job, JobsDbBuilder, Recency, SalaryRange, SalaryStats, SoftwareJobs,
+        MEMORY_DB, WHOLE_SALARIES_VERSION,
+    };
+
+    fn job(title: &str, company: &str, site: &str, tags: &[&str]) -> Job {
+        Job {
+            title: title.to_string(),
+            company: company.to_string(),
+            site: site.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
         }
+    }
 
-        Ok(())
+    #[test]
+    fn test_age_days() {
+        let posted = |date_posted: String| Job {
+            date_posted,
+            ..Default::default()
+        };
+
+        let today = posted(CryptoJobsList::now_and_format());
+        assert_eq!(today.age_days(), Some(0));
+        assert_eq!(today.relative_date_posted(), "today");
+
+        let yesterday = posted(CryptoJobsList::sub_duration_and_format(Duration::days(1)));
+        assert_eq!(yesterday.relative_date_posted(), "yesterday");
+
+        let week = posted(format!(
+            "{} 10:00:00",
+            CryptoJobsList::sub_duration_and_format(Duration::days(7))
+        ));
+        assert_eq!(week.age_days(), Some(7));
+        assert_eq!(week.relative_date_posted(), "7 days ago");
+
+        assert_eq!(posted("May 6th".to_string()).age_days(), None);
+        assert_eq!(
+            posted("May 6th".to_string()).relative_date_posted(),
+            "May 6th"
+        );
+        assert_eq!(posted("".to_string()).relative_date_posted(), "unknown");
+    }
+
+    #[test]
+    fn test_recency() {
+        assert_eq!(Recency::from_age_days(Some(0)), Recency::Today);
+        assert_eq!(Recency::from_age_days(Some(-2)), Recency::Today);
+        assert_eq!(Recency::from_age_days(Some(6)), Recency::ThisWeek);
+        assert_eq!(Recency::from_age_days(Some(7)), Recency::ThisMonth);
+        assert_eq!(Recency::from_age_days(Some(30)), Recency::Older);
+        assert_eq!(Recency::from_age_days(None), Recency::Unknown);
+
+        let posted = |date_posted: &str| Job {
+            date_posted: date_posted.to_string(),
+            ..Default::default()
+        };
+        let today = Local::now().date_naive().format("%Y-%m-%d").to_string();
+        assert_eq!(posted(&today).recency(), Recency::Today);
+        assert_eq!(posted("2020-01-01").recency(), Recency::Older);
+        assert_eq!(posted("").recency(), Recency::Unknown);
+        assert_eq!(posted("3 days ago").recency(), Recency::Unknown);
+        assert_eq!(Recency::Unknown.to_string(), "Unknown date");
+    }
+
+    #[test]
+    fn test_locations() {
+        let located = |location: &str, work_mode: &str| Job {
+            title: location.to_string(),
+            location: location.to_string(),
+            work_mode: work_mode.to_string(),
+            ..Default::default()
+        };
+        let jobs = || {
+            SoftwareJobs::new().import(vec![vec![
+                located("London, UK", "onsite"),
+                located("Berlin, Germany", "onsite"),
+                located("Remote - Americas", "remote"),
+            ]])
+        };
+        let titles = |jobs: SoftwareJobs| jobs.0.into_iter().map(|j| j.title).collect::<Vec<_>>();
+
+        assert_eq!(
+            titles(jobs().locations(vec!["germany"])),
+            ["Berlin, Germany"]
+        );
+        assert_eq!(
+            titles(jobs().locations(vec!["london", "remote"])),
+            ["London, UK", "Remote - Americas"]
+        );
+    }
+
+    #[test]
+    fn test_fallback_apply_links() {
+        let site = "https://web3.career";
+        let mut orphaned = job("Rust Engineer", "Parity", site, &[]);
+        orphaned.source_url = "https://web3.career/?page=2".to_string();
+        let mut linked = job("Backend Engineer", "Kraken", site, &[]);
+        linked.apply = "https://kraken.com/jobs/1".to_string();
+        linked.source_url = "https://web3.career/?page=1".to_string();
+
+        let jobs = SoftwareJobs::new()
+            .import(vec![vec![orphaned, linked]])
+            .with_fallback_apply_links()
+            .0;
+        assert_eq!(jobs[0].apply, "https://web3.career/?page=2");
+        assert!(jobs[0].apply_is_fallback());
+        assert!(format!("{:?}", jobs[0]).contains("(listing page)"));
+        assert_eq!(jobs[1].apply, "https://kraken.com/jobs/1");
+        assert!(!jobs[1].apply_is_fallback());
+    }
+
+    #[test]
+    fn test_companies() {
+        let site = "https://web3.career";
+        let jobs = SoftwareJobs::new().import(vec![vec![
+            job("Rust Engineer", "Coinbase, Inc.", site, &[]),
+            job("Rust Engineer", "Coinbase Wallet", site, &[]),
+            job("Rust Engineer", "Kraken", site, &[]),
+        ]]);
+        let companies = jobs
+            .companies(vec!["coinbase", "Kraken"])
+            .0
+            .into_iter()
+            .map(|j| j.company)
+            .collect::<Vec<_>>();
+        assert_eq!(companies, ["Coinbase, Inc.", "Kraken"]);
+    }
+
+    #[test]
+    fn test_dedup_key() {
+        let site = "https://web3.career";
+        let a = job("Senior Rust Engineer", "Parity", site, &["rust"]);
+        let mut b = job(" senior  rust ENGINEER", "parity ", site, &[]);
+        b.apply = "https://web3.career/senior-rust-engineer-parity/1".to_string();
+        assert_eq!(a.dedup_key(), b.dedup_key());
+
+        assert_ne!(
+            a.dedup_key(),
+            job(
+                "Senior Rust Engineer",
+                "Parity",
+                "https://cryptojobslist.com",
+                &[]
+            )
+            .dedup_key()
+        );
+        assert_ne!(
+            a.dedup_key(),
+            job("Rust Engineer", "Parity", site, &[]).dedup_key()
+        );
+    }
+
+    #[test]
+    fn test_merge_fills_empty_fields() {
+        let mut a = job("Rust Engineer", "Acme", "https://web3.career", &["rust"]);
+        let mut b = job("Rust Engineer", "Acme", "https://cryptojobslist.com", &[]);
+        b.location = "Remote".to_string();
+        b.remuneration = "$100k - $150k".to_string();
+        (b.rem_lower, b.rem_upper) = (Some(100000), Some(150000));
+        b.currency = Some("USD".to_string());
+        b.company_url = Some("https://acme.com".to_string());
+
+        a.merge(&b);
+        assert_eq!(a.location, "Remote");
+        assert_eq!(a.remuneration, "$100k - $150k");
+        assert_eq!((a.rem_lower, a.rem_upper), (Some(100000), Some(150000)));
+        assert_eq!(a.currency.as_deref(), Some("USD"));
+        assert_eq!(a.company_url.as_deref(), Some("https://acme.com"));
+        assert_eq!(a.site, "https://web3.career");
+    }
+
+    #[test]
+    fn test_merge_unions_tags() {
+        let mut a = job("Rust Engineer", "Acme", "", &["rust", "defi"]);
+        a.merge(&job("Rust Engineer", "Acme", "", &["defi", "remote"]));
+        assert_eq!(a.tags, ["rust", "defi", "remote"]);
+    }
+
+    #[test]
+    fn test_merge_keeps_own_values_on_conflict() {
+        let mut a = job("Rust Engineer", "Acme", "https://web3.career", &[]);
+        a.location = "London".to_string();
+        a.apply = "https://web3.career/rust-engineer/1".to_string();
+        let mut b = job(
+            "Rust Engineer (Remote)",
+            "Acme Inc",
+            "https://near.org",
+            &[],
+        );
+        b.location = "Remote".to_string();
+        b.apply = "https://acme.com/jobs/1".to_string();
+
+        a.merge(&b);
+        assert_eq!(a.location, "London");
+        assert_eq!(a.apply, "https://web3.career/rust-engineer/1");
+        assert_eq!(a.title, "Rust Engineer");
+        assert_eq!(a.company, "Acme");
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        SoftwareJobs::reset(&conn).unwrap();
+        SoftwareJobs::write_jobs(
+            &mut conn,
+            &[job("Rust Engineer", "Acme", "", &[])],
+            None,
+            "2024-05-06 12:00:00",
+        )
+        .unwrap();
+
+        SoftwareJobs::append_history(
+            &mut conn,
+            &[job("Rust Engineer", "Acme", "", &[])],
+            "2024-05-06 12:00:00",
+        )
+        .unwrap();
+        SoftwareJobs::save_favorite(&conn, 1).unwrap();
+
+        SoftwareJobs::reset(&conn).unwrap();
+        for table in ["jobs", "job_history", "latest_jobs", "favorites"] {
+            let cnt: i64 = conn
+                .query_row(&format!("select count(*) from {table}"), (), |row| {
+                    row.get(0)
+                })
+                .unwrap();
+            assert_eq!(cnt, 0, "{table}");
+        }
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        SoftwareJobs::reset(&conn).unwrap();
+        SoftwareJobs::write_jobs(
+            &mut conn,
+            &[job("Rust Engineer", "Acme", "", &[])],
+            None,
+            "2024-05-06 12:00:00",
+        )
+        .unwrap();
+
+        let dir = env::temp_dir().join(format!("jobhunt-test-snapshots-{}", process::id()));
+        let date = NaiveDate::from_ymd_opt(2024, 5, 6).unwrap();
+        let path = SoftwareJobs::snapshot(&conn, &dir.join("nested"), date).unwrap();
+        assert!(path.ends_with("nested/jobs-2024-05-06.db"));
+        let snapshot = Connection::open(&path).unwrap();
+        let title: String = snapshot
+            .query_row("select title from jobs", (), |row| row.get(0))
+            .unwrap();
+        assert_eq!(title, "Rust Engineer");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_progress_line() {
+        assert_eq!(
+            progress_line("https://web3.career", 120),
+            "✓ web3.career: 120 jobs"
+        );
+        assert_eq!(
+            progress_line("https://www.cryptojobslist.com", 0),
+            "✓ cryptojobslist.com: 0 jobs"
+        );
+        assert_eq!(progress_line("Rust Jobs", 3), "✓ Rust Jobs: 3 jobs");
+    }
+
+    #[test]
+    fn test_site_jobs() {
+        let jobs = vec![job("Rust Engineer", "Parity", "https://web3.career", &[])];
+        let timeout = || ErrorKind::RequestTimeout("https://cryptojobslist.com".to_string());
+        let job_vecs = site_jobs(vec![
+            ("web3careers".to_string(), Ok(Some(jobs.clone()))),
+            ("cryptojobslist".to_string(), Err(timeout())),
+            ("solana".to_string(), Ok(None)),
+        ])
+        .unwrap();
+        assert_eq!(job_vecs, [jobs]);
+
+        let err = site_jobs(vec![
+            ("web3careers".to_string(), Err(timeout())),
+            ("cryptojobslist".to_string(), Err(timeout())),
+        ])
+        .unwrap_err();
+        assert!(matches!(err, ErrorKind::AllSitesFailed(_)));
+        assert!(err.to_string().contains("cryptojobslist: Request to"));
+    }
+
+    #[tokio::test]
+    async fn test_with_progress_catches_panics() {
+        let jobs = vec![job("Rust Engineer", "Parity", "https://web3.career", &[])];
+        let ok = with_progress("web3careers", false, async { Ok(Some(jobs.clone())) }).await;
+        let panicked = with_progress("cryptojobslist", false, async {
+            panic!("selector not found");
+        })
+        .await;
+        assert!(matches!(&panicked, Err(ErrorKind::Panicked(msg)) if msg == "selector not found"));
+
+        let job_vecs = site_jobs(vec![
+            ("web3careers".to_string(), ok),
+            ("cryptojobslist".to_string(), panicked),
+        ])
+        .unwrap();
+        assert_eq!(job_vecs, [jobs]);
+    }
+
+    #[test]
+    fn test_history() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let mut first = job("Rust Engineer", "Parity", "https://web3.career", &[]);
+        first.remuneration = "$90k - $140k".to_string();
+        let other = job("Go Engineer", "Aave", "https://web3.career", &[]);
+        SoftwareJobs::append_history(&mut conn, &[first.clone(), other], "2024-05-06 09:00:00")
+            .unwrap();
+        let mut second = first.clone();
+        second.remuneration = "$100k - $150k".to_string();
+        SoftwareJobs::append_history(&mut conn, &[second], "2024-05-07 09:00:00").unwrap();
+
+        let history = SoftwareJobs::history(&conn, &first.dedup_key()).unwrap();
+        let timeline = history
+            .iter()
+            .map(|(scraped_at, job)| (scraped_at.as_str(), job.remuneration.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            timeline,
+            [
+                ("2024-05-06 09:00:00", "$90k - $140k"),
+                ("2024-05-07 09:00:00", "$100k - $150k")
+            ]
+        );
+        let latest: Vec<String> = conn
+            .prepare("select remuneration from latest_jobs order by title")
+            .unwrap()
+            .query_map((), |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(latest, ["", "$100k - $150k"]);
+
+        // history is empty, not an error, before it's first written
+        let conn = Connection::open_in_memory().unwrap();
+        assert!(SoftwareJobs::history(&conn, &first.dedup_key())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_related_companies() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let jobs = [
+            job("Rust Engineer", "Parity", "", &["rust", "substrate"]),
+            job("Runtime Engineer", "Parity, Inc.", "", &["rust", "wasm"]),
+            job(
+                "Rust Engineer",
+                "Solana Labs",
+                "",
+                &["rust", "wasm", "solana"],
+            ),
+            job(
+                "Protocol Engineer",
+                "Polkadot",
+                "",
+                &["rust", "substrate", "wasm"],
+            ),
+            job("Solidity Engineer", "Uniswap", "", &["solidity"]),
+        ];
+        SoftwareJobs::write_jobs(&mut conn, &jobs, None, "2024-05-06 12:00:00").unwrap();
+        let profiles = SoftwareJobs::company_profiles(&conn).unwrap();
+        assert_eq!(profiles["parity"].jobs, 2);
+
+        let related = SoftwareJobs::related_companies(&profiles, "PARITY").unwrap();
+        let names: Vec<&str> = related.iter().map(|r| r.company.as_str()).collect();
+        assert_eq!(names, ["Polkadot", "Solana Labs"]);
+        assert_eq!(related[0].shared_tags, ["rust", "substrate", "wasm"]);
+        assert_eq!(related[0].score, 1.0);
+        assert_eq!(related[1].score, 0.5);
+        assert!(SoftwareJobs::related_companies(&profiles, "Aave").is_none());
+    }
+
+    #[test]
+    fn test_company_trends() {
+        let counts = |jobs: &[Job]| {
+            let mut conn = Connection::open_in_memory().unwrap();
+            SoftwareJobs::write_jobs(&mut conn, jobs, None, "2024-05-06 12:00:00").unwrap();
+            SoftwareJobs::company_counts(&conn).unwrap()
+        };
+        let before = counts(&[
+            job("Rust Engineer", "Acme", "", &[]),
+            job("Go Engineer", "Parity", "", &[]),
+            job("Solidity Engineer", "Parity", "", &[]),
+            job("Protocol Engineer", "Aave", "", &[]),
+        ]);
+        let after = counts(&[
+            job("Rust Engineer", "Acme", "", &[]),
+            job("Backend Engineer", "Acme, Inc.", "", &[]),
+            job("Frontend Engineer", "Acme", "", &[]),
+            job("Protocol Engineer", "Aave", "", &[]),
+            job("Solidity Engineer", "Uniswap", "", &[]),
+        ]);
+        let trend = |company: &str, before, after| CompanyTrend {
+            company: company.to_string(),
+            before,
+            after,
+        };
+        assert_eq!(
+            SoftwareJobs::company_trends(&before, &after),
+            [
+                trend("Acme", 1, 3),
+                trend("Parity", 2, 0),
+                trend("Uniswap", 0, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let dir = env::temp_dir().join(format!("jobhunt-test-trends-{}", process::id()));
+        assert!(SoftwareJobs::snapshots(&dir).unwrap().is_empty());
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["jobs-2024-05-06.db", "jobs-2024-04-30.db", "notes.db"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let snapshots = SoftwareJobs::snapshots(&dir).unwrap();
+        assert_eq!(
+            snapshots,
+            [
+                dir.join("jobs-2024-04-30.db"),
+                dir.join("jobs-2024-05-06.db")
+            ]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_corrupt_db() {
+        let dir = env::temp_dir().join(format!("jobhunt-test-corrupt-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("jobs.db");
+        assert!(SoftwareJobs::check_integrity(&path).is_ok());
+
+        fs::write(
+            &path,
+            "not a database, just some garbage bytes ".repeat(200),
+        )
+        .unwrap();
+        let err = SoftwareJobs::check_integrity(&path).unwrap_err();
+        assert!(matches!(err, ErrorKind::CorruptDb(_)), "{err}");
+
+        let backup = SoftwareJobs::set_aside_corrupt_db(&path).unwrap();
+        assert!(!path.exists());
+        assert!(backup
+            .to_string_lossy()
+            .contains(&format!("jobs.db.corrupt-{}", Local::now().format("%Y"))));
+
+        let mut conn = Connection::open(&path).unwrap();
+        SoftwareJobs::write_jobs(
+            &mut conn,
+            &[job("Rust Engineer", "Acme", "", &[])],
+            None,
+            "2024-05-06 12:00:00",
+        )
+        .unwrap();
+        assert!(SoftwareJobs::check_integrity(&path).is_ok());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_add_to_db() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        SoftwareJobs::new()
+            .import(vec![vec![
+                job("Rust Engineer", "Acme", "https://web3.career", &[]),
+                job("Rust Engineer", "Acme", "https://web3.career", &[]),
+            ]])
+            .dedup()
+            .add_to_db(&mut conn)
+            .unwrap();
+        let titles = conn
+            .prepare("select title from jobs")
+            .unwrap()
+            .query_map((), |row| row.get::<_, String>(0))
+            .unwrap()
+            .collect::<Result<Vec<String>, _>>()
+            .unwrap();
+        assert_eq!(titles, ["Rust Engineer"]);
+    }
+
+    #[test]
+    fn test_open_memory_db() {
+        let memory = Path::new(MEMORY_DB);
+        let mut conn = SoftwareJobs::open_db_at(memory).unwrap();
+        let jobs = [job("Rust Engineer", "Acme", "", &[])];
+        SoftwareJobs::write_jobs(&mut conn, &jobs, None, "2024-05-06 12:00:00").unwrap();
+        // another connection sees the same DB while the first is open
+        let other = SoftwareJobs::open_db_at(memory).unwrap();
+        let cnt: i64 = other
+            .query_row("select count(*) from jobs", (), |row| row.get(0))
+            .unwrap();
+        assert_eq!(cnt, 1);
+        assert!(!memory.exists());
+    }
+
+    #[test]
+    fn test_favorites() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let jobs = [
+            job("Rust Engineer", "Acme", "", &[]),
+            job("Go Engineer", "Acme", "", &[]),
+        ];
+        SoftwareJobs::write_jobs(&mut conn, &jobs, None, "2024-05-06 12:00:00").unwrap();
+        assert!(SoftwareJobs::favorite_rows(&conn).unwrap().is_empty());
+
+        let saved = SoftwareJobs::save_favorite(&conn, 2).unwrap();
+        assert_eq!(saved.title, "Go Engineer");
+        // saving it again is fine
+        SoftwareJobs::save_favorite(&conn, 2).unwrap();
+        match SoftwareJobs::save_favorite(&conn, 3) {
+            Err(ErrorKind::SqliteQuery(msg)) => assert_eq!(msg, "No job with id 3."),
+            res => panic!("expected an error, got {:?}", res.map(|job| job.title)),
+        }
+
+        // a favorite is kept across refreshes, matched on its dedup key
+        let refreshed = [
+            job("Protocol Engineer", "Parity", "", &[]),
+            job("go engineer", "ACME", "", &[]),
+        ];
+        SoftwareJobs::write_jobs(&mut conn, &refreshed, None, "2024-05-07 12:00:00").unwrap();
+        let favorites = SoftwareJobs::favorite_rows(&conn).unwrap();
+        assert_eq!(favorites.len(), 1);
+        assert_eq!(favorites[0].0, 2);
+        assert_eq!(favorites[0].1.title, "go engineer");
+    }
+
+    #[test]
+    fn test_migrate_salaries() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        SoftwareJobs::create_table(&conn, "jobs").unwrap();
+        // bounds stored by older versions, in thousands with 0 for none
+        conn.execute(
+            "insert into jobs (title, company, date_posted, apply, site, rem_lower, rem_upper, \
+            dedup_key) values ('Rust Engineer', 'Acme', '', '', '', 90, 140, 'a'), \
+            ('Go Engineer', 'Acme', '', '', '', 0, 0, 'b')",
+            (),
+        )
+        .unwrap();
+        let mut paid = job("Protocol Engineer", "Parity", "", &[]);
+        (paid.rem_lower, paid.rem_upper) = (Some(100000), Some(150000));
+        SoftwareJobs::write_jobs(&mut conn, &[paid], None, "2024-05-06 12:00:00").unwrap();
+        // converting again leaves them as they are
+        SoftwareJobs::migrate_salaries(&conn).unwrap();
+        let bounds = conn
+            .prepare("select rem_lower, rem_upper from jobs order by id")
+            .unwrap()
+            .query_map((), |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<Vec<(Option<i64>, Option<i64>)>, _>>()
+            .unwrap();
+        assert_eq!(
+            bounds,
+            [
+                (Some(90000), Some(140000)),
+                (None, None),
+                (Some(100000), Some(150000))
+            ]
+        );
+        let version: i64 = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, WHOLE_SALARIES_VERSION);
+    }
+
+    #[test]
+    fn test_write_jobs_upserts() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let rows = |conn: &Connection| {
+            let mut stmt = conn
+                .prepare("select id, title, location, stale from jobs order by id")
+                .unwrap();
+            stmt.query_map((), |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .unwrap()
+            .collect::<Result<Vec<(i64, String, String, bool)>, _>>()
+            .unwrap()
+        };
+
+        // a jobs table from before upserts is replaced
+        conn.execute(&format!("create table jobs ({})", Job::column_defs()), ())
+            .unwrap();
+        let old = [
+            job("Rust Engineer", "Acme", "", &[]),
+            job("Go Engineer", "Acme", "", &[]),
+        ];
+        SoftwareJobs::write_jobs(&mut conn, &old, None, "2024-05-06 12:00:00").unwrap();
+        let s = |s: &str| s.to_string();
+        assert_eq!(
+            rows(&conn),
+            [
+                (1, s("Rust Engineer"), s(""), false),
+                (2, s("Go Engineer"), s(""), false),
+            ]
+        );
+
+        let mut rust = job("rust engineer", "Acme", "", &[]);
+        rust.location = "Remote".to_string();
+        let new = [rust, job("Protocol Engineer", "Parity", "", &[])];
+        SoftwareJobs::write_jobs(&mut conn, &new, Some(1), "2024-05-07 12:00:00").unwrap();
+        // the job found again is updated in place, and the one not found is kept but stale
+        assert_eq!(
+            rows(&conn),
+            [
+                (1, s("rust engineer"), s("Remote"), false),
+                (2, s("Go Engineer"), s(""), true),
+                (3, s("Protocol Engineer"), s(""), false),
+            ]
+        );
+
+        SoftwareJobs::write_jobs(&mut conn, &old, None, "2024-05-08 12:00:00").unwrap();
+        assert_eq!(
+            rows(&conn).iter().map(|row| row.3).collect::<Vec<_>>(),
+            [false, false, true]
+        );
+        // jobs keep the time they were first seen
+        let first_seen = conn
+            .prepare("select first_seen from jobs order by id")
+            .unwrap()
+            .query_map((), |row| row.get(0))
+            .unwrap()
+            .collect::<Result<Vec<String>, _>>()
+            .unwrap();
+        assert_eq!(
+            first_seen,
+            [
+                "2024-05-06 12:00:00",
+                "2024-05-06 12:00:00",
+                "2024-05-07 12:00:00"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_row_round_trip() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(&format!("create table jobs ({})", Job::column_defs()), ())
+            .unwrap();
+        let mut original = job(
+            "Senior Rust Engineer",
+            "Acme",
+            "https://web3.career",
+            &["rust"],
+        );
+        original.remuneration = "$100k - $150k".to_string();
+        original.rem_lower = Some(100000);
+        original.rem_upper = Some(150000);
+        original.currency = Some("USD".to_string());
+        original.company_url = Some("https://acme.com".to_string());
+        original.logo_url = Some("https://acme.com/logo.png".to_string());
+        original.deadline = Some("2024-06-30".to_string());
+        original.applicants = Some(57);
+        original.category = Some("engineering".to_string());
+        original.source_url = "https://web3.career/?page=1".to_string();
+        original.classify();
+        conn.execute(
+            &Job::insert_sql("jobs"),
+            params_from_iter(original.to_params().unwrap()),
+        )
+        .unwrap();
+
+        let read = conn
+            .query_row("select * from jobs", (), Job::from_row)
+            .unwrap();
+        assert_eq!(read, original);
+    }
+
+    #[test]
+    fn test_from_row_reads_columns_by_name() {
+        let conn = Connection::open_in_memory().unwrap();
+        // the columns in reverse order, as after a migration that added columns differently
+        let defs = Job::column_defs();
+        let mut defs = defs.split(", ").collect::<Vec<&str>>();
+        defs.reverse();
+        let defs = defs.join(", ");
+        conn.execute(&format!("create table jobs ({defs})"), ())
+            .unwrap();
+        let mut original = job("Rust Engineer", "Acme", "https://web3.career", &["rust"]);
+        original.rem_lower = Some(100000);
+        original.rem_upper = Some(150000);
+        original.applicants = Some(12);
+        conn.execute(
+            &Job::insert_sql("jobs"),
+            params_from_iter(original.to_params().unwrap()),
+        )
+        .unwrap();
+        let read = conn
+            .query_row("select * from jobs", (), Job::from_row)
+            .unwrap();
+        assert_eq!(read, original);
+
+        // a projection without the optional columns reads them as empty
+        let read = conn
+            .query_row(
+                "select site, apply, date_posted, company, title from jobs",
+                (),
+                Job::from_row,
+            )
+            .unwrap();
+        assert_eq!(read.title, "Rust Engineer");
+        assert_eq!(read.site, "https://web3.career");
+        assert_eq!((read.rem_lower, read.applicants), (None, None));
+        assert!(read.tags.is_empty());
+        assert!(conn
+            .query_row("select title from jobs", (), Job::from_row)
+            .is_err());
+    }
+
+    #[test]
+    fn test_salary_stats() {
+        let paid = |lower, upper| Job {
+            rem_lower: lower,
+            rem_upper: upper,
+            ..Default::default()
+        };
+        let jobs = [
+            paid(Some(100000), Some(150000)),
+            paid(None, None),
+            paid(Some(80000), Some(100000)),
+            paid(Some(150000), Some(250000)),
+        ];
+        let stats = SalaryStats::from_jobs(&jobs).unwrap();
+        assert_eq!(
+            stats,
+            SalaryStats {
+                min: 90.0,
+                median: 125.0,
+                max: 200.0,
+                with_salary: 3,
+                total: 4,
+            }
+        );
+        assert_eq!(
+            stats.to_string(),
+            "Salary: min 90k, median 125k, max 200k. 3 of 4 jobs (75%) list a salary."
+        );
+        assert_eq!(SalaryStats::from_jobs(&[paid(None, None)]), None);
+    }
+
+    #[test]
+    fn test_parse_salary_range() {
+        let range = |lower, upper| SalaryRange { lower, upper };
+        assert_eq!(
+            "100k-200k".parse::<SalaryRange>().unwrap(),
+            range(Some(100000), Some(200000))
+        );
+        assert_eq!(
+            "$100K - $200K".parse::<SalaryRange>().unwrap(),
+            range(Some(100000), Some(200000))
+        );
+        assert_eq!(
+            ">150".parse::<SalaryRange>().unwrap(),
+            range(Some(150000), None)
+        );
+        assert_eq!(
+            "<80000".parse::<SalaryRange>().unwrap(),
+            range(None, Some(80000))
+        );
+        assert!("200k-100k".parse::<SalaryRange>().is_err());
+        assert!("lots".parse::<SalaryRange>().is_err());
+        assert!(">".parse::<SalaryRange>().is_err());
+        assert_eq!(
+            range(Some(100000), Some(200000)).condition(),
+            "rem_upper is not null and rem_upper >= 100000 and rem_lower <= 200000"
+        );
+    }
+
+    #[test]
+    fn test_search_terms() {
+        assert_eq!(
+            search_terms(r#"select jobs where title like "%Rust%" and company like '%par_ity%'"#),
+            ["rust", "par", "ity"]
+        );
+        assert!(search_terms("select jobs where rem_upper > 100").is_empty());
+    }
+
+    #[test]
+    fn test_highlight() {
+        let terms = ["rust".to_string()];
+        assert_eq!(
+            highlight_spans("Senior Rust Engineer", &terms),
+            [("Senior ", false), ("Rust", true), (" Engineer", false)]
+        );
+        assert_eq!(
+            highlight_spans("rust", &["RUST".to_string(), String::new()]),
+            [("", false), ("rust", true), ("", false)]
+        );
+        assert_eq!(
+            highlight_spans("Senior Rust Engineer", &[]),
+            [("Senior Rust Engineer", false)]
+        );
+    }
+
+    #[test]
+    fn test_relevance() {
+        let weights = Relevance::default();
+        let days_ago = |d: i64| {
+            (Local::now() - Duration::days(d))
+                .format("%Y-%m-%d")
+                .to_string()
+        };
+        let mut fresh = job("Rust Engineer", "Acme", "", &[]);
+        fresh.date_posted = days_ago(0);
+        let mut week_old = fresh.clone();
+        week_old.date_posted = days_ago(7);
+
+        assert_eq!(fresh.relevance(&weights), 1.0);
+        assert_eq!(week_old.relevance(&weights), 0.5);
+
+        week_old.remuneration = "$100k - $150k".to_string();
+        week_old.work_mode = "remote".to_string();
+        assert_eq!(week_old.relevance(&weights), 1.5);
+        assert!(week_old.relevance(&weights) > fresh.relevance(&weights));
+
+        let undated = job("Rust Engineer", "Acme", "", &[]);
+        assert_eq!(undated.relevance(&weights), 0.0);
+    }
+
+    #[test]
+    fn test_explain_title_filter() {
+        let title_filter = TitleFilter::default();
+        let explain = |title: &str| job(title, "", "", &[]).explain_title_filter(&title_filter);
+
+        let included = explain("Senior Rust Engineer");
+        assert!(included.is_included());
+        assert_eq!(included, FilterExplanation::Matched("engineer".to_string()));
+
+        let excluded = explain("Product Designer");
+        assert!(!excluded.is_included());
+        assert_eq!(
+            excluded,
+            FilterExplanation::NoMatch(title_filter.keywords.clone())
+        );
+
+        let title_filter = TitleFilter {
+            enabled: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            job("Product Designer", "", "", &[]).explain_title_filter(&title_filter),
+            FilterExplanation::FilterOff
+        );
+
+        // an empty keyword list means no filter
+        let title_filter = TitleFilter {
+            keywords: Vec::new(),
+            ..Default::default()
+        };
+        assert_eq!(
+            job("Product Designer", "", "", &[]).explain_title_filter(&title_filter),
+            FilterExplanation::FilterOff
+        );
+    }
+
+    #[test]
+    fn test_strip_tracking_params() {
+        let strip = |apply: &str| {
+            let mut job = Job {
+                apply: apply.to_string(),
+                ..Default::default()
+            };
+            job.strip_tracking_params();
+            job.apply
+        };
+        assert_eq!(
+            strip("https://boards.greenhouse.io/acme/jobs/1?gh_jid=123&utm_source=web3.career&ref=web3career"),
+            "https://boards.greenhouse.io/acme/jobs/1?gh_jid=123"
+        );
+        assert_eq!(
+            strip("https://acme.com/careers/rust?UTM_Medium=email&utm_campaign=jobs"),
+            "https://acme.com/careers/rust"
+        );
+        assert_eq!(
+            strip("https://jobs.lever.co/acme/1?lever-source=web3&team=Eng"),
+            "https://jobs.lever.co/acme/1?lever-source=web3&team=Eng"
+        );
+        assert_eq!(strip("mailto:jobs@acme.com"), "mailto:jobs@acme.com");
+        assert_eq!(strip("/jobs/1?ref=x"), "/jobs/1?ref=x");
+    }
+
+    #[test]
+    fn test_infer_company_url() {
+        let company_url = |apply: &str, site: &str| {
+            Job {
+                apply: apply.to_string(),
+                site: site.to_string(),
+                ..Default::default()
+            }
+            .infer_company_url()
+        };
+        let site = "https://jobs.solana.com/jobs";
+
+        assert_eq!(
+            company_url("https://www.parity.io/careers/123", site),
+            Some("https://parity.io".to_string())
+        );
+        assert_eq!(
+            company_url("https://careers.chainlink.com/jobs/1?ref=web3", site),
+            Some("https://chainlink.com".to_string())
+        );
+        assert_eq!(
+            company_url("mailto:jobs@aave.com", site),
+            Some("https://aave.com".to_string())
+        );
+        assert_eq!(
+            company_url("https://boards.greenhouse.io/kraken/jobs/123", site),
+            None
+        );
+        assert_eq!(company_url("https://jobs.lever.co/ledger/abc", site), None);
+        assert_eq!(
+            company_url("https://jobs.solana.com/companies/solana/jobs/1", site),
+            None
+        );
+        assert_eq!(company_url("", site), None);
+    }
+
+    #[test]
+    fn test_dedup_merges_tags() {
+        let jobs = SoftwareJobs::new()
+            .import(vec![
+                vec![job(
+                    "Rust Engineer",
+                    "Parity",
+                    "https://web3.career",
+                    &["Rust"],
+                )],
+                vec![
+                    job(
+                        "Rust  Engineer",
+                        "parity",
+                        "https://cryptojobslist.com",
+                        &["rust", " Remote", "DeFi"],
+                    ),
+                    job(
+                        "Go Engineer",
+                        "Parity",
+                        "https://cryptojobslist.com",
+                        &["Go"],
+                    ),
+                ],
+            ])
+            .dedup()
+            .0;
+
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].site, "https://web3.career");
+        assert_eq!(jobs[0].tags, vec!["rust", "remote", "defi"]);
+        assert_eq!(jobs[1].tags, vec!["go"]);
     }
 }
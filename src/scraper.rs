@@ -1,62 +1,59 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::DateTime;
+use colored::Colorize;
 use itertools::Itertools;
 use regex::Regex;
-use reqwest::header::USER_AGENT;
-use reqwest::Client;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
+use reqwest::{Client, Url};
+use scraper::selector::ToCss;
 use scraper::{ElementRef, Html, Selector};
+use serde::Deserialize;
+use tokio::sync::{Semaphore, SemaphorePermit};
 
+use crate::config::{self, Config, Retry, DEFAULT_USER_AGENT};
 use crate::repository::Job;
 use crate::site::{
-    Common, CryptoJobsList, DateFormatter, NearJobs, Site, SolanaJobs, SubstrateJobs, Web3Careers,
+    applicants_from, currency_from, site_host, Common, CommonBoard, CommonSite, CryptoJobsList,
+    CustomSite, DateFormatter, GreenhouseJobs, NearJobs, Site, SiteGroup, SolanaJobs,
+    SubstrateJobs, Web3Careers, COMMON_FILTER, DEFAULT_GROUP, WEB3_CAREERS_REQUEST_DELAY,
 };
-use crate::ErrorKind;
+use crate::{classify, red_println, selectors, ErrorKind};
+
+/// Matches a remuneration range (e.g. "$90k - $140k", "£90k - £140k" or "CHF 120k - CHF 150k")
+/// or a single value (e.g. "USDC 120k" or "Up to €150k"), in a currency shown by its symbol or code.
+pub const REM_REGEX: &str = r"(\$|€|£|\b[A-Z]{3,4} )(\d)+k( - (\$|€|£|\b[A-Z]{3,4} )(\d)+k)?";
 
-const REM_REGEX: &str = r"(\$|€)(\d)+k - (\$|€)(\d)+k";
+/// Matches a posted date as stored, e.g. "2024-05-06" or "2024-05-06 12:05:50".
+pub const DATE_REGEX: &str = r"(\d{4})-(\d{2})-(\d{2})( (\d{2}):(\d{2}):(\d{2}))?";
 
 /// All jobsite structs must implement the Scraper trait.
 #[allow(async_fn_in_trait)]
 pub trait Scraper {
-    /// Scrapes the job website and adds Job instances to the site's jobs array - Job instances have
-    /// the structure:
-    /// ```
-    /// struct Job {
-    ///     pub title: String,
-    ///     pub company: String,
-    ///     pub date_posted: String,
-    ///     pub location: String,
-    ///     pub remuneration: String,
-    ///     pub tags: Vec<String>,
-    ///     pub apply: String,
-    ///     pub site: &'static str,
-    ///     pub rem_lower: u16,
-    ///     pub rem_upper: u16,
-    /// }
-    /// ```
-    /// as defined in repository module.
-    async fn scrape(self) -> Result<Self, ErrorKind>
+    /// Scrapes the job website and adds [`Job`] instances (as defined in the repository module) to
+    /// the site's jobs array. The client is shared by every site, so its connection pool is reused
+    /// across them.
+    async fn scrape(self, client: &Client) -> Result<Self, ErrorKind>
     where
         Self: Sized;
 
+    /// How long to wait between the requests for consecutive pages of the site, to avoid being
+    /// throttled. No delay by default.
+    fn request_delay(&self) -> Duration {
+        Duration::ZERO
+    }
+
     /// Gets an HTML doc for a jobsite.
+    #[tracing::instrument(level = "debug", skip(client))]
     async fn get_html_doc(client: &Client, url_full: &str) -> Result<Html, ErrorKind> {
-        let res = client
-            .get(url_full)
-            .header(
-                USER_AGENT,
-                "Mozilla/5.0 (iPad; CPU OS 12_2 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Mobile/15E148",
-            )
-            .send()
-            .await
-            .map_err(|e| ErrorKind::Request(url_full.to_string(), e.to_string()))?;
-        if !res.status().is_success() {
-            Err(ErrorKind::Request(
-                url_full.to_string(),
-                format!("Request failed with code {}", res.status().as_u16()),
-            ))?;
-        }
-        let body = res
-            .text()
-            .await
-            .map_err(|e| ErrorKind::Decode(e.to_string()))?;
+        let config = config::get();
+        let body = get_body(client, url_full, &config).await?;
+        check_document_size(url_full, &body, config.min_document_bytes)?;
         let doc = Html::parse_document(&body);
         Ok(doc)
     }
@@ -65,6 +62,447 @@ pub trait Scraper {
     fn get_selector(selectors: &str) -> Result<Selector, ErrorKind> {
         Selector::parse(selectors).map_err(|e| ErrorKind::Selector(e.to_string()))
     }
+
+    /// Gets an ordered list of fallback selectors for a specific HTML element. Sites sometimes serve
+    /// more than one layout, so the selectors are tried in order and the first to match is used.
+    fn get_selectors<S>(selectors: &[S]) -> Result<Vec<Selector>, ErrorKind>
+    where
+        S: AsRef<str>,
+    {
+        selectors
+            .iter()
+            .map(|s| Self::get_selector(s.as_ref()))
+            .collect()
+    }
+}
+
+/// A wall-clock budget shared by a set of scrapes, e.g. all the sites scraped on a refresh. The
+/// scrapes can run concurrently. Scrapes still running when the budget runs out are cancelled, and
+/// their sites recorded as cut off.
+pub struct ScrapeBudget {
+    deadline: Option<tokio::time::Instant>,
+    cut_off: Mutex<Vec<String>>,
+}
+
+impl ScrapeBudget {
+    /// Starts a budget of the given duration, or an unlimited budget for None.
+    pub fn start(budget: Option<Duration>) -> Self {
+        Self {
+            deadline: budget.map(|b| tokio::time::Instant::now() + b),
+            cut_off: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Runs a scrape within the remaining budget. Returns None, recording the site, if the budget
+    /// ran out first.
+    pub async fn run<T, F>(&self, site: &str, scrape: F) -> Result<Option<T>, ErrorKind>
+    where
+        F: Future<Output = Result<T, ErrorKind>>,
+    {
+        let Some(deadline) = self.deadline else {
+            return scrape.await.map(Some);
+        };
+        match tokio::time::timeout_at(deadline, scrape).await {
+            Ok(res) => res.map(Some),
+            Err(_) => {
+                self.cut_off.lock().unwrap().push(site.to_string());
+                Ok(None)
+            }
+        }
+    }
+
+    /// Returns the sites cut off by the budget running out.
+    pub fn cut_off(&self) -> Vec<String> {
+        self.cut_off.lock().unwrap().clone()
+    }
+}
+
+/// Scrapes a site, scraping it again after a short delay (the retry backoff) up to `retries` times
+/// while it returns no jobs. Some boards intermittently serve a valid page without any jobs (e.g. a
+/// hydration race), while a broken selector finds no jobs every time - so a site still without
+/// jobs after its retries is warned about. Cached pages of the site are dropped before each retry,
+/// so they're fetched again.
+pub async fn scrape_retrying_empty<F, Fut>(
+    url: &str,
+    retries: u32,
+    scrape: F,
+) -> Result<Vec<Job>, ErrorKind>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<Vec<Job>, ErrorKind>>,
+{
+    let mut jobs = scrape().await?;
+    for _ in 0..retries {
+        if !jobs.is_empty() {
+            break;
+        }
+        tokio::time::sleep(config::get().retry.backoff).await;
+        let host = site_host(url);
+        HTML_CACHE
+            .lock()
+            .unwrap()
+            .retain(|cached_url, _| site_host(cached_url) != host);
+        jobs = scrape().await?;
+    }
+    if jobs.is_empty() && retries > 0 {
+        red_println!(format!(
+            "Warning: {url} returned no jobs after {retries} retries, so its selectors may be broken."
+        ));
+    }
+    Ok(jobs)
+}
+
+/// The request limits of a site group.
+struct GroupLimit {
+    slots: Semaphore,
+    delay: Duration,
+    /// The earliest time the next request to the group may start.
+    next_start: tokio::sync::Mutex<tokio::time::Instant>,
+}
+
+impl GroupLimit {
+    fn new(group: &SiteGroup) -> Self {
+        Self {
+            slots: Semaphore::new(group.concurrency.max(1)),
+            delay: Duration::from_millis(group.delay_ms),
+            next_start: tokio::sync::Mutex::new(tokio::time::Instant::now()),
+        }
+    }
+}
+
+/// Limits the requests made to each site group. A request waits for a free slot in its group,
+/// then for the group's delay since the previous request to the group started, then for a free
+/// slot in the global limit. So a group never has more than its own concurrency in flight, and all
+/// the groups together never have more than the global limit.
+pub struct RateLimiter {
+    global: Semaphore,
+    groups: HashMap<String, GroupLimit>,
+    /// The group of each host listed in a group.
+    hosts: HashMap<String, String>,
+}
+
+/// Held while a request is in flight, freeing its slots when dropped.
+pub struct RatePermit<'a> {
+    _group: SemaphorePermit<'a>,
+    _global: SemaphorePermit<'a>,
+}
+
+impl RateLimiter {
+    pub fn new(groups: &BTreeMap<String, SiteGroup>, max_concurrent_requests: usize) -> Self {
+        let mut limits = HashMap::new();
+        let mut hosts = HashMap::new();
+        for (name, group) in groups {
+            limits.insert(name.clone(), GroupLimit::new(group));
+            for host in &group.hosts {
+                let host = host.to_lowercase();
+                hosts.insert(
+                    host.strip_prefix("www.").unwrap_or(&host).to_string(),
+                    name.clone(),
+                );
+            }
+        }
+        limits
+            .entry(DEFAULT_GROUP.to_string())
+            .or_insert_with(|| GroupLimit::new(&SiteGroup::default()));
+        Self {
+            global: Semaphore::new(max_concurrent_requests.max(1)),
+            groups: limits,
+            hosts,
+        }
+    }
+
+    /// Returns the name of the group a URL's host is in.
+    pub fn group_of(&self, url: &str) -> &str {
+        site_host(url)
+            .and_then(|host| self.hosts.get(&host))
+            .map(String::as_str)
+            .unwrap_or(DEFAULT_GROUP)
+    }
+
+    /// Waits until a request to the URL may start within the limits.
+    pub async fn acquire(&self, url: &str) -> RatePermit<'_> {
+        let group = &self.groups[self.group_of(url)];
+        let group_permit = group
+            .slots
+            .acquire()
+            .await
+            .expect("rate limiter semaphores are never closed");
+        if !group.delay.is_zero() {
+            let start = {
+                let mut next_start = group.next_start.lock().await;
+                let start = (*next_start).max(tokio::time::Instant::now());
+                *next_start = start + group.delay;
+                start
+            };
+            tokio::time::sleep_until(start).await;
+        }
+        let global_permit = self
+            .global
+            .acquire()
+            .await
+            .expect("rate limiter semaphores are never closed");
+        RatePermit {
+            _group: group_permit,
+            _global: global_permit,
+        }
+    }
+}
+
+/// The rate limiter for all requests, built from the session settings when first used.
+static RATE_LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| {
+    let config = config::get();
+    RateLimiter::new(&config.site_groups, config.max_concurrent_requests)
+});
+
+/// The maximum number of pages held in the HTML cache.
+const MAX_CACHED_PAGES: usize = 64;
+
+/// A bounded in-memory cache of page bodies for the session, keyed by URL.
+static HTML_CACHE: LazyLock<Mutex<HashMap<String, (Instant, String)>>> =
+    LazyLock::new(Default::default);
+
+/// Gets the body of a page, from the HTML cache if it's enabled and holds a fresh copy.
+async fn get_body(client: &Client, url_full: &str, config: &Config) -> Result<String, ErrorKind> {
+    let cache = &config.cache;
+    let mut headers = config.headers_for(url_full);
+    if !headers.keys().any(|k| k.eq_ignore_ascii_case("user-agent")) {
+        let user_agent = next_user_agent(&config.user_agents).to_string();
+        headers.insert("User-Agent".to_string(), user_agent);
+    }
+    if !cache.enabled {
+        return fetch_body_with_retry(client, url_full, &headers, &config.retry).await;
+    }
+    if let Some((fetched_at, body)) = HTML_CACHE.lock().unwrap().get(url_full) {
+        if fetched_at.elapsed() < cache.ttl {
+            return Ok(body.clone());
+        }
+    }
+
+    let body = fetch_body_with_retry(client, url_full, &headers, &config.retry).await?;
+    let mut html_cache = HTML_CACHE.lock().unwrap();
+    html_cache.retain(|_, (fetched_at, _)| fetched_at.elapsed() < cache.ttl);
+    if html_cache.len() >= MAX_CACHED_PAGES {
+        if let Some(oldest) = html_cache
+            .iter()
+            .min_by_key(|(_, (fetched_at, _))| *fetched_at)
+            .map(|(url, _)| url.clone())
+        {
+            html_cache.remove(&oldest);
+        }
+    }
+    html_cache.insert(url_full.to_string(), (Instant::now(), body.clone()));
+    Ok(body)
+}
+
+/// Requests a page and returns its body, retrying with exponential backoff on retryable errors.
+async fn fetch_body_with_retry(
+    client: &Client,
+    url_full: &str,
+    headers: &BTreeMap<String, String>,
+    retry: &Retry,
+) -> Result<String, ErrorKind> {
+    let mut attempt = 0;
+    loop {
+        let res = {
+            let _permit = RATE_LIMITER.acquire(url_full).await;
+            fetch_body(client, url_full, headers).await
+        };
+        match res {
+            Err(err) if err.is_retryable() && attempt < retry.retries => {
+                tokio::time::sleep(retry.backoff * 2u32.saturating_pow(attempt)).await;
+                attempt += 1;
+            }
+            res => return res,
+        }
+    }
+}
+
+/// Markers found in the body of bot challenge pages (e.g. Cloudflare's "Just a moment...").
+const CHALLENGE_MARKERS: &[&str] = &[
+    "cf-chl-",
+    "cf_chl_opt",
+    "challenge-platform",
+    "<title>Just a moment...</title>",
+    "Attention Required! | Cloudflare",
+];
+
+/// Returns true if a response body is a bot challenge page rather than the requested page.
+fn is_challenge(body: &str) -> bool {
+    CHALLENGE_MARKERS.iter().any(|m| body.contains(m))
+}
+
+/// Counts the requests made, to take turns between the configured User-Agents.
+static USER_AGENT_TURN: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the User-Agent to send with the next request, taking turns between those given.
+fn next_user_agent(user_agents: &[String]) -> &str {
+    match user_agents.len() {
+        0 => DEFAULT_USER_AGENT,
+        len => &user_agents[USER_AGENT_TURN.fetch_add(1, AtomicOrdering::Relaxed) % len],
+    }
+}
+
+/// Builds the client used to request every site, with the configured request timeout.
+pub fn client() -> Client {
+    client_with_timeout(config::get().request_timeout)
+}
+
+/// Builds a client whose requests time out after the given duration.
+fn client_with_timeout(timeout: Duration) -> Client {
+    Client::builder()
+        .timeout(timeout)
+        .build()
+        .expect("the client's TLS backend should initialise")
+}
+
+/// Requests a page and returns its body. The given headers are sent in addition to, or in place
+/// of, the default headers.
+async fn fetch_body(
+    client: &Client,
+    url_full: &str,
+    headers: &BTreeMap<String, String>,
+) -> Result<String, ErrorKind> {
+    let mut header_map = HeaderMap::new();
+    header_map.insert(USER_AGENT, HeaderValue::from_static(DEFAULT_USER_AGENT));
+    // headers are validated when they're loaded
+    for (name, value) in headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            header_map.insert(name, value);
+        }
+    }
+    let res = client
+        .get(url_full)
+        .headers(header_map)
+        .send()
+        .await
+        .map_err(|e| match e.is_timeout() {
+            true => ErrorKind::RequestTimeout(url_full.to_string()),
+            false => ErrorKind::RequestTransport(url_full.to_string(), e.to_string()),
+        })?;
+    let status = res.status().as_u16();
+    tracing::debug!(url = url_full, status, "fetched page");
+    if !res.status().is_success() {
+        if status == 403 || status == 503 {
+            let body = res.text().await.unwrap_or_default();
+            if is_challenge(&body) {
+                return Err(ErrorKind::Challenged(url_full.to_string()));
+            }
+        }
+        return Err(ErrorKind::RequestStatus(url_full.to_string(), status));
+    }
+    res.text()
+        .await
+        .map_err(|e| ErrorKind::Decode(e.to_string()))
+}
+
+/// Returns an error if a body is too small to be a real page - e.g. a server returning a 200 with
+/// an empty shell - rather than letting every selector silently match nothing.
+fn check_document_size(url_full: &str, body: &str, min_bytes: usize) -> Result<(), ErrorKind> {
+    let bytes = body.trim().len();
+    if bytes < min_bytes {
+        return Err(ErrorKind::EmptyDocument(url_full.to_string(), bytes));
+    }
+    Ok(())
+}
+
+/// The title given to a job whose title couldn't be scraped.
+pub const UNTITLED: &str = "(untitled)";
+
+/// Decides whether to keep a scraped job whose title selector didn't match. Unless strict, the job
+/// is kept with a placeholder title as long as it has a company or apply link.
+fn keep_untitled(job: &mut Job, strict: bool) -> bool {
+    if strict || (job.company.is_empty() && job.apply.is_empty()) {
+        return false;
+    }
+    job.title = UNTITLED.to_string();
+    true
+}
+
+/// Warns if any jobs from a site were kept without a title - a sign the title selector is broken.
+fn warn_untitled(site: &str, jobs: &[Job]) {
+    let cnt = jobs.iter().filter(|job| job.title == UNTITLED).count();
+    if cnt > 0 {
+        red_println!(format!(
+            "Warning: {cnt} jobs from {site} had no title and were kept as \"{UNTITLED}\"."
+        ));
+    }
+}
+
+/// Warns that no jobs were found on the first page of a site with its job-list selectors. That's
+/// almost always the site's markup having changed, which would otherwise look like it had no jobs.
+fn warn_no_jobs_matched<S: AsRef<str>>(site: &str, selectors: &[S]) {
+    let selectors = selectors.iter().map(AsRef::as_ref).join(" | ");
+    red_println!(format!(
+        "Warning: the job-list selector \"{selectors}\" found no jobs on {site} - its markup may have changed."
+    ));
+}
+
+/// Sets the listing page that each of the jobs was scraped from.
+fn from_page(mut jobs: Vec<Job>, page_url: &str) -> Vec<Job> {
+    for job in &mut jobs {
+        job.source_url = page_url.to_string();
+    }
+    jobs
+}
+
+/// The order scraped jobs are returned in - by title, then company, then apply link - so a scrape
+/// gives the same order on every run whatever order the pages or requests complete in.
+fn job_order(a: &Job, b: &Job) -> Ordering {
+    (&a.title, &a.company, &a.apply).cmp(&(&b.title, &b.company, &b.apply))
+}
+
+/// Removes duplicate jobs and sorts the rest into a deterministic order.
+fn unique_sorted(jobs: Vec<Job>) -> Vec<Job> {
+    let mut jobs = jobs.into_iter().unique().collect::<Vec<Job>>();
+    jobs.sort_by(job_order);
+    jobs
+}
+
+/// Returns the absolute URL of the first image matched within a job element, preferring the
+/// lazy-loaded `data-src` over `src` (which is often a placeholder until the image is loaded).
+/// Inline `data:` images and empty attributes are ignored.
+fn logo_url(el: &ElementRef, selectors: &[Selector], base: &str) -> Option<String> {
+    let img = el.select_first(selectors)?;
+    let src = ["data-src", "src"]
+        .into_iter()
+        .filter_map(|attr| img.value().attr(attr).map(str::trim))
+        .find(|src| !src.is_empty() && !src.starts_with("data:"))?;
+    Url::parse(base)
+        .and_then(|base| base.join(src))
+        .map(|url| url.to_string())
+        .ok()
+}
+
+/// The elements a CSS selector matches on a page, as reported by the "test-selectors" command.
+pub struct SelectorMatches {
+    pub count: usize,
+    /// The text of the first few matches.
+    pub samples: Vec<String>,
+}
+
+/// Fetches a page and matches a CSS selector against it, keeping the text of the first `samples`
+/// matches. The selector is checked before the page is fetched, so a selector that doesn't parse
+/// fails straight away.
+pub async fn test_selector(
+    url: &str,
+    selector: &str,
+    samples: usize,
+) -> Result<SelectorMatches, ErrorKind> {
+    // the page is fetched as it would be for a custom site
+    let selector = CustomSite::get_selector(selector)?;
+    let doc = CustomSite::get_html_doc(&client(), url).await?;
+    let matches: Vec<ElementRef> = doc.select(&selector).collect();
+    Ok(SelectorMatches {
+        count: matches.len(),
+        samples: matches
+            .iter()
+            .take(samples)
+            .map(|el| el.get_text())
+            .collect(),
+    })
 }
 
 trait GetText {
@@ -77,296 +515,1645 @@ impl GetText for ElementRef<'_> {
     }
 }
 
+/// Selects elements using an ordered list of fallback selectors.
+trait SelectFallback {
+    /// Returns the elements matched by a single selector.
+    fn matching<'a>(&'a self, selector: &Selector) -> impl Iterator<Item = ElementRef<'a>>;
+
+    /// Called when none of the selectors passed to `select_all` match anything. Does nothing by
+    /// default.
+    fn on_no_match(&self, _selectors: &[Selector]) {}
+
+    /// Returns the first element matched by the first selector that matches anything.
+    fn select_first(&self, selectors: &[Selector]) -> Option<ElementRef<'_>> {
+        selectors.iter().find_map(|s| self.matching(s).next())
+    }
+
+    /// Returns all elements matched by the first selector that matches anything.
+    fn select_all(&self, selectors: &[Selector]) -> Vec<ElementRef<'_>> {
+        let elements = selectors
+            .iter()
+            .map(|s| self.matching(s).collect::<Vec<_>>())
+            .find(|v| !v.is_empty())
+            .unwrap_or_default();
+        if elements.is_empty() {
+            self.on_no_match(selectors);
+        }
+        elements
+    }
+}
+
+impl SelectFallback for ElementRef<'_> {
+    fn matching<'a>(&'a self, selector: &Selector) -> impl Iterator<Item = ElementRef<'a>> {
+        self.select(selector)
+    }
+}
+
+impl SelectFallback for Html {
+    fn matching<'a>(&'a self, selector: &Selector) -> impl Iterator<Item = ElementRef<'a>> {
+        self.select(selector)
+    }
+
+    // on a whole page, nothing matching usually means the site's markup changed
+    fn on_no_match(&self, selectors: &[Selector]) {
+        let selectors = selectors.iter().map(Selector::to_css_string).join(" | ");
+        tracing::warn!(selectors, "selectors matched zero elements");
+    }
+}
+
 impl Scraper for Web3Careers {
-    async fn scrape(mut self) -> Result<Self, ErrorKind>
+    async fn scrape(self, client: &Client) -> Result<Self, ErrorKind>
     where
         Self: Sized,
     {
-        let client = Client::new();
+        let config = config::get();
+        let mut site = self
+            .scrape_filters(client, &config.web3_careers_filters)
+            .await?;
+        site.fill_salaries(client, config.web3_careers_salary_pages)
+            .await?;
+        Ok(site)
+    }
+
+    fn request_delay(&self) -> Duration {
+        WEB3_CAREERS_REQUEST_DELAY
+    }
+}
+
+impl Web3Careers {
+    /// Scrapes up to `max_pages` pages of the listing for each job-function filter (e.g.
+    /// "backend-jobs"), or of the unfiltered listing if there are none. A job listed under more
+    /// than one filter (or on more than one page) is only kept once. A listing stops at its first
+    /// page without jobs, and if a page lists exactly the same jobs as the page before it, the site
+    /// is taken to be ignoring the page number and the rest of that listing is skipped.
+    async fn scrape_filters(
+        mut self,
+        client: &Client,
+        filters: &[String],
+    ) -> Result<Self, ErrorKind> {
         let url = self.get_url();
-        for i in 1..6 {
-            let mut jobs = Self::_scrape(url, &client, i).await?;
-            self.jobs.append(&mut jobs);
+        let filters = match filters.is_empty() {
+            true => vec![None],
+            false => filters.iter().map(|f| Some(f.as_str())).collect(),
+        };
+        let mut seen = HashSet::new();
+        for filter in filters {
+            let mut prev_keys = HashSet::new();
+            for i in 1..=self.max_pages() {
+                if i > 1 {
+                    tokio::time::sleep(self.request_delay()).await;
+                }
+                let jobs = Self::_scrape(url, client, filter, i).await?;
+                // past the last page of the listing
+                if jobs.is_empty() {
+                    if i == 1 {
+                        let s = selectors::for_site(selectors::WEB3_CAREERS);
+                        warn_no_jobs_matched(url, selectors::field(&s, "jobs"));
+                    }
+                    break;
+                }
+                let keys = jobs.iter().map(Job::dedup_key).collect::<HashSet<String>>();
+                if keys == prev_keys {
+                    red_println!(format!(
+                        "Warning: page {i} of {url} repeated page {}, so its later pages were skipped.",
+                        i - 1
+                    ));
+                    break;
+                }
+                self.jobs
+                    .extend(jobs.into_iter().filter(|job| seen.insert(job.dedup_key())));
+                prev_keys = keys;
+            }
         }
-        self.jobs = self.jobs.into_iter().unique().collect();
+        self.jobs = unique_sorted(self.jobs);
         Ok(self)
     }
-}
 
-impl Web3Careers {
-    /// Used to scrape web3careers jobsite for a specific page number.
+    /// Fills in the remuneration of jobs whose listing row has none from their detail pages, which
+    /// often show a salary the listing doesn't. At most `limit` detail pages are fetched. A detail
+    /// page that can't be fetched just leaves its job without a remuneration.
+    async fn fill_salaries(&mut self, client: &Client, limit: usize) -> Result<(), ErrorKind> {
+        if limit == 0 {
+            return Ok(());
+        }
+        let rem_regex = Regex::new(REM_REGEX).unwrap();
+        let s = selectors::for_site(selectors::WEB3_CAREERS);
+        let remuneration_selector =
+            Self::get_selectors(selectors::field(&s, "detail_remuneration"))?;
+        let mut failed = 0;
+        let delay = self.request_delay();
+        let jobs = self
+            .jobs
+            .iter_mut()
+            .filter(|job| job.remuneration.is_empty() && !job.apply.is_empty())
+            .take(limit);
+        for (i, job) in jobs.enumerate() {
+            if i > 0 {
+                tokio::time::sleep(delay).await;
+            }
+            let Ok(doc) = Self::get_html_doc(client, &job.apply).await else {
+                failed += 1;
+                continue;
+            };
+            // detail pages write salaries the way cryptojobslist does, e.g. "$ 90k-140k"
+            let remuneration = doc
+                .select_first(&remuneration_selector)
+                .map(|el| CryptoJobsList::format_remuneration_from(&el.get_text()))
+                .filter(|remuneration| rem_regex.is_match(remuneration));
+            if let Some(remuneration) = remuneration {
+                (job.rem_lower, job.rem_upper) = Self::get_upper_lower(&remuneration);
+                job.currency = currency_from(&remuneration);
+                job.remuneration = remuneration;
+            }
+        }
+        if failed > 0 {
+            red_println!(format!(
+                "Warning: {failed} web3careers detail page{} couldn't be fetched for salaries.",
+                if failed == 1 { "" } else { "s" }
+            ));
+        }
+        Ok(())
+    }
+
+    /// Used to scrape web3careers jobsite for a specific job-function filter and page number.
     async fn _scrape(
         url: &'static str,
         client: &Client,
+        filter: Option<&str>,
         page_number: u8,
     ) -> Result<Vec<Job>, ErrorKind>
     where
         Self: Scraper + Site,
     {
-        let mut jobs = Vec::new();
-        let url_full = format!("{}?page={}", url, page_number);
+        let url_full = match filter {
+            Some(filter) => format!("{}/{}?page={}", url, filter, page_number),
+            None => format!("{}?page={}", url, page_number),
+        };
         let doc = Self::get_html_doc(client, &url_full).await?;
+        Ok(from_page(Self::parse_jobs(url, &doc)?, &url_full))
+    }
+
+    /// Parses the Job instances out of a web3careers HTML doc.
+    fn parse_jobs(url: &str, doc: &Html) -> Result<Vec<Job>, ErrorKind> {
+        let mut jobs = Vec::new();
+        let strict = config::get().strict_titles;
+        let rem_regex = Regex::new(REM_REGEX).unwrap();
 
         // HTML selectors
-        let jobs_list_selector =
-            Self::get_selector("body>main>div>div>div>div>div>table>tbody>tr")?;
-        let title_selector =
-            Self::get_selector("body>main>div>div>div>div>div>table>tbody>tr>td>div>div>div>a>h2")?;
-        let company_selector =
-            Self::get_selector("body>main>div>div>div>div>div>table>tbody>tr>td>a>h3")?;
-        let location_selector =
-            Self::get_selector("body>main>div>div>div>div>div>table>tbody>tr>td:nth-child(4)")?;
-        let date_selector =
-            Self::get_selector("body>main>div>div>div>div>div>table>tbody>tr>td>time")?;
-        let remuneration_selector =
-            Self::get_selector("body>main>div>div>div>div>div>table>tbody>tr>td:nth-child(5)>p")?;
-        let tag_selector =
-            Self::get_selector("body>main>div>div>div>div>div>table>tbody>tr>td>div>span")?;
+        let s = selectors::for_site(selectors::WEB3_CAREERS);
+        let jobs_list_selector = Self::get_selectors(selectors::field(&s, "jobs"))?;
+        let title_selector = Self::get_selectors(selectors::field(&s, "title"))?;
+        let company_selector = Self::get_selectors(selectors::field(&s, "company"))?;
+        let location_selector = Self::get_selectors(selectors::field(&s, "location"))?;
+        let date_selector = Self::get_selectors(selectors::field(&s, "date"))?;
+        let remuneration_selector = Self::get_selectors(selectors::field(&s, "remuneration"))?;
+        let tag_selector = Self::get_selectors(selectors::field(&s, "tags"))?;
+        let logo_selector = Self::get_selectors(selectors::field(&s, "logo"))?;
 
-        for el in doc.select(&jobs_list_selector) {
+        for el in doc.select_all(&jobs_list_selector) {
             let mut job = Job::new();
             job.site = url.to_string();
 
-            if let Some(element) = el.select(&title_selector).next() {
+            let title = el.select_first(&title_selector);
+            if let Some(element) = title {
                 job.title = element.get_text();
-                if let Some(path_raw) = el.value().attr("onclick") {
-                    job.apply = Web3Careers::format_apply_url_from(url, path_raw);
-                }
-                if let Some(element) = el.select(&company_selector).next() {
-                    job.company = element.get_text();
-                }
-                if let Some(element) = el.select(&location_selector).next() {
-                    job.location = element.get_text();
-                }
-                if let Some(element) = el.select(&date_selector).next() {
-                    if let Some(date_raw) = element.value().attr("datetime") {
-                        job.date_posted = Self::format_date_from(date_raw);
-                    }
-                }
-                if let Some(element) = el.select(&remuneration_selector).next() {
-                    let remuneration = element.get_text();
-                    if !remuneration.is_empty()
-                        && Regex::new(REM_REGEX).unwrap().is_match(&remuneration)
-                    {
-                        (job.rem_lower, job.rem_upper) = Self::get_upper_lower(&remuneration);
-                        job.remuneration = remuneration;
-                    }
+            }
+            if let Some(path_raw) = el.value().attr("onclick") {
+                job.apply = Web3Careers::format_apply_url_from(url, path_raw);
+            }
+            if let Some(element) = el.select_first(&company_selector) {
+                job.company = element.get_text();
+            }
+            if let Some(element) = el.select_first(&location_selector) {
+                job.location = element.get_text();
+            }
+            if let Some(element) = el.select_first(&date_selector) {
+                if let Some(date_raw) = element.value().attr("datetime") {
+                    job.date_posted = Self::format_date_from(date_raw);
                 }
-                for tag_el in el.select(&tag_selector) {
-                    job.tags.push(tag_el.get_text());
+            }
+            if let Some(element) = el.select_first(&remuneration_selector) {
+                let remuneration = element.get_text();
+                if !remuneration.is_empty() && rem_regex.is_match(&remuneration) {
+                    (job.rem_lower, job.rem_upper) = Self::get_upper_lower(&remuneration);
+                    job.currency = currency_from(&remuneration);
+                    job.remuneration = remuneration;
                 }
+            }
+            for tag_el in el.select_all(&tag_selector) {
+                job.tags.push(tag_el.get_text());
+            }
+            job.category = classify::category_from_tags(&job.tags);
+            job.logo_url = logo_url(&el, &logo_selector, url);
 
+            if title.is_some() || keep_untitled(&mut job, strict) {
                 jobs.push(job);
             }
         }
+        warn_untitled(url, &jobs);
         jobs = jobs.into_iter().unique().collect();
         Ok(jobs)
     }
 }
 
 impl Scraper for CryptoJobsList {
-    async fn scrape(mut self) -> Result<Self, ErrorKind>
+    async fn scrape(mut self, client: &Client) -> Result<Self, ErrorKind>
     where
         Self: Sized,
     {
         let url = self.get_url();
-        let url_full = format!("{url}/engineering?sort=recent");
-        let doc = Self::get_html_doc(&Client::new(), &url_full).await?;
+        for page in 1..=config::get().crypto_jobs_list_pages {
+            if page > 1 {
+                tokio::time::sleep(self.request_delay()).await;
+            }
+            let url_full = match page {
+                1 => format!("{url}/engineering?sort=recent"),
+                page => format!("{url}/engineering?sort=recent&page={page}"),
+            };
+            let doc = Self::get_html_doc(client, &url_full).await?;
+            let mut jobs = from_page(Self::parse_jobs(url, &doc)?, &url_full);
+            // an empty page means we're past the last page of results
+            if jobs.is_empty() {
+                if page == 1 {
+                    let s = selectors::for_site(selectors::CRYPTO_JOBS_LIST);
+                    warn_no_jobs_matched(url, selectors::field(&s, "jobs"));
+                }
+                break;
+            }
+            self.jobs.append(&mut jobs);
+        }
+        self.jobs = unique_sorted(self.jobs);
+        Ok(self)
+    }
+}
+
+impl CryptoJobsList {
+    /// Parses the Job instances out of a cryptojobslist HTML doc.
+    fn parse_jobs(url: &str, doc: &Html) -> Result<Vec<Job>, ErrorKind> {
+        let mut jobs = Vec::new();
+        let strict = config::get().strict_titles;
 
         // HTML selectors
-        let jobs_list_selector = Self::get_selector("main>section>section>table>tbody>tr")?;
-        let title_selector = Self::get_selector("main>section>section>table>tbody>tr>td>div>a")?;
-        let company_selector = Self::get_selector("main>section>section>table>tbody>tr>td>a")?;
-        let location_selector =
-            Self::get_selector("main>section>section>table>tbody>tr>td:nth-child(5)>span")?;
-        let date_selector =
-            Self::get_selector("main>section>section>table>tbody>tr>td.job-time-since-creation")?;
-        let remuneration_selector =
-            Self::get_selector("main>section>section>table>tbody>tr>td>span.job-salary-text")?;
-        let tag_selector = Self::get_selector("main>section>section>table>tbody>tr>td>span")?;
+        let s = selectors::for_site(selectors::CRYPTO_JOBS_LIST);
+        let jobs_list_selector = Self::get_selectors(selectors::field(&s, "jobs"))?;
+        let title_selector = Self::get_selectors(selectors::field(&s, "title"))?;
+        let company_selector = Self::get_selectors(selectors::field(&s, "company"))?;
+        let location_selector = Self::get_selectors(selectors::field(&s, "location"))?;
+        let date_selector = Self::get_selectors(selectors::field(&s, "date"))?;
+        let remuneration_selector = Self::get_selectors(selectors::field(&s, "remuneration"))?;
+        let tag_selector = Self::get_selectors(selectors::field(&s, "tags"))?;
+        let logo_selector = Self::get_selectors(selectors::field(&s, "logo"))?;
 
-        for el in doc.select(&jobs_list_selector) {
+        for el in doc.select_all(&jobs_list_selector) {
             let mut job = Job::new();
             job.site = url.to_string();
 
-            if let Some(element) = el.select(&title_selector).next() {
+            let title = el.select_first(&title_selector);
+            if let Some(element) = title {
                 job.title = element.get_text();
                 if let Some(path) = element.value().attr("href") {
                     job.apply = format!("{}{}", url, path);
                 }
-                if let Some(element) = el.select(&company_selector).next() {
-                    job.company = element.get_text();
+            }
+            if let Some(element) = el.select_first(&company_selector) {
+                job.company = element.get_text();
+            }
+            if let Some(element) = el.select_first(&location_selector) {
+                job.location = element.get_text();
+            }
+            if let Some(element) = el.select_first(&date_selector) {
+                let date_raw = element.get_text();
+                job.date_posted = CryptoJobsList::format_date_from(&date_raw);
+            }
+            if let Some(element) = el.select_first(&remuneration_selector) {
+                let remuneration_raw = element.get_text();
+                job.remuneration = CryptoJobsList::format_remuneration_from(&remuneration_raw);
+                if !job.remuneration.is_empty() {
+                    (job.rem_lower, job.rem_upper) = Self::get_upper_lower(&job.remuneration);
+                    job.currency = currency_from(&job.remuneration);
                 }
-                if let Some(element) = el.select(&location_selector).next() {
-                    job.location = element.get_text();
+            }
+            for tag_el in el.select_all(&tag_selector) {
+                job.tags.push(tag_el.get_text());
+            }
+            if !job.tags.is_empty() {
+                job.tags.remove(0);
+            }
+            job.category = classify::category_from_tags(&job.tags);
+            job.logo_url = logo_url(&el, &logo_selector, url);
+
+            if title.is_some() || keep_untitled(&mut job, strict) {
+                jobs.push(job);
+            }
+        }
+        warn_untitled(url, &jobs);
+        jobs = jobs.into_iter().unique().collect();
+        Ok(jobs)
+    }
+}
+
+impl Scraper for CustomSite {
+    async fn scrape(mut self, client: &Client) -> Result<Self, ErrorKind>
+    where
+        Self: Sized,
+    {
+        for url in self.config.page_urls() {
+            let doc = Self::get_html_doc(client, &url).await?;
+            let mut jobs = from_page(self.parse_jobs(&url, &doc)?, &url);
+            self.jobs.append(&mut jobs);
+        }
+        if self.jobs.is_empty() {
+            warn_no_jobs_matched(&self.config.name, &self.config.selectors.jobs);
+        }
+        self.jobs = unique_sorted(self.jobs);
+        Ok(self)
+    }
+}
+
+impl CustomSite {
+    /// Parses the Job instances out of an HTML doc using the site's configured selectors. Relative
+    /// apply links are resolved against the page URL.
+    fn parse_jobs(&self, page_url: &str, doc: &Html) -> Result<Vec<Job>, ErrorKind> {
+        let mut jobs = Vec::new();
+        let strict = config::get().strict_titles;
+        let site_url = self.config.site_url();
+        let page_url = Url::parse(page_url).ok();
+        let rem_regex = Regex::new(REM_REGEX).unwrap();
+        let date_regex = Regex::new(r"^\d{4}-\d{2}-\d{2}").unwrap();
+
+        // HTML selectors
+        let s = &self.config.selectors;
+        let jobs_list_selector = Self::get_selectors(&s.jobs)?;
+        let title_selector = Self::get_selectors(&s.title)?;
+        let company_selector = Self::get_selectors(&s.company)?;
+        let location_selector = Self::get_selectors(&s.location)?;
+        let date_selector = Self::get_selectors(&s.date)?;
+        let remuneration_selector = Self::get_selectors(&s.remuneration)?;
+        let tag_selector = Self::get_selectors(&s.tags)?;
+        let apply_selector = Self::get_selectors(&s.apply)?;
+        let logo_selector = Self::get_selectors(&s.logo)?;
+        let deadline_selector = Self::get_selectors(&s.deadline)?;
+        let applicants_selector = Self::get_selectors(&s.applicants)?;
+        let category_selector = Self::get_selectors(&s.category)?;
+
+        for el in doc.select_all(&jobs_list_selector) {
+            let mut job = Job::new();
+            job.site = site_url.clone();
+
+            let title = el.select_first(&title_selector);
+            if let Some(element) = title {
+                job.title = element.get_text();
+            }
+            if let Some(element) = el.select_first(&company_selector) {
+                job.company = element.get_text();
+            }
+            if let Some(element) = el.select_first(&location_selector) {
+                job.location = match element.value().attr("content") {
+                    Some(c) => c.to_string(),
+                    None => element.get_text(),
+                };
+            }
+            if let Some(element) = el.select_first(&date_selector) {
+                let date_raw = match element.value().attr("datetime") {
+                    Some(d) => d.to_string(),
+                    None => element
+                        .value()
+                        .attr("content")
+                        .map(|c| c.to_string())
+                        .unwrap_or_else(|| element.get_text()),
+                };
+                job.date_posted = match date_regex.find(&date_raw) {
+                    Some(date) => date.as_str().to_string(),
+                    None => CryptoJobsList::format_date_from(&date_raw),
+                };
+            }
+            if let Some(element) = el.select_first(&remuneration_selector) {
+                let remuneration = element.get_text();
+                if rem_regex.is_match(&remuneration) {
+                    (job.rem_lower, job.rem_upper) = CryptoJobsList::get_upper_lower(&remuneration);
+                    job.currency = currency_from(&remuneration);
+                    job.remuneration = remuneration;
                 }
-                if let Some(element) = el.select(&date_selector).next() {
-                    let date_raw = element.get_text();
-                    job.date_posted = CryptoJobsList::format_date_from(&date_raw);
+            }
+            for tag_el in el.select_all(&tag_selector) {
+                job.tags.push(tag_el.get_text());
+            }
+            if let Some(element) = el.select_first(&apply_selector) {
+                if let Some(href) = element.value().attr("href") {
+                    job.apply = match &page_url {
+                        Some(base) => base
+                            .join(href)
+                            .map(|u| u.to_string())
+                            .unwrap_or(href.to_string()),
+                        None => href.to_string(),
+                    };
                 }
-                if let Some(element) = el.select(&remuneration_selector).next() {
-                    let remuneration_raw = element.get_text();
-                    job.remuneration = CryptoJobsList::format_remuneration_from(&remuneration_raw);
-                    if !job.remuneration.is_empty() {
-                        (job.rem_lower, job.rem_upper) = Self::get_upper_lower(&job.remuneration);
-                    }
+            }
+            if let Some(page_url) = &page_url {
+                job.logo_url = logo_url(&el, &logo_selector, page_url.as_str());
+            }
+            if let Some(element) = el.select_first(&deadline_selector) {
+                let deadline_raw = ["datetime", "content"]
+                    .iter()
+                    .find_map(|attr| element.value().attr(attr).map(str::to_string))
+                    .unwrap_or_else(|| element.get_text());
+                job.deadline = CryptoJobsList::format_deadline_from(&deadline_raw);
+            }
+            if let Some(element) = el.select_first(&applicants_selector) {
+                job.applicants = applicants_from(&element.get_text());
+            }
+            if let Some(element) = el.select_first(&category_selector) {
+                job.category = match element.value().attr("content") {
+                    Some(c) => classify::category(c),
+                    None => classify::category(&element.get_text()),
+                };
+            }
+
+            if title.is_some() || keep_untitled(&mut job, strict) {
+                jobs.push(job);
+            }
+        }
+        warn_untitled(&site_url, &jobs);
+        jobs = jobs.into_iter().unique().collect();
+        Ok(jobs)
+    }
+}
+
+/// The body of a Greenhouse board's jobs endpoint.
+#[derive(Deserialize)]
+struct GreenhouseBoard {
+    jobs: Vec<GreenhouseJob>,
+}
+
+/// A job as listed by a Greenhouse board's jobs endpoint.
+#[derive(Deserialize)]
+struct GreenhouseJob {
+    title: String,
+    absolute_url: String,
+    updated_at: String,
+    location: Option<GreenhouseLocation>,
+    company_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GreenhouseLocation {
+    name: String,
+}
+
+impl Scraper for GreenhouseJobs {
+    async fn scrape(mut self, client: &Client) -> Result<Self, ErrorKind>
+    where
+        Self: Sized,
+    {
+        // the board is JSON rather than HTML, so it's fetched without the document size check
+        let url_full = self.board_url();
+        let body = get_body(client, &url_full, &config::get()).await?;
+        let board = serde_json::from_str::<GreenhouseBoard>(&body)
+            .map_err(|e| ErrorKind::Decode(format!("{url_full} isn't a Greenhouse board. {e}")))?;
+        let site = url_full.trim_end_matches("/jobs");
+        let jobs = board.jobs.into_iter().map(|gh| Job {
+            title: gh.title.trim().to_string(),
+            // boards only sometimes name their company, so the board token stands in for it
+            company: gh.company_name.unwrap_or_else(|| self.board().to_string()),
+            date_posted: Self::format_date_from(&gh.updated_at),
+            location: gh.location.map(|l| l.name).unwrap_or_default(),
+            apply: gh.absolute_url,
+            site: site.to_string(),
+            ..Default::default()
+        });
+        self.jobs = unique_sorted(from_page(jobs.collect(), &url_full));
+        Ok(self)
+    }
+}
+
+impl GreenhouseJobs {
+    /// Formats an `updated_at` timestamp (e.g. "2024-05-06T12:05:50-04:00") as a posted date.
+    fn format_date_from(updated_at: &str) -> String {
+        DateTime::parse_from_rfc3339(updated_at)
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// Implements the Scraper trait for common jobsites.
+impl Scraper for CommonSite {
+    async fn scrape(mut self, client: &Client) -> Result<Self, ErrorKind>
+    where
+        Self: Sized,
+    {
+        let url = self.board.url.clone();
+        let mut prev_keys = HashSet::new();
+        for page in 1..=config::get().common_pages {
+            if page > 1 {
+                tokio::time::sleep(self.request_delay()).await;
+            }
+            let url_full = self.board.page_url(page);
+            let doc = Self::get_html_doc(client, &url_full).await?;
+            let jobs = from_page(Self::parse_jobs(&url, &doc)?, &url_full);
+            // an empty page means we're past the last page of results, and a repeated one that
+            // the board ignores the page number
+            let keys = jobs.iter().map(Job::dedup_key).collect::<HashSet<String>>();
+            if jobs.is_empty() || keys == prev_keys {
+                if page == 1 {
+                    let s = selectors::for_site(selectors::COMMON);
+                    warn_no_jobs_matched(&url, selectors::field(&s, "jobs"));
+                }
+                break;
+            }
+            self.jobs.extend(jobs);
+            prev_keys = keys;
+        }
+        self.jobs = unique_sorted(self.jobs);
+        Ok(self)
+    }
+}
+
+impl CommonSite {
+    /// Parses the Job instances out of a common jobsite HTML doc.
+    fn parse_jobs(url: &str, doc: &Html) -> Result<Vec<Job>, ErrorKind> {
+        let mut jobs = Vec::new();
+        let strict = config::get().strict_titles;
+
+        // HTML selectors
+        let s = selectors::for_site(selectors::COMMON);
+        let jobs_list_selector = Self::get_selectors(selectors::field(&s, "jobs"))?;
+        let title_selector = Self::get_selectors(selectors::field(&s, "title"))?;
+        let company_selector = Self::get_selectors(selectors::field(&s, "company"))?;
+        let location_selector = Self::get_selectors(selectors::field(&s, "location"))?;
+        let date_selector = Self::get_selectors(selectors::field(&s, "date"))?;
+        let apply_selector = Self::get_selectors(selectors::field(&s, "apply"))?;
+        let logo_selector = Self::get_selectors(selectors::field(&s, "logo"))?;
+        let deadline_selector = Self::get_selectors(selectors::field(&s, "deadline"))?;
+        let category_selector = Self::get_selectors(selectors::field(&s, "category"))?;
+
+        for el in doc.select_all(&jobs_list_selector) {
+            let mut job = Job::new();
+            job.site = url.to_string();
+
+            let title = el.select_first(&title_selector);
+            if let Some(element) = title {
+                job.title = element.get_text();
+            }
+            if let Some(element) = el.select_first(&company_selector) {
+                job.company = element.get_text();
+            }
+            if let Some(element) = el.select_first(&location_selector) {
+                if let Some(c) = element.value().attr("content") {
+                    job.location = c.to_string();
                 }
-                for tag_el in el.select(&tag_selector) {
-                    job.tags
-                        .push(tag_el.text().collect::<String>().trim().to_owned());
+            }
+            if let Some(element) = el.select_first(&date_selector) {
+                if let Some(c) = element.value().attr("content") {
+                    job.date_posted = c.to_string();
                 }
-                if !job.tags.is_empty() {
-                    job.tags.remove(0);
+            }
+            if let Some(element) = el.select_first(&apply_selector) {
+                if let Some(path_raw) = element.value().attr("href") {
+                    job.apply = Self::format_apply_url_from(url, path_raw);
                 }
+            }
+            job.logo_url = logo_url(&el, &logo_selector, url);
+            if let Some(element) = el.select_first(&deadline_selector) {
+                job.deadline = element
+                    .value()
+                    .attr("content")
+                    .and_then(CryptoJobsList::format_deadline_from);
+            }
+            if let Some(element) = el.select_first(&category_selector) {
+                job.category = match element.value().attr("content") {
+                    Some(c) => classify::category(c),
+                    None => classify::category(&element.get_text()),
+                };
+            }
 
-                self.jobs.push(job);
+            if title.is_some() || keep_untitled(&mut job, strict) {
+                jobs.push(job);
             }
         }
-        self.jobs = self.jobs.into_iter().unique().collect();
-        Ok(self)
+        warn_untitled(url, &jobs);
+        jobs = jobs.into_iter().unique().collect();
+        Ok(jobs)
     }
 }
 
-/// Implements the Scraper trait for common jobsites.
+/// Implements Scraper for a built-in common jobsite, scraping it as a CommonSite with the default
+/// filter.
 macro_rules! impl_scraper_for_common {
-    ($t:ident, $qp:expr) => {
+    ($t:ident) => {
         impl Scraper for $t {
-            async fn scrape(mut self) -> Result<Self, ErrorKind>
+            async fn scrape(mut self, client: &Client) -> Result<Self, ErrorKind>
             where
                 Self: Sized,
             {
                 let url = self.get_url();
-                let url_full = format!("{url}?filter={}", $qp);
-                let doc = Self::get_html_doc(&Client::new(), &url_full).await?;
-
-                // HTML selectors
-                let jobs_list_selector = Self::get_selector("#content>div>div>div>div>div>div")?;
-                let title_selector =
-                    Self::get_selector("#content>div>div>div>div>div>div>div>div>h4>a>div>div")?;
-                let company_selector =
-                    Self::get_selector("#content>div>div>div>div>div>div>div>div>div>div>a")?;
-                let location_selector = Self::get_selector(
-                    "#content>div>div>div>div>div>div>div>div>div>div>div>meta",
-                )?;
-                let date_selector = Self::get_selector(
-                    "#content>div>div>div>div>div>div>div>div>div>div>div>div>meta",
-                )?;
-                let apply_selector = Self::get_selector(
-                    "#content>div>div>div>div>div>div>div>div.sc-beqWaB.sc-gueYoa.hcVvkM.MYFxR>a",
-                )?;
-
-                for el in doc.select(&jobs_list_selector) {
-                    let mut job = Job::new();
-                    job.site = url.to_string();
-
-                    if let Some(element) = el.select(&title_selector).next() {
-                        job.title = element.get_text();
-                        if let Some(element) = el.select(&company_selector).next() {
-                            job.company = element.get_text();
-                        }
-                        if let Some(element) = el.select(&location_selector).next() {
-                            if let Some(c) = element.value().attr("content") {
-                                job.location = c.to_string();
-                            }
-                        }
-                        if let Some(element) = el.select(&date_selector).next() {
-                            if let Some(c) = element.value().attr("content") {
-                                job.date_posted = c.to_string();
-                            }
-                        }
-                        if let Some(element) = el.select(&apply_selector).next() {
-                            if let Some(path_raw) = element.value().attr("href") {
-                                job.apply = Self::format_apply_url_from(url, path_raw);
-                            }
-                        }
-
-                        self.jobs.push(job);
-                    }
-                }
-                self.jobs = self.jobs.into_iter().unique().collect();
+                let board = CommonBoard::new(url, url, COMMON_FILTER);
+                self.jobs = CommonSite::new(board).scrape(client).await?.jobs;
                 Ok(self)
             }
         }
     };
 }
 
-impl_scraper_for_common!(
-    SolanaJobs,
-    "eyJqb2JfZnVuY3Rpb25zIjpbIlNvZnR3YXJlIEVuZ2luZWVyaW5nIl19"
-);
-impl_scraper_for_common!(
-    SubstrateJobs,
-    "eyJqb2JfZnVuY3Rpb25zIjpbIlNvZnR3YXJlIEVuZ2luZWVyaW5nIl19"
-);
-impl_scraper_for_common!(
-    NearJobs,
-    "eyJqb2JfZnVuY3Rpb25zIjpbIlNvZnR3YXJlIEVuZ2luZWVyaW5nIl19"
-);
+impl_scraper_for_common!(SolanaJobs);
+impl_scraper_for_common!(SubstrateJobs);
+impl_scraper_for_common!(NearJobs);
 
 #[cfg(test)]
 mod tests {
-    use regex::Regex;
-
     use crate::repository::Job;
     use crate::site::{
-        CryptoJobsList, NearJobs, Site, SolanaJobs, SubstrateJobs, Web3Careers,
-        CRYPTO_JOBS_LIST_URL, NEAR_JOBS_URL, SOLANA_JOBS_URL, SUBSTRATE_JOBS_URL, WEB3_CAREERS_URL,
+        CommonSite, CryptoJobsList, CustomSite, DateFormatter, GreenhouseJobs, NearJobs, Site,
+        SiteGroup, SitesFile, SolanaJobs, SubstrateJobs, Web3Careers, CRYPTO_JOBS_LIST_URL,
+        NEAR_JOBS_URL, SOLANA_JOBS_URL, SUBSTRATE_JOBS_URL, WEB3_CAREERS_REQUEST_DELAY,
+        WEB3_CAREERS_URL,
     };
 
-    use super::{Scraper, REM_REGEX};
+    use std::collections::{BTreeMap, HashMap};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    use reqwest::Client;
+    use scraper::{Html, Selector};
 
-    const DATE_REGEX: &str = r"(\d{4})-(\d{2})-(\d{2})( (\d{2}):(\d{2}):(\d{2}))?";
+    use crate::config::{Cache, Config, Retry, DEFAULT_USER_AGENT};
+    use crate::doctor::{valid_field, CHECKED_FIELDS};
+    use crate::test_server::{Response, TestServer};
+    use crate::ErrorKind;
+
+    use super::{
+        client_with_timeout, fetch_body, fetch_body_with_retry, get_body, job_order, keep_untitled,
+        logo_url, next_user_agent, scrape_retrying_empty, test_selector, unique_sorted,
+        RateLimiter, ScrapeBudget, Scraper, UNTITLED,
+    };
 
     #[tokio::test]
     async fn test_scrape_web3careers() {
-        let jobs = Web3Careers::new().scrape().await.unwrap().jobs;
-        assert_eq!(jobs[0].site, WEB3_CAREERS_URL);
+        let jobs = Web3Careers::new()
+            .scrape(&Client::new())
+            .await
+            .unwrap()
+            .jobs;
+        assert!(jobs.iter().all(|job| job.site == WEB3_CAREERS_URL));
         job_assertions(jobs)
     }
 
     #[tokio::test]
     async fn test_scrape_crypto_jobs_list() {
-        let jobs = CryptoJobsList::new().scrape().await.unwrap().jobs;
-        assert_eq!(jobs[0].site, CRYPTO_JOBS_LIST_URL);
+        let jobs = CryptoJobsList::new()
+            .scrape(&Client::new())
+            .await
+            .unwrap()
+            .jobs;
+        assert!(jobs.iter().all(|job| job.site == CRYPTO_JOBS_LIST_URL));
         job_assertions(jobs)
     }
 
     #[tokio::test]
     async fn test_scrape_solana_jobs() {
-        let jobs = SolanaJobs::new().scrape().await.unwrap().jobs;
-        assert_eq!(jobs[0].site, SOLANA_JOBS_URL);
+        let jobs = SolanaJobs::new().scrape(&Client::new()).await.unwrap().jobs;
+        assert!(jobs.iter().all(|job| job.site == SOLANA_JOBS_URL));
+        // the later pages are scraped too
+        let url_full = format!(
+            "{SOLANA_JOBS_URL}?filter=eyJqb2JfZnVuY3Rpb25zIjpbIlNvZnR3YXJlIEVuZ2luZWVyaW5nIl19"
+        );
+        let doc = SolanaJobs::get_html_doc(&Client::new(), &url_full)
+            .await
+            .unwrap();
+        let first_page = CommonSite::parse_jobs(SOLANA_JOBS_URL, &doc).unwrap();
+        assert!(jobs.len() > first_page.len());
         job_assertions(jobs)
     }
 
     #[tokio::test]
     async fn test_scrape_substrate_jobs() {
-        let jobs = SubstrateJobs::new().scrape().await.unwrap().jobs;
-        assert_eq!(jobs[0].site, SUBSTRATE_JOBS_URL);
+        let jobs = SubstrateJobs::new()
+            .scrape(&Client::new())
+            .await
+            .unwrap()
+            .jobs;
+        assert!(jobs.iter().all(|job| job.site == SUBSTRATE_JOBS_URL));
         job_assertions(jobs)
     }
 
     #[tokio::test]
     async fn test_scrape_near_jobs() {
-        let jobs = NearJobs::new().scrape().await.unwrap().jobs;
-        assert_eq!(jobs[0].site, NEAR_JOBS_URL);
+        let jobs = NearJobs::new().scrape(&Client::new()).await.unwrap().jobs;
+        assert!(jobs.iter().all(|job| job.site == NEAR_JOBS_URL));
         job_assertions(jobs)
     }
 
-    fn job_assertions(jobs: Vec<Job>) {
-        assert!(jobs.len() > 0);
-        for job in &jobs {
-            assert!(!job.title.is_empty());
-            assert!(!job.company.is_empty());
-            assert!(Regex::new(DATE_REGEX).unwrap().is_match(&job.date_posted));
-            assert!(
-                Regex::new(REM_REGEX).unwrap().is_match(&job.remuneration)
-                    || job.remuneration.is_empty()
+    const WEB3_CAREERS_ROW: &str = r#"
+        <td><div><div><div><a><h2>Senior Rust Engineer</h2></a></div></div></div></td>
+        <td><a><img src="data:image/gif;base64,R0lGOD" data-src="/logos/parity.png"><h3>Parity</h3></a></td>
+        <td><time datetime="2024-05-06 12:05:50+07:00">1d</time></td>
+        <td>Remote</td>
+        <td><p>$90k - $140k</p></td>
+        <td><div><span>rust</span><span>defi</span></div></td>
+    "#;
+
+    #[test]
+    fn test_web3careers_parse_other_currencies() {
+        let rows = [
+            "£90k - £140k",
+            "CHF 120k - CHF 150k",
+            "USDC 90k",
+            "Up to £150k",
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, rem)| {
+            let row = WEB3_CAREERS_ROW
+                .replace("Senior Rust Engineer", &format!("Engineer {i}"))
+                .replace("$90k - $140k", rem);
+            format!("<tr onclick=\"tableTurboRowClick(event, '/engineer/{i}')\">{row}</tr>")
+        })
+        .collect::<String>();
+        let page = format!(
+            "<html><body><main><section><table><tbody>{rows}</tbody></table></section></main></body></html>"
+        );
+        let jobs = Web3Careers::parse_jobs(WEB3_CAREERS_URL, &Html::parse_document(&page)).unwrap();
+        let salaries = jobs
+            .iter()
+            .map(|j| (j.remuneration.as_str(), j.currency.as_deref(), j.rem_upper))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            salaries,
+            [
+                ("£90k - £140k", Some("GBP"), Some(140000)),
+                ("CHF 120k - CHF 150k", Some("CHF"), Some(150000)),
+                ("USDC 90k", Some("USDC"), Some(90000)),
+                ("Up to £150k", Some("GBP"), Some(150000)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_web3careers_parse_layout_variants() {
+        let onclick = "tableTurboRowClick(event, '/senior-rust-engineer-parity/1')";
+        let layout_a = format!(
+            r#"<html><body><main><div><div><div><div><div><table><tbody>
+            <tr onclick="{onclick}">{WEB3_CAREERS_ROW}</tr>
+            </tbody></table></div></div></div></div></div></main></body></html>"#
+        );
+        let layout_b = format!(
+            r#"<html><body><main><section><table><tbody>
+            <tr onclick="{onclick}">{WEB3_CAREERS_ROW}</tr>
+            </tbody></table></section></main></body></html>"#
+        );
+
+        for layout in [layout_a, layout_b] {
+            let doc = Html::parse_document(&layout);
+            let jobs = Web3Careers::parse_jobs(WEB3_CAREERS_URL, &doc).unwrap();
+            assert_eq!(jobs.len(), 1);
+            let job = &jobs[0];
+            assert_eq!(job.title, "Senior Rust Engineer");
+            assert_eq!(job.company, "Parity");
+            assert_eq!(job.location, "Remote");
+            assert_eq!(job.date_posted, "2024-05-06");
+            assert_eq!(job.remuneration, "$90k - $140k");
+            assert_eq!(job.currency.as_deref(), Some("USD"));
+            assert_eq!((job.rem_lower, job.rem_upper), (Some(90000), Some(140000)));
+            assert_eq!(job.tags, vec!["rust", "defi"]);
+            assert_eq!(job.category, None);
+            assert_eq!(
+                job.apply,
+                "https://web3.career/senior-rust-engineer-parity/1"
+            );
+            assert_eq!(
+                job.logo_url.as_deref(),
+                Some("https://web3.career/logos/parity.png")
             );
-            assert!(
-                job.apply.starts_with("https")
-                    || job.apply.starts_with("mailto")
-                    || job.apply.is_empty()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_groups() {
+        let groups = BTreeMap::from([(
+            "slow".to_string(),
+            SiteGroup {
+                hosts: vec!["www.slow.test".to_string()],
+                concurrency: 1,
+                delay_ms: 100,
+            },
+        )]);
+        let limiter = RateLimiter::new(&groups, 8);
+        assert_eq!(limiter.group_of("https://slow.test/jobs"), "slow");
+        assert_eq!(limiter.group_of("https://fast.test/jobs"), "default");
+
+        // the slow group's requests are spaced out by its delay
+        let start = Instant::now();
+        for _ in 0..3 {
+            let _permit = limiter.acquire("https://slow.test/jobs").await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        // while the slow group's only slot is taken, the default group isn't held up
+        let _held = limiter.acquire("https://slow.test/jobs").await;
+        let start = Instant::now();
+        for _ in 0..3 {
+            let _permit = limiter.acquire("https://fast.test/jobs").await;
+        }
+        assert!(start.elapsed() < Duration::from_millis(100));
+        let waiting = limiter.acquire("https://slow.test/jobs");
+        assert!(tokio::time::timeout(Duration::from_millis(50), waiting)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_scrape_retrying_empty() {
+        // the first response is a valid page without any jobs yet
+        let fetches = AtomicUsize::new(0);
+        let server = TestServer::start(move |_| match fetches.fetch_add(1, Ordering::SeqCst) {
+            0 => Response::ok(EMPTY_PAGE),
+            _ => Response::ok(&CUSTOM_SITE_PAGE.replace("{page}", "1")),
+        });
+        let sites_file = format!(
+            r#"
+            [[sites]]
+            name = "Flaky Board"
+            url = "{}/jobs"
+            selectors = {{ jobs = "ul.jobs > li", title = "h3" }}
+            "#,
+            server.url()
+        );
+        let site = SitesFile::parse(&sites_file, false)
+            .unwrap()
+            .sites
+            .remove(0);
+        let url = site.site_url();
+        let jobs = scrape_retrying_empty(&url, 1, || async {
+            Ok(CustomSite::new(site.clone())
+                .scrape(&Client::new())
+                .await?
+                .jobs)
+        })
+        .await
+        .unwrap();
+        assert_eq!(server.requests().len(), 2);
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].title, "Protocol Engineer 1");
+    }
+
+    #[tokio::test]
+    async fn test_test_selector() {
+        let server = TestServer::start(|_| Response::ok(&CUSTOM_SITE_PAGE.replace("{page}", "1")));
+        let url = format!("{}/jobs", server.url());
+        let matches = test_selector(&url, "ul.jobs > li h3", 5).await.unwrap();
+        assert_eq!(matches.count, 1);
+        assert_eq!(matches.samples, ["Protocol Engineer 1"]);
+
+        // an invalid selector fails without fetching the page
+        let err = test_selector(&url, "ul.jobs >", 5).await.err().unwrap();
+        assert!(matches!(err, ErrorKind::Selector(_)));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_scrape_greenhouse() {
+        let server = TestServer::start(|req| match req.path.as_str() {
+            "/chainlinklabs/jobs" => Response::ok(
+                r#"{"jobs": [
+                    {"id": 1, "title": " Senior Rust Engineer ", "updated_at": "2024-05-06T12:05:50-04:00",
+                     "location": {"name": "Remote"}, "absolute_url": "https://boards.greenhouse.io/chainlinklabs/jobs/1",
+                     "company_name": "Chainlink Labs"},
+                    {"id": 2, "title": "Protocol Engineer", "updated_at": "2024-05-01T09:00:00Z",
+                     "location": null, "absolute_url": "https://boards.greenhouse.io/chainlinklabs/jobs/2"}
+                ], "meta": {"total": 2}}"#,
+            ),
+            _ => Response::ok("<html></html>"),
+        });
+        let url = Box::leak(server.url().to_string().into_boxed_str());
+
+        let jobs = GreenhouseJobs::with_url(url)
+            .with_board("chainlinklabs")
+            .scrape(&Client::new())
+            .await
+            .unwrap()
+            .jobs;
+        assert_eq!(jobs.len(), 2);
+        let (protocol, rust) = (&jobs[0], &jobs[1]);
+        assert_eq!(rust.title, "Senior Rust Engineer");
+        assert_eq!(rust.company, "Chainlink Labs");
+        assert_eq!(rust.date_posted, "2024-05-06");
+        assert_eq!(rust.location, "Remote");
+        assert_eq!(
+            rust.apply,
+            "https://boards.greenhouse.io/chainlinklabs/jobs/1"
+        );
+        assert_eq!(rust.site, format!("{url}/chainlinklabs"));
+        assert_eq!(protocol.company, "chainlinklabs");
+        assert_eq!(protocol.location, "");
+
+        let res = GreenhouseJobs::with_url(url)
+            .with_board("nonexistent")
+            .scrape(&Client::new())
+            .await;
+        assert!(matches!(res, Err(ErrorKind::Decode(_))));
+    }
+
+    #[tokio::test]
+    async fn test_scrape_budget() {
+        let scrape = |delay: u64| async move {
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            Ok::<_, ErrorKind>(delay)
+        };
+        let budget = ScrapeBudget::start(Some(Duration::from_millis(200)));
+        assert_eq!(budget.run("fast", scrape(10)).await.unwrap(), Some(10));
+        assert_eq!(budget.run("slow", scrape(5_000)).await.unwrap(), None);
+        // the budget has run out, so later sites are cut off too
+        assert_eq!(budget.run("later", scrape(10)).await.unwrap(), None);
+        assert_eq!(budget.cut_off(), ["slow", "later"]);
+
+        let unlimited = ScrapeBudget::start(None);
+        assert_eq!(unlimited.run("fast", scrape(10)).await.unwrap(), Some(10));
+        assert!(unlimited.cut_off().is_empty());
+
+        // scrapes sharing a budget run concurrently
+        let budget = ScrapeBudget::start(Some(Duration::from_millis(1_000)));
+        let started = Instant::now();
+        let (a, b) = tokio::join!(budget.run("a", scrape(300)), budget.run("b", scrape(300)));
+        assert_eq!((a.unwrap(), b.unwrap()), (Some(300), Some(300)));
+        assert!(started.elapsed() < Duration::from_millis(550));
+    }
+
+    #[test]
+    fn test_logo_url() {
+        let logo = |row: &str| {
+            let doc = Html::parse_fragment(&format!("<div>{row}</div>"));
+            let el = doc.root_element();
+            let selectors = [Selector::parse("img").unwrap()];
+            logo_url(&el, &selectors, "https://jobs.example.com/board/")
+        };
+        assert_eq!(
+            logo(r#"<img src="https://cdn.example.com/acme.png">"#).as_deref(),
+            Some("https://cdn.example.com/acme.png")
+        );
+        assert_eq!(
+            logo(r#"<img src="/blank.gif" data-src="acme.png">"#).as_deref(),
+            Some("https://jobs.example.com/board/acme.png")
+        );
+        assert_eq!(logo(r#"<img src="data:image/gif;base64,R0lGOD">"#), None);
+        assert_eq!(logo(r#"<img src="">"#), None);
+        assert_eq!(logo("<span>Acme</span>"), None);
+    }
+
+    /// A page with no job listings, but big enough to pass the empty document check.
+    const EMPTY_PAGE: &str = r#"<!DOCTYPE html><html lang="en"><head><meta charset="utf-8">
+        <title>Jobs</title><meta name="viewport" content="width=device-width, initial-scale=1">
+        <link rel="stylesheet" href="/assets/app.css"></head><body><header><nav></nav></header>
+        <main><p>No jobs found.</p></main><footer></footer></body></html>"#;
+
+    #[tokio::test]
+    async fn test_empty_document() {
+        let server = TestServer::start(|req| match req.path.as_str() {
+            "/empty" => Response::ok(" <html></html> "),
+            _ => Response::ok(EMPTY_PAGE),
+        });
+        let client = Client::new();
+
+        let url = format!("{}/empty", server.url());
+        match Web3Careers::get_html_doc(&client, &url).await {
+            Err(ErrorKind::EmptyDocument(u, 13)) => assert_eq!(u, url),
+            res => panic!("expected EmptyDocument, got {:?}", res.map(|_| ())),
+        }
+        let url = format!("{}/jobs", server.url());
+        assert!(Web3Careers::get_html_doc(&client, &url).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_web3careers_stops_on_repeated_page() {
+        let rows = ["/rust-engineer/1", "/backend-engineer/2"]
+            .iter()
+            .zip(["Rust Engineer", "Backend Engineer"])
+            .map(|(path, title)| {
+                let row = WEB3_CAREERS_ROW.replace("Senior Rust Engineer", title);
+                format!(r#"<tr onclick="tableTurboRowClick(event, '{path}')">{row}</tr>"#)
+            })
+            .collect::<String>();
+        let page =
+            format!("<html><body><main><table><tbody>{rows}</tbody></table></main></body></html>");
+        // every page number gets the first page
+        let server = TestServer::start(move |_| Response::ok(&page));
+        let url = Box::leak(server.url().to_string().into_boxed_str());
+
+        let jobs = Web3Careers::with_url(url)
+            .scrape_filters(&Client::new(), &[])
+            .await
+            .unwrap()
+            .jobs;
+        assert_eq!(
+            jobs.iter().map(|j| j.title.as_str()).collect::<Vec<_>>(),
+            ["Backend Engineer", "Rust Engineer"]
+        );
+        let paths = server
+            .requests()
+            .into_iter()
+            .map(|r| r.path)
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["/?page=1", "/?page=2"]);
+    }
+
+    #[tokio::test]
+    async fn test_web3careers_max_pages() {
+        let row = |page: &str| {
+            let row = WEB3_CAREERS_ROW.replace("Senior Rust Engineer", &format!("Engineer {page}"));
+            format!(
+                r#"<html><body><main><table><tbody><tr onclick="tableTurboRowClick(event, '/engineer/{page}')">{row}</tr></tbody></table></main></body></html>"#
+            )
+        };
+        // pages 1 and 2 have jobs, and the listing ends after them
+        let server = TestServer::start(move |req| match req.path.as_str() {
+            "/?page=1" => Response::ok(&row("1")),
+            "/?page=2" => Response::ok(&row("2")),
+            _ => Response::ok(EMPTY_PAGE),
+        });
+        let url = Box::leak(server.url().to_string().into_boxed_str());
+
+        let site = Web3Careers::with_url(url).with_max_pages(1);
+        let jobs = site.scrape_filters(&Client::new(), &[]).await.unwrap().jobs;
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(server.requests().len(), 1);
+
+        // the first page without jobs ends the listing, and the pages are requested a delay apart
+        let start = Instant::now();
+        let jobs = Web3Careers::with_url(url)
+            .scrape_filters(&Client::new(), &[])
+            .await
+            .unwrap()
+            .jobs;
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(server.requests().len(), 1 + 3);
+        assert!(start.elapsed() >= WEB3_CAREERS_REQUEST_DELAY * 2);
+        assert_eq!(CryptoJobsList::new().request_delay(), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_web3careers_fill_salaries() {
+        let rows = [
+            ("Rust Engineer", "/rust-engineer/1"),
+            ("Go Engineer", "/go-engineer/2"),
+        ]
+        .iter()
+        .map(|(title, path)| {
+            let row = WEB3_CAREERS_ROW
+                .replace("Senior Rust Engineer", title)
+                .replace("$90k - $140k", "");
+            format!(r#"<tr onclick="tableTurboRowClick(event, '{path}')">{row}</tr>"#)
+        })
+        .collect::<String>();
+        let listing =
+            format!("<html><body><main><table><tbody>{rows}</tbody></table></main></body></html>");
+        let server = TestServer::start(move |req| match req.path.as_str() {
+            "/rust-engineer/1" => Response::ok(&EMPTY_PAGE.replace(
+                "<p>No jobs found.</p>",
+                r#"<p class="salary">$ 100k-150k</p>"#,
+            )),
+            "/go-engineer/2" => Response::ok(EMPTY_PAGE),
+            _ => Response::ok(&listing),
+        });
+        let url = Box::leak(server.url().to_string().into_boxed_str());
+
+        let mut site = Web3Careers::with_url(url)
+            .scrape_filters(&Client::new(), &[])
+            .await
+            .unwrap();
+        site.fill_salaries(&Client::new(), 5).await.unwrap();
+        let job = |title: &str| site.jobs.iter().find(|j| j.title == title).unwrap();
+        assert_eq!(job("Rust Engineer").remuneration, "$100k - $150k");
+        assert_eq!(
+            (
+                job("Rust Engineer").rem_lower,
+                job("Rust Engineer").rem_upper
+            ),
+            (Some(100000), Some(150000))
+        );
+        assert_eq!(job("Rust Engineer").currency.as_deref(), Some("USD"));
+        assert_eq!(job("Go Engineer").remuneration, "");
+
+        // the number of detail pages fetched is bounded
+        let mut site = Web3Careers::with_url(url)
+            .scrape_filters(&Client::new(), &[])
+            .await
+            .unwrap();
+        let fetched = server.requests().len();
+        site.fill_salaries(&Client::new(), 1).await.unwrap();
+        assert_eq!(server.requests().len(), fetched + 1);
+    }
+
+    #[tokio::test]
+    async fn test_web3careers_merges_filters() {
+        let page = |rows: &[(&str, &str)]| {
+            let rows = rows
+                .iter()
+                .map(|(title, path)| {
+                    let row = WEB3_CAREERS_ROW.replace("Senior Rust Engineer", title);
+                    format!(r#"<tr onclick="tableTurboRowClick(event, '{path}')">{row}</tr>"#)
+                })
+                .collect::<String>();
+            format!("<html><body><main><table><tbody>{rows}</tbody></table></main></body></html>")
+        };
+        let backend = page(&[
+            ("Backend Engineer", "/backend-engineer/1"),
+            ("Senior Rust Engineer", "/senior-rust-engineer/2"),
+        ]);
+        let smart_contract = page(&[
+            ("Solidity Engineer", "/solidity-engineer/3"),
+            (
+                "Senior Rust Engineer",
+                "/senior-rust-engineer/2?from=smart-contract",
+            ),
+        ]);
+        let server = TestServer::start(move |req| match req.path.as_str() {
+            "/backend-jobs?page=1" => Response::ok(&backend),
+            "/smart-contract-jobs?page=1" => Response::ok(&smart_contract),
+            _ => Response::ok(EMPTY_PAGE),
+        });
+        let url = Box::leak(server.url().to_string().into_boxed_str());
+
+        let filters = [
+            "backend-jobs".to_string(),
+            "smart-contract-jobs".to_string(),
+        ];
+        let jobs = Web3Careers::with_url(url)
+            .scrape_filters(&Client::new(), &filters)
+            .await
+            .unwrap()
+            .jobs;
+        assert_eq!(
+            jobs.iter().map(|j| j.title.as_str()).collect::<Vec<_>>(),
+            [
+                "Backend Engineer",
+                "Senior Rust Engineer",
+                "Solidity Engineer"
+            ]
+        );
+        assert!(server
+            .requests()
+            .iter()
+            .all(|r| r.path.starts_with("/backend-jobs?")
+                || r.path.starts_with("/smart-contract-jobs?")));
+    }
+
+    #[test]
+    fn test_web3careers_keeps_untitled_job() {
+        let row = WEB3_CAREERS_ROW.replace("<h2>Senior Rust Engineer</h2>", "<span></span>");
+        let layout = format!(
+            r#"<html><body><main><div><div><div><div><div><table><tbody>
+            <tr>{row}</tr><tr><td></td></tr>
+            </tbody></table></div></div></div></div></div></main></body></html>"#
+        );
+        let doc = Html::parse_document(&layout);
+        let jobs = Web3Careers::parse_jobs(WEB3_CAREERS_URL, &doc).unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].title, UNTITLED);
+        assert_eq!(jobs[0].company, "Parity");
+    }
+
+    #[test]
+    fn test_keep_untitled() {
+        let mut job = Job {
+            company: "Parity".to_string(),
+            ..Default::default()
+        };
+        assert!(!keep_untitled(&mut job.clone(), true));
+        assert!(keep_untitled(&mut job, false));
+        assert_eq!(job.title, UNTITLED);
+        assert!(!keep_untitled(&mut Job::new(), false));
+    }
+
+    #[tokio::test]
+    async fn test_cached_fetch_within_ttl() {
+        let server = TestServer::start(|_| Response::ok("<html></html>"));
+        let url = format!("{}/jobs", server.url());
+        let config = Config {
+            cache: Cache {
+                enabled: true,
+                ttl: Duration::from_secs(60),
+            },
+            ..Default::default()
+        };
+
+        let client = Client::new();
+        let first = get_body(&client, &url, &config).await.unwrap();
+        let second = get_body(&client, &url, &config).await.unwrap();
+        assert_eq!(first, second);
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(server.requests()[0].path, "/jobs");
+
+        let disabled = Config {
+            cache: Cache {
+                enabled: false,
+                ..config.cache
+            },
+            ..Default::default()
+        };
+        get_body(&client, &url, &disabled).await.unwrap();
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_error_kinds() {
+        let server = TestServer::start(|req| match req.path.as_str() {
+            "/missing" => Response::status(404),
+            "/unavailable" => Response::status(503),
+            _ => {
+                std::thread::sleep(Duration::from_millis(500));
+                Response::ok("<html></html>")
+            }
+        });
+        let client = Client::new();
+
+        for (path, code) in [("/missing", 404), ("/unavailable", 503)] {
+            let url = format!("{}{path}", server.url());
+            match fetch_body(&client, &url, &BTreeMap::new()).await {
+                Err(ErrorKind::RequestStatus(u, c)) => assert_eq!((u, c), (url, code)),
+                res => panic!("expected RequestStatus, got {res:?}"),
+            }
+        }
+
+        let client = client_with_timeout(Duration::from_millis(100));
+        let url = format!("{}/slow", server.url());
+        match fetch_body(&client, &url, &BTreeMap::new()).await {
+            Err(ErrorKind::RequestTimeout(u)) => assert_eq!(u, url),
+            res => panic!("expected RequestTimeout, got {res:?}"),
+        }
+
+        // nothing listens on port 9 (discard) locally
+        let url = "http://127.0.0.1:9/jobs";
+        match fetch_body(&client, url, &BTreeMap::new()).await {
+            Err(ErrorKind::RequestTransport(u, _)) => assert_eq!(u, url),
+            res => panic!("expected RequestTransport, got {res:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_body_with_retry() {
+        // unavailable twice, then the page is served
+        let fetches = AtomicUsize::new(0);
+        let server = TestServer::start(move |req| match req.path.as_str() {
+            "/missing" => Response::status(404),
+            _ => match fetches.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Response::status(503),
+                _ => Response::ok(EMPTY_PAGE),
+            },
+        });
+        let client = Client::new();
+        let retry = Retry {
+            retries: 2,
+            backoff: Duration::from_millis(10),
+        };
+
+        let url = format!("{}/jobs", server.url());
+        let body = fetch_body_with_retry(&client, &url, &BTreeMap::new(), &retry).await;
+        assert_eq!(body.unwrap(), EMPTY_PAGE);
+        assert_eq!(server.requests().len(), 3);
+
+        // a 404 isn't retried
+        let url = format!("{}/missing", server.url());
+        match fetch_body_with_retry(&client, &url, &BTreeMap::new(), &retry).await {
+            Err(ErrorKind::RequestStatus(_, 404)) => {}
+            res => panic!("expected a 404, got {res:?}"),
+        }
+        assert_eq!(server.requests().len(), 4);
+    }
+
+    #[test]
+    fn test_unique_sorted() {
+        let job = |title: &str, company: &str| Job {
+            title: title.to_string(),
+            company: company.to_string(),
+            ..Default::default()
+        };
+        let jobs = unique_sorted(vec![
+            job("Rust Engineer", "Parity"),
+            job("Backend Engineer", "Acme"),
+            job("Rust Engineer", "Acme"),
+            job("Backend Engineer", "Acme"),
+        ]);
+        assert_eq!(
+            jobs.iter()
+                .map(|j| (j.title.as_str(), j.company.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                ("Backend Engineer", "Acme"),
+                ("Rust Engineer", "Acme"),
+                ("Rust Engineer", "Parity")
+            ]
+        );
+    }
+
+    const CRYPTO_JOBS_LIST_PAGE: &str = r#"
+        <html><body><main><section><section><table><tbody>
+            <tr>
+                <td><div><a href="/jobs/rust-engineer-{page}">Rust Engineer {page}</a></div></td>
+                <td><a>Acme</a></td>
+                <td><span>Featured</span></td>
+                <td><span class="job-salary-text">$ 90k-140k</span></td>
+                <td><span>Remote</span></td>
+                <td><span>rust</span></td>
+                <td class="job-time-since-creation">2d</td>
+            </tr>
+        </tbody></table></section></section></main></body></html>
+    "#;
+
+    #[tokio::test]
+    async fn test_scrape_crypto_jobs_list_pages() {
+        let server = TestServer::start(|req| match req.path.as_str() {
+            "/engineering?sort=recent" => {
+                Response::ok(&CRYPTO_JOBS_LIST_PAGE.replace("{page}", "1"))
+            }
+            "/engineering?sort=recent&page=2" => {
+                Response::ok(&CRYPTO_JOBS_LIST_PAGE.replace("{page}", "2"))
+            }
+            _ => Response::ok(EMPTY_PAGE),
+        });
+        let url = Box::leak(server.url().to_string().into_boxed_str());
+
+        let jobs = CryptoJobsList::with_url(url)
+            .scrape(&Client::new())
+            .await
+            .unwrap()
+            .jobs;
+        assert_eq!(
+            jobs.iter().map(|j| j.title.as_str()).collect::<Vec<_>>(),
+            ["Rust Engineer 1", "Rust Engineer 2"]
+        );
+        assert_eq!(jobs[1].apply, format!("{url}/jobs/rust-engineer-2"));
+        assert_eq!(jobs[1].tags, ["$ 90k-140k", "Remote", "rust"]);
+        // stops at the first empty page rather than fetching every page
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_sends_site_headers() {
+        let server = TestServer::start(|_| Response::ok("<html></html>"));
+        let client = Client::new();
+        let url = format!("{}/jobs", server.url());
+
+        get_body(&client, &url, &Config::default()).await.unwrap();
+        let headers = BTreeMap::from([
+            ("Referer".to_string(), "https://example.com/".to_string()),
+            ("User-Agent".to_string(), "jobhunt-test".to_string()),
+        ]);
+        let config = Config {
+            site_headers: HashMap::from([("127.0.0.1".to_string(), headers)]),
+            ..Default::default()
+        };
+        get_body(&client, &url, &config).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests[0].header("referer"), None);
+        assert_eq!(requests[0].header("user-agent"), Some(DEFAULT_USER_AGENT));
+        assert_eq!(requests[1].header("referer"), Some("https://example.com/"));
+        assert_eq!(requests[1].header("user-agent"), Some("jobhunt-test"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_rotates_user_agents() {
+        let server = TestServer::start(|_| Response::ok("<html></html>"));
+        let client = Client::new();
+        let url = format!("{}/jobs", server.url());
+        let config = Config {
+            user_agents: vec!["jobhunt-a".to_string(), "jobhunt-b".to_string()],
+            ..Default::default()
+        };
+        for _ in 0..4 {
+            get_body(&client, &url, &config).await.unwrap();
+        }
+
+        // other tests' requests share the turns, so only which agents were sent is checked
+        for req in server.requests() {
+            assert!(matches!(
+                req.header("user-agent"),
+                Some("jobhunt-a" | "jobhunt-b")
+            ));
+        }
+        assert_eq!(next_user_agent(&["jobhunt-a".to_string()]), "jobhunt-a");
+        assert_eq!(next_user_agent(&[]), DEFAULT_USER_AGENT);
+    }
+
+    const CHALLENGE_PAGE: &str = r#"<!DOCTYPE html><html><head><title>Just a moment...</title></head>
+        <body><div id="challenge-running">Checking if the site connection is secure</div>
+        <script src="/cdn-cgi/challenge-platform/h/b/orchestrate/chl_page/v1"></script></body></html>"#;
+
+    #[tokio::test]
+    async fn test_fetch_challenged() {
+        let server = TestServer::start(|req| match req.path.as_str() {
+            "/challenge" => Response {
+                status: 403,
+                body: CHALLENGE_PAGE.to_string(),
+            },
+            _ => Response::status(403),
+        });
+        let client = Client::new();
+
+        let url = format!("{}/challenge", server.url());
+        match fetch_body(&client, &url, &BTreeMap::new()).await {
+            Err(ErrorKind::Challenged(u)) => assert_eq!(u, url),
+            res => panic!("expected Challenged, got {res:?}"),
+        }
+
+        let url = format!("{}/forbidden", server.url());
+        match fetch_body(&client, &url, &BTreeMap::new()).await {
+            Err(ErrorKind::RequestStatus(_, 403)) => {}
+            res => panic!("expected RequestStatus, got {res:?}"),
+        }
+    }
+
+    const CUSTOM_SITE_PAGE: &str = r#"
+        <html><body><ul class="jobs">
+            <li>
+                <h3>Protocol Engineer {page}</h3>
+                <span class="company">Acme</span>
+                <meta class="location" content="Remote">
+                <time datetime="2024-05-06T10:00:00Z">May 6</time>
+                <span class="salary">$100k - $150k</span>
+                <span class="tag">Rust</span><span class="tag">P2P</span>
+                <a class="apply" href="/jobs/{page}">Apply</a>
+                <span class="closes">Closes in 5 days</span>
+                <span class="applicants">Over 100 applicants</span>
+                <span class="department">Protocol Research</span>
+            </li>
+        </ul></body></html>
+    "#;
+
+    #[tokio::test]
+    async fn test_scrape_custom_site_from_config() {
+        let server = TestServer::start(|req| {
+            let page = req.path.rsplit('=').next().unwrap().to_string();
+            Response::ok(&CUSTOM_SITE_PAGE.replace("{page}", &page))
+        });
+        let sites_file = format!(
+            r#"
+            [[sites]]
+            name = "Example Board"
+            url = "{}/jobs?page={{page}}"
+            pages = 2
+
+            [sites.selectors]
+            jobs = "ul.jobs > li"
+            title = ["h2", "h3"]
+            company = ".company"
+            location = ".location"
+            date = "time"
+            remuneration = ".salary"
+            tags = ".tag"
+            apply = "a.apply"
+            deadline = ".closes"
+            applicants = ".applicants"
+            category = ".department"
+            "#,
+            server.url()
+        );
+
+        let mut sites = SitesFile::parse(&sites_file, false).unwrap().sites;
+        assert_eq!(sites.len(), 1);
+        let jobs = CustomSite::new(sites.remove(0))
+            .scrape(&Client::new())
+            .await
+            .unwrap()
+            .jobs;
+
+        assert_eq!(server.requests().len(), 2);
+        assert_eq!(jobs.len(), 2);
+        let job = &jobs[1];
+        assert_eq!(job.title, "Protocol Engineer 2");
+        assert_eq!(job.company, "Acme");
+        assert_eq!(job.location, "Remote");
+        assert_eq!(job.date_posted, "2024-05-06");
+        assert_eq!((job.rem_lower, job.rem_upper), (Some(100000), Some(150000)));
+        assert_eq!(job.tags, vec!["Rust", "P2P"]);
+        assert_eq!(job.apply, format!("{}/jobs/2", server.url()));
+        assert_eq!(job.site, format!("{}/jobs", server.url()));
+        assert_eq!(job.applicants, Some(100));
+        assert_eq!(job.category.as_deref(), Some("protocol research"));
+        assert_eq!(job.source_url, format!("{}/jobs?page=2", server.url()));
+        assert_eq!(
+            job.deadline,
+            Some(CryptoJobsList::add_duration_and_format(
+                chrono::Duration::days(5)
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scrape_common_pages() {
+        let page = |titles: &[&str]| {
+            let postings = titles
+                .iter()
+                .map(|title| {
+                    format!(
+                        r#"<div itemscope itemtype="https://schema.org/JobPosting">
+                        <h4><a><div itemprop="title">{title}</div></a></h4>
+                        <div itemprop="hiringOrganization"><a>Solana Labs</a></div></div>"#
+                    )
+                })
+                .collect::<String>();
+            format!(r#"<html><body><div id="content">{postings}</div></body></html>"#)
+        };
+        let filter = "eyJqb2JfZnVuY3Rpb25zIjpbIlNvZnR3YXJlIEVuZ2luZWVyaW5nIl19";
+        let server = TestServer::start(move |req| {
+            let path = req.path.replace(filter, "f");
+            match path.as_str() {
+                "/?filter=f" => Response::ok(&page(&["Rust Engineer", "Go Engineer"])),
+                "/?filter=f&page=2" => Response::ok(&page(&["Protocol Engineer"])),
+                _ => Response::ok(EMPTY_PAGE),
+            }
+        });
+        let url = Box::leak(server.url().to_string().into_boxed_str());
+
+        let jobs = SolanaJobs::with_url(url)
+            .scrape(&Client::new())
+            .await
+            .unwrap()
+            .jobs;
+        // more jobs than the first page lists, stopping at the first empty page
+        assert_eq!(
+            jobs.iter().map(|j| j.title.as_str()).collect::<Vec<_>>(),
+            ["Go Engineer", "Protocol Engineer", "Rust Engineer"]
+        );
+        assert_eq!(server.requests().len(), 3);
+        assert!(jobs[1].source_url.ends_with("&page=2"));
+    }
+
+    #[test]
+    fn test_common_parse_deadline() {
+        let posting = |deadline: &str| {
+            format!(
+                r#"<div itemscope itemtype="https://schema.org/JobPosting">
+                <h4><a><div itemprop="title">Rust Engineer</div></a></h4>
+                <div itemprop="hiringOrganization"><a>Solana Labs</a></div>
+                {deadline}
+                </div>"#
             )
+        };
+        let page = format!(
+            r#"<html><body><div id="content">{}{}</div></body></html>"#,
+            posting(
+                r#"<meta itemprop="validThrough" content="2024-06-30T23:59:59.000Z">
+                <meta itemprop="occupationalCategory" content="Software Engineering">"#
+            ),
+            posting("")
+        );
+        let doc = Html::parse_document(&page);
+        let jobs = CommonSite::parse_jobs(SOLANA_JOBS_URL, &doc).unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].deadline.as_deref(), Some("2024-06-30"));
+        assert_eq!(jobs[0].category.as_deref(), Some("engineering"));
+        assert_eq!(jobs[1].deadline, None);
+        assert_eq!(jobs[1].category, None);
+    }
+
+    #[test]
+    fn test_parse_sites_file_errors() {
+        let invalid_selector = r#"{"sites": [{"name": "Bad", "url": "https://example.com",
+            "selectors": {"jobs": "li", "title": "h2[["}}]}"#;
+        let err = SitesFile::parse(invalid_selector, true).unwrap_err();
+        assert!(err.contains("site \"Bad\" has an invalid title selector \"h2[[\""));
+
+        let missing_title = r#"{"sites": [{"name": "Bad", "url": "https://example.com",
+            "selectors": {"jobs": "li"}}]}"#;
+        assert!(SitesFile::parse(missing_title, true).is_err());
+
+        let invalid_header = r#"
+            [headers."cryptojobslist.com"]
+            "Bad Header" = "x"
+        "#;
+        let err = SitesFile::parse(invalid_header, false).unwrap_err();
+        assert!(err.contains("invalid header name \"Bad Header\""));
+
+        let invalid_url = r#"{"sites": [{"name": "Bad", "url": "example",
+            "selectors": {"jobs": "li", "title": "h2"}}]}"#;
+        let err = SitesFile::parse(invalid_url, true).unwrap_err();
+        assert!(err.contains("site \"Bad\" has an invalid url"));
+
+        let invalid_override = r#"
+            [selectors.web3careers]
+            title = "h2[["
+        "#;
+        let err = SitesFile::parse(invalid_override, false).unwrap_err();
+        assert!(err.contains("invalid selector override"));
+        let valid_override = r#"
+            [selectors.web3careers]
+            title = "h2.title"
+        "#;
+        let file = SitesFile::parse(valid_override, false).unwrap();
+        assert_eq!(file.selectors["web3careers"]["title"], ["h2.title"]);
+    }
+
+    fn job_assertions(jobs: Vec<Job>) {
+        assert!(!jobs.is_empty());
+        assert!(jobs.windows(2).all(|w| job_order(&w[0], &w[1]).is_le()));
+        for job in &jobs {
+            for field in CHECKED_FIELDS {
+                // lenient titles keep untitled jobs, which the doctor counts against the site
+                let untitled = field == "title" && job.title == UNTITLED;
+                assert!(
+                    valid_field(field, job) || untitled,
+                    "invalid {field}: {job:?}"
+                );
+            }
         }
     }
 }
@@ -0,0 +1,127 @@
+//! The HTML selectors of the built-in jobsites. The defaults are embedded from `selectors.toml`,
+//! and any field can be overridden (e.g. from a sites file) without redefining the others.
+
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+use scraper::Selector;
+
+use crate::config;
+
+/// The selectors of a site's fields, keyed by field name. Each field lists its selectors in order
+/// of preference.
+pub type SiteSelectors = BTreeMap<String, Vec<String>>;
+
+/// The selectors of the solana, substrate and near jobsites, which share a layout.
+pub const COMMON: &str = "common";
+pub const WEB3_CAREERS: &str = "web3careers";
+pub const CRYPTO_JOBS_LIST: &str = "cryptojobslist";
+
+static DEFAULTS: LazyLock<BTreeMap<String, SiteSelectors>> = LazyLock::new(|| {
+    toml::from_str(include_str!("selectors.toml")).expect("the embedded selectors are valid TOML")
+});
+
+/// Returns the default selectors of each built-in site, keyed by site (e.g. "web3careers").
+pub fn defaults() -> &'static BTreeMap<String, SiteSelectors> {
+    &DEFAULTS
+}
+
+/// Merges overrides over the defaults field by field, so overriding a site's title selectors
+/// keeps its other defaults. Every overridden site and field must have a default, and every
+/// merged selector must parse.
+pub fn merge(
+    defaults: &BTreeMap<String, SiteSelectors>,
+    overrides: &BTreeMap<String, SiteSelectors>,
+) -> Result<BTreeMap<String, SiteSelectors>, String> {
+    let mut merged = defaults.clone();
+    for (site, fields) in overrides {
+        let Some(selectors) = merged.get_mut(site) else {
+            Err(format!(
+                "there are no built-in selectors for \"{site}\". Try one of: {}",
+                defaults.keys().cloned().collect::<Vec<String>>().join(", ")
+            ))?
+        };
+        for (field, field_selectors) in fields {
+            if !selectors.contains_key(field) {
+                Err(format!(
+                    "\"{site}\" has no \"{field}\" selectors to override. Try one of: {}",
+                    selectors
+                        .keys()
+                        .cloned()
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ))?;
+            }
+            if field_selectors.is_empty() {
+                Err(format!(
+                    "\"{site}\" must have at least one {field} selector"
+                ))?;
+            }
+            selectors.insert(field.clone(), field_selectors.clone());
+        }
+    }
+    for (site, fields) in &merged {
+        for (field, selectors) in fields {
+            for selector in selectors {
+                Selector::parse(selector).map_err(|e| {
+                    format!("\"{site}\" has an invalid {field} selector \"{selector}\": {e}")
+                })?;
+            }
+        }
+    }
+    Ok(merged)
+}
+
+/// Returns the selectors of a built-in site, with the overrides from the session settings merged
+/// over the defaults.
+pub fn for_site(site: &str) -> SiteSelectors {
+    let mut selectors = DEFAULTS.get(site).cloned().unwrap_or_default();
+    if let Some(overrides) = config::get().selector_overrides.get(site) {
+        selectors.extend(overrides.clone());
+    }
+    selectors
+}
+
+/// Returns the selectors of a field, or none if the site has no selectors for it.
+pub fn field<'a>(selectors: &'a SiteSelectors, field: &str) -> &'a [String] {
+    selectors.get(field).map(Vec::as_slice).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{defaults, field, merge, COMMON, CRYPTO_JOBS_LIST, WEB3_CAREERS};
+
+    #[test]
+    fn test_merge_partial_override() {
+        assert!(defaults().contains_key(WEB3_CAREERS));
+        assert!(defaults().contains_key(CRYPTO_JOBS_LIST));
+        assert!(!field(&defaults()[COMMON], "apply").is_empty());
+
+        let overrides = BTreeMap::from([(
+            WEB3_CAREERS.to_string(),
+            BTreeMap::from([("title".to_string(), vec!["h2.job-title".to_string()])]),
+        )]);
+        let merged = merge(defaults(), &overrides).unwrap();
+        assert_eq!(field(&merged[WEB3_CAREERS], "title"), ["h2.job-title"]);
+        assert_eq!(
+            field(&merged[WEB3_CAREERS], "company"),
+            field(&defaults()[WEB3_CAREERS], "company")
+        );
+        assert_eq!(merged[CRYPTO_JOBS_LIST], defaults()[CRYPTO_JOBS_LIST]);
+
+        let override_with = |site: &str, field: &str, selector: &str| {
+            BTreeMap::from([(
+                site.to_string(),
+                BTreeMap::from([(field.to_string(), vec![selector.to_string()])]),
+            )])
+        };
+        let err = merge(defaults(), &override_with(WEB3_CAREERS, "title", "h2[[")).unwrap_err();
+        assert!(err.contains("\"web3careers\" has an invalid title selector \"h2[[\""));
+        let err = merge(defaults(), &override_with(WEB3_CAREERS, "salary", "p")).unwrap_err();
+        assert!(err.contains("\"web3careers\" has no \"salary\" selectors to override"));
+        let err = merge(defaults(), &override_with("indeed", "title", "h2")).unwrap_err();
+        assert!(err.contains("there are no built-in selectors for \"indeed\""));
+    }
+}
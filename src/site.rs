@@ -1,6 +1,16 @@
-use chrono::{Duration, Local};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use chrono::{Duration, Local, NaiveDate};
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::Url;
+use scraper::Selector;
+use serde::{Deserialize, Deserializer};
 
 use crate::repository::Job;
+use crate::selectors::{self, SiteSelectors};
+use crate::ErrorKind;
 
 /// Job site URLs used for scraping.
 pub const WEB3_CAREERS_URL: &str = "https://web3.career";
@@ -8,6 +18,17 @@ pub const CRYPTO_JOBS_LIST_URL: &str = "https://cryptojobslist.com";
 pub const SOLANA_JOBS_URL: &str = "https://jobs.solana.com/jobs";
 pub const SUBSTRATE_JOBS_URL: &str = "https://careers.substrate.io/jobs";
 pub const NEAR_JOBS_URL: &str = "https://careers.near.org/jobs";
+pub const GREENHOUSE_URL: &str = "https://boards-api.greenhouse.io/v1/boards";
+
+/// The filter query param of the built-in common jobsites - base64 JSON selecting the "Software
+/// Engineering" job function.
+pub const COMMON_FILTER: &str = "eyJqb2JfZnVuY3Rpb25zIjpbIlNvZnR3YXJlIEVuZ2luZWVyaW5nIl19";
+
+/// The default maximum number of pages scraped from each web3careers listing.
+pub const WEB3_CAREERS_MAX_PAGES: u8 = 5;
+
+/// The delay between requests for consecutive web3careers pages, to stay under its rate limit.
+pub const WEB3_CAREERS_REQUEST_DELAY: std::time::Duration = std::time::Duration::from_millis(750);
 
 /// All jobsite structs must implement the Site trait and conform to the structure:
 /// ```
@@ -43,21 +64,124 @@ pub trait DateFormatter {
     fn now_and_format() -> String {
         Local::now().format("%Y-%m-%d").to_string()
     }
+
+    /// Returns a formatted ("%Y-%m-%d") version of now plus a time duration.
+    fn add_duration_and_format(duration: Duration) -> String {
+        Local::now()
+            .checked_add_signed(duration)
+            .unwrap_or(Local::now())
+            .format("%Y-%m-%d")
+            .to_string()
+    }
+
+    /// Formats a date from a free-form elapsed time, e.g. "12d", "3 days ago", "about an hour ago",
+    /// "yesterday" or "just now". The first number and the unit after it are used, and anything
+    /// that can't be understood is taken to be now.
+    fn format_elapsed_from(time_elapsed: &str) -> String {
+        let raw = time_elapsed.trim().to_lowercase();
+        // "an hour ago" is one hour ago
+        let words = raw
+            .split_whitespace()
+            .map(|word| match word {
+                "a" | "an" => "1",
+                word => word,
+            })
+            .collect::<Vec<&str>>();
+        if words.contains(&"yesterday") {
+            return Self::sub_duration_and_format(Duration::days(1));
+        }
+        let text = words.join(" ");
+        let Some(start) = text.find(|c: char| c.is_ascii_digit()) else {
+            // e.g. "today" or "just now"
+            return Self::now_and_format();
+        };
+        let rest = &text[start..];
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let Ok(n) = rest[..end].parse::<i64>() else {
+            return Self::now_and_format();
+        };
+        let unit = rest[end..]
+            .trim_start()
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .next()
+            .unwrap_or_default();
+        let duration = match unit.trim_end_matches('s') {
+            "h" | "hr" | "hour" => Duration::hours(n),
+            "d" | "day" => Duration::days(n),
+            "w" | "wk" | "week" => Duration::weeks(n),
+            "m" | "mo" | "month" => Duration::days(n * 30),
+            "y" | "yr" | "year" => Duration::days(n * 365),
+            _ => Duration::zero(),
+        };
+        Self::sub_duration_and_format(duration)
+    }
+
+    /// Formats an application deadline, either relative (e.g. "Closes in 5 days", "tomorrow") or
+    /// absolute (e.g. "2024-06-30", "30 June 2024", "Jun 30, 2024"). Returns None if the deadline
+    /// can't be understood.
+    fn format_deadline_from(deadline_raw: &str) -> Option<String> {
+        let raw = deadline_raw.trim().to_lowercase();
+        let raw = ["closes", "closing", "apply by", "deadline"]
+            .iter()
+            .fold(raw.as_str(), |raw, prefix| {
+                raw.strip_prefix(prefix).unwrap_or(raw)
+            })
+            .trim_start_matches([':', ' ']);
+
+        match raw {
+            "today" => return Some(Self::now_and_format()),
+            "tomorrow" => return Some(Self::add_duration_and_format(Duration::days(1))),
+            _ => {}
+        }
+        if let Some(relative) = raw.strip_prefix("in ") {
+            let mut parts = relative.split_whitespace();
+            let n = parts.next()?.parse::<i64>().ok()?;
+            let duration = match parts.next()?.trim_end_matches('s') {
+                "hour" | "minute" => Duration::zero(),
+                "day" => Duration::days(n),
+                "week" => Duration::weeks(n),
+                "month" => Duration::days(n * 30),
+                _ => return None,
+            };
+            return Some(Self::add_duration_and_format(duration));
+        }
+        if let Some(date) = raw
+            .get(..10)
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        {
+            return Some(date.format("%Y-%m-%d").to_string());
+        }
+        [
+            "%d %B %Y",
+            "%d %b %Y",
+            "%B %d, %Y",
+            "%b %d, %Y",
+            "%B %d %Y",
+            "%b %d %Y",
+        ]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(raw, format).ok())
+        .map(|date| date.format("%Y-%m-%d").to_string())
+    }
 }
 
 /// Generates a jobsite struct and implements the Site trait.
 macro_rules! generate_jobsite_struct_and_impl {
-    ($t:ident, $url:ident) => {
+    ($t:ident, $url:ident $(, $field:ident: $ty:ty = $default:expr)*) => {
         #[derive(Default)]
         pub struct $t {
             url: &'static str,
             pub jobs: Vec<Job>,
+            $($field: $ty,)*
         }
 
         impl Site for $t {
             fn new() -> Self {
                 Self {
                     url: $url,
+                    $($field: $default,)*
                     ..Default::default()
                 }
             }
@@ -66,16 +190,44 @@ macro_rules! generate_jobsite_struct_and_impl {
                 self.url
             }
         }
+
+        #[cfg(test)]
+        impl $t {
+            /// Points the jobsite at another URL, e.g. a local test server.
+            pub fn with_url(url: &'static str) -> Self {
+                Self {
+                    url,
+                    $($field: $default,)*
+                    ..Default::default()
+                }
+            }
+        }
     };
 }
 
-generate_jobsite_struct_and_impl!(Web3Careers, WEB3_CAREERS_URL);
+generate_jobsite_struct_and_impl!(
+    Web3Careers,
+    WEB3_CAREERS_URL,
+    max_pages: u8 = WEB3_CAREERS_MAX_PAGES
+);
 generate_jobsite_struct_and_impl!(CryptoJobsList, CRYPTO_JOBS_LIST_URL);
 generate_jobsite_struct_and_impl!(SolanaJobs, SOLANA_JOBS_URL);
 generate_jobsite_struct_and_impl!(SubstrateJobs, SUBSTRATE_JOBS_URL);
 generate_jobsite_struct_and_impl!(NearJobs, NEAR_JOBS_URL);
+generate_jobsite_struct_and_impl!(GreenhouseJobs, GREENHOUSE_URL, board: String = String::new());
 
 impl Web3Careers {
+    /// Sets the maximum number of pages scraped from each listing.
+    pub fn with_max_pages(mut self, max_pages: u8) -> Self {
+        self.max_pages = max_pages;
+        self
+    }
+
+    /// Returns the maximum number of pages scraped from each listing.
+    pub fn max_pages(&self) -> u8 {
+        self.max_pages
+    }
+
     /// Formats an onclick function (as a &str) into a URL path string.
     pub fn format_apply_url_from(url: &str, a: &str) -> String {
         let v = a.split(' ').collect::<Vec<&str>>();
@@ -91,72 +243,549 @@ impl Web3Careers {
     }
 
     /// Returns upper and lower bounds for remuneration.
-    pub fn get_upper_lower(r: &str) -> (u16, u16) {
-        let rem_v = r.split('-').map(|s| s.trim()).collect::<Vec<&str>>();
-        let lower = rem_v[0]
-            .replace(['$', 'k'], "")
-            .trim()
-            .parse::<u16>()
-            .unwrap();
-        let upper = rem_v[1]
-            .replace(['$', 'k'], "")
-            .trim()
-            .parse::<u16>()
-            .unwrap();
-        (lower, upper)
+    pub fn get_upper_lower(r: &str) -> (Option<i64>, Option<i64>) {
+        rem_bounds(r)
+    }
+}
+
+impl GreenhouseJobs {
+    /// Sets the token of the Greenhouse board to scrape, e.g. "chainlinklabs" for
+    /// https://boards.greenhouse.io/chainlinklabs.
+    pub fn with_board(mut self, board: &str) -> Self {
+        self.board = board.to_string();
+        self
+    }
+
+    /// Returns the token of the board.
+    pub fn board(&self) -> &str {
+        &self.board
     }
+
+    /// Returns the URL of the board's jobs endpoint.
+    pub fn board_url(&self) -> String {
+        format!("{}/{}/jobs", self.url, self.board)
+    }
+}
+
+/// Parses an applicant (or view) count such as "57 applicants", "Over 100 applicants", "100+" or
+/// "1,204 views". Open-ended counts are capped at their stated figure. Returns None if there's no
+/// number.
+pub fn applicants_from(text: &str) -> Option<u32> {
+    let digits = text
+        .chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit() || *c == ',')
+        .filter(char::is_ascii_digit)
+        .collect::<String>();
+    digits.parse::<u32>().ok()
+}
+
+/// Strips a leading "up to" (in any case) from a remuneration string.
+fn strip_up_to(r: &str) -> Option<&str> {
+    r.get(..6)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("up to "))
+        .map(|_| r[6..].trim_start())
+}
+
+/// Returns the lower and upper bounds of a formatted remuneration, e.g. (90000, 140000) for
+/// "$90k - $140k". A single value (e.g. "$120k" or "Up to $150k") is both bounds. Bounds that
+/// can't be parsed are None.
+fn rem_bounds(r: &str) -> (Option<i64>, Option<i64>) {
+    let r = strip_up_to(r.trim()).unwrap_or(r);
+    // the number of thousands between any currency and the "k"
+    let bound = |b: &str| {
+        b.trim_matches(|c: char| !c.is_ascii_digit())
+            .parse::<i64>()
+            .ok()
+            .filter(|n| *n > 0)
+            .and_then(|n| n.checked_mul(1000))
+    };
+    match r.split('-').collect::<Vec<&str>>()[..] {
+        [lower, upper] => (bound(lower), bound(upper)),
+        [value] => (bound(value), bound(value)),
+        _ => (None, None),
+    }
+}
+
+/// Currency symbols and codes found in remuneration strings, with the ISO code of each. Stablecoins
+/// come first, so "USDC" isn't taken for "USD".
+const CURRENCIES: &[(&str, &str)] = &[
+    ("USDC", "USDC"),
+    ("USDT", "USDT"),
+    ("$", "USD"),
+    ("USD", "USD"),
+    ("€", "EUR"),
+    ("EUR", "EUR"),
+    ("£", "GBP"),
+    ("GBP", "GBP"),
+    ("CHF", "CHF"),
+];
+
+/// Returns the symbol a remuneration currency token is shown with, e.g. "£" for "GBP". Tokens
+/// without a symbol (e.g. "CHF" or "USDC") are kept as they are, followed by a space.
+fn currency_symbol(token: &str) -> String {
+    match token.to_uppercase().as_str() {
+        "" => String::new(),
+        "$" | "USD" => "$".to_string(),
+        "€" | "EUR" => "€".to_string(),
+        "£" | "GBP" => "£".to_string(),
+        _ => format!("{token} "),
+    }
+}
+
+/// Returns the ISO code of the currency of a remuneration string (e.g. "USD" for "$90k - $140k"),
+/// or None if there's no remuneration or its currency isn't recognised.
+pub fn currency_from(remuneration: &str) -> Option<String> {
+    let remuneration = remuneration.trim().to_uppercase();
+    CURRENCIES
+        .iter()
+        .find(|(marker, _)| remuneration.contains(marker))
+        .map(|(_, code)| code.to_string())
 }
 
 impl CryptoJobsList {
+    /// Formats a remuneration range (e.g. "$ 90k-140k" as "$90k - $140k") or a single value (e.g.
+    /// "EUR 95k" as "€95k", or "up to $150k" as "Up to $150k"). The currency before the first
+    /// number is shown as its symbol where it has one (e.g. "GBP" as "£"), and as it is otherwise
+    /// (e.g. "USDC 90k" or "CHF 120k").
     pub fn format_remuneration_from(r: &str) -> String {
-        if r.starts_with("EUR") {
-            let r = r.replace("EUR", "");
-            let rem_v = r.split('-').map(|s| s.trim()).collect::<Vec<&str>>();
-            match rem_v.len() {
-                2 => format!("€{} - €{}", rem_v[0], rem_v[1]),
-                _ => "".to_string(),
-            }
-        } else {
-            let r = r.replace('$', "");
-            let rem_v = r.split('-').map(|s| s.trim()).collect::<Vec<&str>>();
-            match rem_v.len() {
-                2 => format!("${} - ${}", rem_v[0], rem_v[1]),
-                _ => "".to_string(),
-            }
+        let r = r.trim();
+        let (up_to, r) = match strip_up_to(r) {
+            Some(r) => (true, r),
+            None => (false, r),
+        };
+        let start = r.find(|c: char| c.is_ascii_digit()).unwrap_or(r.len());
+        let symbol = currency_symbol(r[..start].trim());
+        // a repeated currency, e.g. the second "$" in "$90k-$140k", is dropped
+        let rem_v = r[start..]
+            .split('-')
+            .map(|s| s.trim().trim_start_matches(|c: char| !c.is_ascii_digit()))
+            .collect::<Vec<&str>>();
+        match rem_v[..] {
+            [lower, upper] => format!("{symbol}{lower} - {symbol}{upper}"),
+            [value] if !value.is_empty() && up_to => format!("Up to {symbol}{value}"),
+            [value] if !value.is_empty() => format!("{symbol}{value}"),
+            _ => "".to_string(),
         }
     }
 
     /// Returns upper and lower bounds for remuneration.
-    pub fn get_upper_lower(r: &str) -> (u16, u16) {
-        let rem_v = r.split('-').map(|s| s.trim()).collect::<Vec<&str>>();
-        let lower = rem_v[0]
-            .replace(['$', '€', 'k'], "")
-            .trim()
-            .parse::<u16>()
-            .unwrap();
-        let upper = rem_v[1]
-            .replace(['$', '€', 'k'], "")
-            .trim()
-            .parse::<u16>()
-            .unwrap();
-        (lower, upper)
+    pub fn get_upper_lower(r: &str) -> (Option<i64>, Option<i64>) {
+        rem_bounds(r)
     }
 }
 
 impl DateFormatter for CryptoJobsList {
     fn format_date_from(time_elapsed: &str) -> String {
-        let v = time_elapsed.chars().collect::<Vec<char>>();
-        match v.len() {
-            len if len >= 2 => {
-                let d: i64 = v[0] as i64 - 0x30;
-                match v[1] {
-                    'd' => Self::sub_duration_and_format(Duration::days(d)),
-                    'w' => Self::sub_duration_and_format(Duration::weeks(d)),
-                    'm' => Self::sub_duration_and_format(Duration::days(d * 30)),
-                    _ => Self::now_and_format(),
-                }
+        Self::format_elapsed_from(time_elapsed)
+    }
+}
+
+/// A jobsite defined at runtime (e.g. in a sites file) rather than compiled in.
+#[derive(Clone, Debug, Deserialize)]
+pub struct SiteConfig {
+    /// A name for the site, used in messages.
+    pub name: String,
+    /// The URL to scrape. If it contains "{page}", pages 1 to `pages` are scraped.
+    pub url: String,
+    #[serde(default = "default_pages")]
+    pub pages: u8,
+    /// The number of jobs to ask for on each page, for sites that take a page size parameter, so
+    /// fewer, larger pages are requested. When unset, the site's own page size is used.
+    #[serde(default)]
+    pub page_size: Option<u32>,
+    /// The query parameter the page size is sent in, e.g. "per_page" (the default) or "limit".
+    #[serde(default = "default_page_size_param")]
+    pub page_size_param: String,
+    /// Request headers sent to the site, e.g. "Accept" or "Referer". These override the defaults.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub selectors: SelectorConfig,
+}
+
+/// HTML selectors for each Job field of a runtime jobsite. Each field may be a single selector or
+/// an ordered list of fallbacks. Field selectors are matched within each element matched by `jobs`.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct SelectorConfig {
+    #[serde(deserialize_with = "one_or_many")]
+    pub jobs: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub title: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub company: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub location: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub date: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub remuneration: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub tags: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub apply: Vec<String>,
+    /// The company logo image. Its `data-src` or `src` is resolved against the page URL.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub logo: Vec<String>,
+    /// The application deadline, from a `datetime` or `content` attribute or the element's text.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub deadline: Vec<String>,
+    /// The number of applicants (or views), e.g. "Over 100 applicants".
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub applicants: Vec<String>,
+    /// The category or department, from a `content` attribute or the element's text.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub category: Vec<String>,
+}
+
+fn default_pages() -> u8 {
+    1
+}
+
+fn default_page_size_param() -> String {
+    "per_page".to_string()
+}
+
+/// Either a single string or a list of strings.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl From<OneOrMany> for Vec<String> {
+    fn from(value: OneOrMany) -> Self {
+        match value {
+            OneOrMany::One(s) => vec![s],
+            OneOrMany::Many(v) => v,
+        }
+    }
+}
+
+/// Deserializes either a single string or a list of strings.
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(OneOrMany::deserialize(deserializer)?.into())
+}
+
+/// Deserializes selector overrides keyed by site and then field, where each field may be a single
+/// selector or a list.
+fn selector_overrides<'de, D>(deserializer: D) -> Result<BTreeMap<String, SiteSelectors>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let overrides = BTreeMap::<String, BTreeMap<String, OneOrMany>>::deserialize(deserializer)?;
+    Ok(overrides
+        .into_iter()
+        .map(|(site, fields)| {
+            let fields = fields
+                .into_iter()
+                .map(|(field, selectors)| (field, selectors.into()))
+                .collect();
+            (site, fields)
+        })
+        .collect())
+}
+
+/// The name of the site group for hosts that aren't listed in any other group.
+pub const DEFAULT_GROUP: &str = "default";
+
+/// Limits on the requests made to a group of hosts. Each group is limited separately, so a host
+/// that has to be scraped slowly doesn't hold up the others.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct SiteGroup {
+    /// The hosts in the group, without "www.". Ignored for the default group, which has every
+    /// host not listed in another group.
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    /// The maximum number of requests to the group's hosts in flight at once.
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// The minimum time between the starts of requests to the group's hosts, in milliseconds.
+    #[serde(default)]
+    pub delay_ms: u64,
+}
+
+impl Default for SiteGroup {
+    fn default() -> Self {
+        Self {
+            hosts: Vec::new(),
+            concurrency: default_concurrency(),
+            delay_ms: 0,
+        }
+    }
+}
+
+fn default_concurrency() -> usize {
+    2
+}
+
+/// A sites file lists runtime jobsites as `[[sites]]` tables (TOML) or a `sites` array (JSON). It
+/// can also set request headers for any site, including the built-in ones, in a `headers` table
+/// keyed by host, request limits for groups of hosts in a `groups` table keyed by group name, and
+/// selectors overriding the built-in sites' defaults in a `selectors` table keyed by site.
+#[derive(Debug, Default, Deserialize)]
+pub struct SitesFile {
+    #[serde(default)]
+    pub sites: Vec<SiteConfig>,
+    #[serde(default)]
+    pub headers: HashMap<String, BTreeMap<String, String>>,
+    #[serde(default)]
+    pub groups: BTreeMap<String, SiteGroup>,
+    #[serde(default, deserialize_with = "selector_overrides")]
+    pub selectors: BTreeMap<String, SiteSelectors>,
+    /// Common jobsites to scrape in place of the built-in ones (solana, substrate and near).
+    #[serde(default)]
+    pub common_boards: Vec<CommonBoard>,
+}
+
+impl SitesFile {
+    /// Loads and validates a sites file. Files ending in ".json" are read as JSON, anything else as
+    /// TOML.
+    pub fn load(path: &Path) -> Result<Self, ErrorKind> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ErrorKind::SitesFile(format!("{}: {e}", path.display())))?;
+        let json = path.extension().is_some_and(|ext| ext == "json");
+        Self::parse(&contents, json)
+            .map_err(|e| ErrorKind::SitesFile(format!("{}: {e}", path.display())))
+    }
+
+    /// Parses and validates the contents of a sites file.
+    pub fn parse(contents: &str, json: bool) -> Result<Self, String> {
+        let file: Self = if json {
+            serde_json::from_str(contents).map_err(|e| e.to_string())?
+        } else {
+            toml::from_str(contents).map_err(|e| e.to_string())?
+        };
+        for site in &file.sites {
+            site.validate()?;
+        }
+        for (host, headers) in &file.headers {
+            validate_headers(host, headers)?;
+        }
+        for board in &file.common_boards {
+            Url::parse(&board.url)
+                .map_err(|e| format!("common board \"{}\" has an invalid url: {e}", board.name))?;
+        }
+        selectors::merge(selectors::defaults(), &file.selectors)
+            .map_err(|e| format!("invalid selector override: {e}"))?;
+        for (name, group) in &file.groups {
+            if group.concurrency == 0 {
+                Err(format!(
+                    "group \"{name}\" must allow at least 1 concurrent request"
+                ))?;
+            }
+        }
+        Ok(file)
+    }
+
+    /// Returns the request headers for each site, keyed by host - those in the `headers` table
+    /// and those of each runtime jobsite.
+    pub fn site_headers(&self) -> HashMap<String, BTreeMap<String, String>> {
+        let mut site_headers = self
+            .headers
+            .iter()
+            .map(|(host, headers)| (host.to_lowercase(), headers.clone()))
+            .collect::<HashMap<_, _>>();
+        for site in &self.sites {
+            if let Some(host) = site_host(&site.url.replace("{page}", "1")) {
+                site_headers
+                    .entry(host)
+                    .or_default()
+                    .extend(site.headers.clone());
+            }
+        }
+        site_headers
+    }
+}
+
+/// Returns the host of a URL without any "www." prefix, e.g. "cryptojobslist.com".
+pub fn site_host(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?.to_lowercase();
+    Some(host.strip_prefix("www.").unwrap_or(&host).to_string())
+}
+
+/// Checks that each header name and value is valid in a request.
+fn validate_headers(site: &str, headers: &BTreeMap<String, String>) -> Result<(), String> {
+    for (name, value) in headers {
+        HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("site \"{site}\" has an invalid header name \"{name}\""))?;
+        HeaderValue::from_str(value)
+            .map_err(|_| format!("site \"{site}\" has an invalid value for header \"{name}\""))?;
+    }
+    Ok(())
+}
+
+impl SiteConfig {
+    /// Checks the URL is valid and that every selector parses.
+    fn validate(&self) -> Result<(), String> {
+        Url::parse(&self.url.replace("{page}", "1"))
+            .map_err(|e| format!("site \"{}\" has an invalid url: {e}", self.name))?;
+        validate_headers(&self.name, &self.headers)?;
+        if self.pages == 0 {
+            Err(format!(
+                "site \"{}\" must scrape at least 1 page",
+                self.name
+            ))?;
+        }
+        if self.page_size == Some(0) {
+            Err(format!(
+                "site \"{}\" must ask for at least 1 job per page",
+                self.name
+            ))?;
+        }
+        if self.page_size.is_some() && self.page_size_param.trim().is_empty() {
+            Err(format!(
+                "site \"{}\" has a page size but no page_size_param to send it in",
+                self.name
+            ))?;
+        }
+        let s = &self.selectors;
+        for (field, selectors) in [
+            ("jobs", &s.jobs),
+            ("title", &s.title),
+            ("company", &s.company),
+            ("location", &s.location),
+            ("date", &s.date),
+            ("remuneration", &s.remuneration),
+            ("tags", &s.tags),
+            ("apply", &s.apply),
+            ("logo", &s.logo),
+            ("deadline", &s.deadline),
+            ("applicants", &s.applicants),
+            ("category", &s.category),
+        ] {
+            if selectors.is_empty() && (field == "jobs" || field == "title") {
+                Err(format!(
+                    "site \"{}\" must have at least one {field} selector",
+                    self.name
+                ))?;
+            }
+            for selector in selectors {
+                Selector::parse(selector).map_err(|e| {
+                    format!(
+                        "site \"{}\" has an invalid {field} selector \"{selector}\": {e}",
+                        self.name
+                    )
+                })?;
             }
-            _ => Self::now_and_format(),
+        }
+        Ok(())
+    }
+
+    /// Returns the URL of each page to scrape, with the page size parameter if there's one.
+    pub fn page_urls(&self) -> Vec<String> {
+        let urls = match self.url.contains("{page}") {
+            true => (1..=self.pages)
+                .map(|i| self.url.replace("{page}", &i.to_string()))
+                .collect(),
+            false => vec![self.url.clone()],
+        };
+        match self.page_size {
+            Some(page_size) => urls
+                .into_iter()
+                .map(|url| self.with_page_size(url, page_size))
+                .collect(),
+            None => urls,
+        }
+    }
+
+    /// Sets the page size parameter of a URL, replacing any already in it.
+    fn with_page_size(&self, url: String, page_size: u32) -> String {
+        let Ok(mut parsed) = Url::parse(&url) else {
+            return url;
+        };
+        let pairs = parsed
+            .query_pairs()
+            .filter(|(name, _)| *name != self.page_size_param.as_str())
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<Vec<_>>();
+        parsed
+            .query_pairs_mut()
+            .clear()
+            .extend_pairs(pairs)
+            .append_pair(&self.page_size_param, &page_size.to_string());
+        parsed.to_string()
+    }
+
+    /// Returns the URL recorded as the site of each Job - the scraped URL without its query.
+    pub fn site_url(&self) -> String {
+        let url = self.url.replace("{page}", "1");
+        url.split('?').next().unwrap_or(&url).to_string()
+    }
+}
+
+/// A common (Ashby-style) jobsite, e.g. a `[[common_boards]]` entry in a sites file. These boards
+/// share their markup, so only the URL and filter differ between them.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CommonBoard {
+    /// A name for the board, used in messages.
+    pub name: String,
+    /// The URL of the board's jobs listing, e.g. "https://jobs.solana.com/jobs".
+    pub url: String,
+    /// The filter query param, e.g. base64 JSON selecting job functions. None is sent if empty.
+    #[serde(default)]
+    pub filter: String,
+}
+
+impl CommonBoard {
+    pub fn new(name: &str, url: &str, filter: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            url: url.to_string(),
+            filter: filter.to_string(),
+        }
+    }
+
+    /// Returns the URL of a page of the board's listing.
+    pub fn page_url(&self, page: u32) -> String {
+        match (self.filter.is_empty(), page) {
+            (true, 1) => self.url.clone(),
+            (true, page) => format!("{}?page={page}", self.url),
+            (false, 1) => format!("{}?filter={}", self.url, self.filter),
+            (false, page) => format!("{}?filter={}&page={page}", self.url, self.filter),
+        }
+    }
+}
+
+/// Returns the built-in common jobsites, scraped unless a sites file lists others.
+pub fn default_common_boards() -> Vec<CommonBoard> {
+    vec![
+        CommonBoard::new("solana", SOLANA_JOBS_URL, COMMON_FILTER),
+        CommonBoard::new("substrate", SUBSTRATE_JOBS_URL, COMMON_FILTER),
+        CommonBoard::new("near", NEAR_JOBS_URL, COMMON_FILTER),
+    ]
+}
+
+/// A jobsite scraped like the common jobsites, from a CommonBoard.
+pub struct CommonSite {
+    pub board: CommonBoard,
+    pub jobs: Vec<Job>,
+}
+
+impl CommonSite {
+    pub fn new(board: CommonBoard) -> Self {
+        Self {
+            board,
+            jobs: Vec::new(),
+        }
+    }
+}
+
+/// A jobsite defined by a SiteConfig.
+pub struct CustomSite {
+    pub config: SiteConfig,
+    pub jobs: Vec<Job>,
+}
+
+impl CustomSite {
+    pub fn new(config: SiteConfig) -> Self {
+        Self {
+            config,
+            jobs: Vec::new(),
         }
     }
 }
@@ -175,12 +804,103 @@ pub trait Common {
 impl Common for SolanaJobs {}
 impl Common for SubstrateJobs {}
 impl Common for NearJobs {}
+impl Common for CommonSite {}
 
 #[cfg(test)]
 mod tests {
     use chrono::Duration;
 
-    use crate::site::{Common, CryptoJobsList, DateFormatter, SolanaJobs, Web3Careers};
+    use crate::site::{
+        applicants_from, currency_from, default_common_boards, Common, CryptoJobsList,
+        DateFormatter, SitesFile, SolanaJobs, Web3Careers,
+    };
+
+    #[test]
+    fn test_page_urls_page_size() {
+        let sites_file = r#"
+            [[sites]]
+            name = "Example Board"
+            url = "https://example.com/jobs?sort=recent&page={page}&limit=20"
+            pages = 2
+            page_size = 100
+            page_size_param = "limit"
+            selectors = { jobs = "li", title = "h2" }
+
+            [[sites]]
+            name = "Other Board"
+            url = "https://other.com/jobs"
+            selectors = { jobs = "li", title = "h2" }
+        "#;
+        let sites = SitesFile::parse(sites_file, false).unwrap().sites;
+        assert_eq!(
+            sites[0].page_urls(),
+            [
+                "https://example.com/jobs?sort=recent&page=1&limit=100",
+                "https://example.com/jobs?sort=recent&page=2&limit=100"
+            ]
+        );
+        assert_eq!(sites[1].page_urls(), ["https://other.com/jobs"]);
+
+        let zero = r#"{"sites": [{"name": "Bad", "url": "https://example.com", "page_size": 0,
+            "selectors": {"jobs": "li", "title": "h2"}}]}"#;
+        let err = SitesFile::parse(zero, true).unwrap_err();
+        assert!(err.contains("site \"Bad\" must ask for at least 1 job per page"));
+    }
+
+    #[test]
+    fn test_common_boards() {
+        let sites_file = r#"
+            [[common_boards]]
+            name = "aptos"
+            url = "https://jobs.aptos.dev/jobs"
+            filter = "abc"
+
+            [[common_boards]]
+            name = "sui"
+            url = "https://jobs.sui.io/jobs"
+        "#;
+        let boards = SitesFile::parse(sites_file, false).unwrap().common_boards;
+        assert_eq!(
+            boards[0].page_url(1),
+            "https://jobs.aptos.dev/jobs?filter=abc"
+        );
+        assert_eq!(
+            boards[0].page_url(2),
+            "https://jobs.aptos.dev/jobs?filter=abc&page=2"
+        );
+        assert_eq!(boards[1].page_url(1), "https://jobs.sui.io/jobs");
+        assert_eq!(boards[1].page_url(3), "https://jobs.sui.io/jobs?page=3");
+        assert_eq!(default_common_boards().len(), 3);
+
+        let bad = r#"{"common_boards": [{"name": "Bad", "url": "jobs"}]}"#;
+        let err = SitesFile::parse(bad, true).unwrap_err();
+        assert!(err.contains("common board \"Bad\" has an invalid url"));
+    }
+
+    #[test]
+    fn test_currency_from() {
+        assert_eq!(currency_from("$90k - $140k").as_deref(), Some("USD"));
+        assert_eq!(currency_from("€90k - €140k").as_deref(), Some("EUR"));
+        assert_eq!(currency_from("£60k - £80k").as_deref(), Some("GBP"));
+        assert_eq!(currency_from("EUR 90k-140k").as_deref(), Some("EUR"));
+        assert_eq!(currency_from("90k-140k usd").as_deref(), Some("USD"));
+        assert_eq!(
+            currency_from("USDC 90k - USDC 140k").as_deref(),
+            Some("USDC")
+        );
+        assert_eq!(currency_from("CHF 120k").as_deref(), Some("CHF"));
+        assert_eq!(currency_from("90k - 140k"), None);
+        assert_eq!(currency_from(""), None);
+    }
+
+    #[test]
+    fn test_applicants_from() {
+        assert_eq!(applicants_from("57 applicants"), Some(57));
+        assert_eq!(applicants_from("Over 100 applicants"), Some(100));
+        assert_eq!(applicants_from("100+"), Some(100));
+        assert_eq!(applicants_from("1,204 views"), Some(1204));
+        assert_eq!(applicants_from("Be an early applicant"), None);
+    }
 
     #[test]
     fn test_web3careers_format_apply_url() {
@@ -211,6 +931,62 @@ mod tests {
             CryptoJobsList::format_remuneration_from("EUR 90k-140k"),
             "€90k - €140k"
         );
+        assert_eq!(CryptoJobsList::format_remuneration_from("$120k"), "$120k");
+        assert_eq!(CryptoJobsList::format_remuneration_from("EUR 95k"), "€95k");
+        assert_eq!(
+            CryptoJobsList::format_remuneration_from("up to $150k"),
+            "Up to $150k"
+        );
+        assert_eq!(
+            CryptoJobsList::format_remuneration_from("GBP 60k-80k"),
+            "£60k - £80k"
+        );
+        assert_eq!(
+            CryptoJobsList::format_remuneration_from("£ 60k - £80k"),
+            "£60k - £80k"
+        );
+        assert_eq!(
+            CryptoJobsList::format_remuneration_from("USDC 90k-140k"),
+            "USDC 90k - USDC 140k"
+        );
+        assert_eq!(
+            CryptoJobsList::format_remuneration_from("CHF 120k"),
+            "CHF 120k"
+        );
+        assert_eq!(
+            CryptoJobsList::format_remuneration_from("90k-140k"),
+            "90k - 140k"
+        );
+        assert_eq!(CryptoJobsList::format_remuneration_from(""), "");
+    }
+
+    #[test]
+    fn test_get_upper_lower() {
+        assert_eq!(
+            CryptoJobsList::get_upper_lower("$90k - $140k"),
+            (Some(90000), Some(140000))
+        );
+        assert_eq!(
+            CryptoJobsList::get_upper_lower("$120k"),
+            (Some(120000), Some(120000))
+        );
+        assert_eq!(
+            CryptoJobsList::get_upper_lower("€95k"),
+            (Some(95000), Some(95000))
+        );
+        assert_eq!(
+            CryptoJobsList::get_upper_lower("Up to $150k"),
+            (Some(150000), Some(150000))
+        );
+        assert_eq!(
+            CryptoJobsList::get_upper_lower("USDC 90k - USDC 140k"),
+            (Some(90000), Some(140000))
+        );
+        assert_eq!(
+            Web3Careers::get_upper_lower("$120k"),
+            (Some(120000), Some(120000))
+        );
+        assert_eq!(Web3Careers::get_upper_lower("Competitive"), (None, None));
     }
 
     #[test]
@@ -227,6 +1003,63 @@ mod tests {
             CryptoJobsList::format_date_from("2w"),
             CryptoJobsList::sub_duration_and_format(Duration::weeks(2))
         );
+        assert_eq!(
+            CryptoJobsList::format_date_from("12d"),
+            CryptoJobsList::sub_duration_and_format(Duration::days(12))
+        );
+        assert_eq!(
+            CryptoJobsList::format_date_from("2y"),
+            CryptoJobsList::sub_duration_and_format(Duration::days(730))
+        );
+    }
+
+    #[test]
+    fn test_format_elapsed() {
+        let elapsed = CryptoJobsList::format_date_from;
+        let ago = CryptoJobsList::sub_duration_and_format;
+        assert_eq!(elapsed("just now"), CryptoJobsList::now_and_format());
+        assert_eq!(elapsed("Today"), CryptoJobsList::now_and_format());
+        assert_eq!(elapsed("yesterday"), ago(Duration::days(1)));
+        assert_eq!(elapsed("about 1 hour ago"), ago(Duration::hours(1)));
+        assert_eq!(elapsed("about an hour ago"), ago(Duration::hours(1)));
+        assert_eq!(elapsed("5 minutes ago"), CryptoJobsList::now_and_format());
+        assert_eq!(elapsed("3 days ago"), ago(Duration::days(3)));
+        assert_eq!(elapsed("Posted 12 days ago"), ago(Duration::days(12)));
+        assert_eq!(elapsed("a week ago"), ago(Duration::weeks(1)));
+        assert_eq!(elapsed("over 2 years ago"), ago(Duration::days(730)));
+        assert_eq!(elapsed("3mo"), ago(Duration::days(90)));
+        assert_eq!(elapsed("unknown"), CryptoJobsList::now_and_format());
+    }
+
+    #[test]
+    fn test_format_deadline() {
+        let deadline = CryptoJobsList::format_deadline_from;
+        assert_eq!(
+            deadline("Closes in 5 days"),
+            Some(CryptoJobsList::add_duration_and_format(Duration::days(5)))
+        );
+        assert_eq!(
+            deadline("in 2 weeks"),
+            Some(CryptoJobsList::add_duration_and_format(Duration::weeks(2)))
+        );
+        assert_eq!(
+            deadline("Tomorrow"),
+            Some(CryptoJobsList::add_duration_and_format(Duration::days(1)))
+        );
+        assert_eq!(
+            deadline("2024-06-30T23:59:59Z").as_deref(),
+            Some("2024-06-30")
+        );
+        assert_eq!(
+            deadline("Apply by 30 June 2024").as_deref(),
+            Some("2024-06-30")
+        );
+        assert_eq!(
+            deadline("Deadline: Jun 30, 2024").as_deref(),
+            Some("2024-06-30")
+        );
+        assert_eq!(deadline("soon"), None);
+        assert_eq!(deadline(""), None);
     }
 
     #[test]